@@ -0,0 +1,117 @@
+//! Generates the built-in city table from `data/cities.csv`
+//!
+//! The CSV is parsed at compile time into a static Rust slice, so the server
+//! ships with the full dataset and does no file I/O at startup.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const CITIES_CSV: &str = "data/cities.csv";
+
+/// Expected header row, in order
+const COLUMNS: &[&str] = &[
+    "name",
+    "country",
+    "continent",
+    "latitude",
+    "longitude",
+    "timezone",
+    "temperature",
+    "condition",
+    "humidity",
+    "wind_speed",
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", CITIES_CSV);
+
+    let mut reader = csv::Reader::from_path(CITIES_CSV)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", CITIES_CSV, e));
+
+    let headers = reader.headers().expect("cities CSV has no header row");
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        COLUMNS,
+        "unexpected columns in {}",
+        CITIES_CSV
+    );
+
+    let mut table = String::from("&[\n");
+    let mut keys = std::collections::HashSet::new();
+
+    for (index, row) in reader.records().enumerate() {
+        let line = index + 2;
+        let row = row.unwrap_or_else(|e| panic!("{}:{}: {}", CITIES_CSV, line, e));
+        let field = |column: &str| {
+            let position = COLUMNS.iter().position(|c| *c == column).unwrap();
+            row[position].trim()
+        };
+        let number = |column: &str| -> f64 {
+            field(column)
+                .parse()
+                .unwrap_or_else(|e| panic!("{}:{}: invalid {}: {}", CITIES_CSV, line, column, e))
+        };
+        let integer = |column: &str| -> i32 {
+            field(column)
+                .parse()
+                .unwrap_or_else(|e| panic!("{}:{}: invalid {}: {}", CITIES_CSV, line, column, e))
+        };
+
+        let key = field("name").to_lowercase();
+        assert!(!key.is_empty(), "{}:{}: empty city name", CITIES_CSV, line);
+        assert!(
+            keys.insert(key.clone()),
+            "{}:{}: duplicate city '{}'",
+            CITIES_CSV,
+            line,
+            key
+        );
+
+        let latitude = number("latitude");
+        let longitude = number("longitude");
+        assert!(
+            (-90.0..=90.0).contains(&latitude),
+            "{}:{}: latitude out of range",
+            CITIES_CSV,
+            line
+        );
+        assert!(
+            (-180.0..=180.0).contains(&longitude),
+            "{}:{}: longitude out of range",
+            CITIES_CSV,
+            line
+        );
+        let humidity = integer("humidity");
+        assert!(
+            (0..=100).contains(&humidity),
+            "{}:{}: humidity out of range",
+            CITIES_CSV,
+            line
+        );
+
+        writeln!(
+            table,
+            "    BuiltinCity {{ key: {:?}, name: {:?}, country: {:?}, continent: {:?}, \
+             latitude: {:?}, longitude: {:?}, timezone: {:?}, temperature: {}, \
+             condition: {:?}, humidity: {}, wind_speed: {} }},",
+            key,
+            field("name"),
+            field("country"),
+            field("continent"),
+            latitude,
+            longitude,
+            field("timezone"),
+            integer("temperature"),
+            field("condition"),
+            humidity,
+            integer("wind_speed"),
+        )
+        .unwrap();
+    }
+    table.push(']');
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("builtin_cities.rs");
+    fs::write(&out, table).unwrap_or_else(|e| panic!("cannot write {}: {}", out.display(), e));
+}
@@ -0,0 +1,507 @@
+use axum::{
+    extract::{rejection::JsonRejection, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::analytics::UsageReport;
+use crate::cities::{CityDatabase, CityRecord, FieldError};
+use crate::events::{CityChange, UpdateEvent};
+use crate::{error_response, AppState, ErrorResponse};
+
+/// Version of the `/admin/snapshot` format, checked on restore
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Validation failure with one entry per offending field
+#[derive(Debug, Serialize)]
+pub struct ValidationErrorResponse {
+    error: String,
+    code: u16,
+    fields: Vec<FieldError>,
+}
+
+/// Error from an admin write, rendered as an [`ErrorResponse`] or a
+/// [`ValidationErrorResponse`]
+#[derive(Debug)]
+pub enum AdminError {
+    Status(StatusCode, String),
+    Validation(Vec<FieldError>),
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        match self {
+            AdminError::Status(status, message) => error_response(status, message).into_response(),
+            AdminError::Validation(fields) => {
+                println!(
+                    "⚠️  Admin: rejected payload with {} invalid field(s)",
+                    fields.len()
+                );
+                let status = StatusCode::UNPROCESSABLE_ENTITY;
+                let body = ValidationErrorResponse {
+                    error: "Validation failed".to_string(),
+                    code: status.as_u16(),
+                    fields,
+                };
+                (status, Json(body)).into_response()
+            }
+        }
+    }
+}
+
+/// The whole city database, as downloaded from `/admin/snapshot`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Snapshot {
+    version: u32,
+    taken_at: DateTime<Utc>,
+    /// Every city, including soft-deleted ones, sorted by name
+    cities: Vec<CityRecord>,
+    /// When each soft-deleted city was deleted, by lowercase name
+    #[serde(default)]
+    deleted: BTreeMap<String, DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    /// Number of cities in the ranking (default 10)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminCitiesQuery {
+    #[serde(default)]
+    include_deleted: bool,
+}
+
+/// Whether the request carries `Authorization: Bearer <admin token>`
+///
+/// Always false when no admin token is configured.
+pub fn has_admin_token(state: &AppState, headers: &HeaderMap) -> bool {
+    let expected = state.config.admin_token.as_deref().unwrap_or_default();
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    matches!(provided, Some(token) if !expected.is_empty() && constant_time_eq(token, expected))
+}
+
+/// Reject requests without `Authorization: Bearer <admin token>`
+pub async fn require_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if has_admin_token(&state, request.headers()) {
+        return next.run(request).await;
+    }
+    println!("🔒 Rejected admin request to {}", request.uri().path());
+    error_response(
+        StatusCode::UNAUTHORIZED,
+        "Missing or invalid admin token".to_string(),
+    )
+    .into_response()
+}
+
+/// List cities, optionally including soft-deleted ones
+pub async fn list_cities(
+    State(state): State<AppState>,
+    Query(query): Query<AdminCitiesQuery>,
+) -> impl IntoResponse {
+    println!(
+        "🛠️  Admin: listing cities (include_deleted={})",
+        query.include_deleted
+    );
+
+    let database = state.cities.current();
+    let mut cities: Vec<_> = database
+        .iter_all()
+        .filter(|(_, record)| query.include_deleted || record.deleted_at.is_none())
+        .map(|(key, record)| {
+            let mut entry = serde_json::to_value(record).unwrap_or_default();
+            entry["key"] = key.into();
+            entry["deleted"] = record.deleted_at.is_some().into();
+            (key.to_string(), entry)
+        })
+        .collect();
+    cities.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Json(serde_json::json!({
+        "count": cities.len(),
+        "cities": cities.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>()
+    }))
+}
+
+/// Create a new city
+pub async fn create_city(
+    State(state): State<AppState>,
+    payload: Result<Json<CityRecord>, JsonRejection>,
+) -> Result<(StatusCode, Json<CityRecord>), AdminError> {
+    let record = validated(payload, None)?;
+    let key = record.name.to_lowercase();
+
+    let created = state.cities.modify(|db| match db.get_any(&key) {
+        Some(existing) if existing.deleted_at.is_some() => Err(AdminError::Status(
+            StatusCode::CONFLICT,
+            format!("City '{}' exists but is deleted; restore it instead", key),
+        )),
+        Some(_) => Err(AdminError::Status(
+            StatusCode::CONFLICT,
+            format!("City '{}' already exists", key),
+        )),
+        None => Ok(db.upsert(&key, record.clone())),
+    })?;
+
+    println!("➕ Admin: created {}", key);
+    publish(&state, key, CityChange::Created);
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Replace an existing city's data
+///
+/// The body's `name` must match the city in the URL (case-insensitively);
+/// a soft-deleted city stays deleted.
+pub async fn update_city(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    payload: Result<Json<CityRecord>, JsonRejection>,
+) -> Result<Json<CityRecord>, AdminError> {
+    let key = name.to_lowercase();
+    let mut record = validated(payload, Some(&key))?;
+
+    let updated = state.cities.modify(|db| {
+        let existing = db.get_any(&key)?;
+        record.deleted_at = existing.deleted_at;
+        Some(db.upsert(&key, record))
+    });
+    let updated = updated.ok_or_else(|| {
+        AdminError::Status(StatusCode::NOT_FOUND, format!("Unknown city '{}'", name))
+    })?;
+
+    println!("✏️  Admin: updated {}", key);
+    publish(&state, key, CityChange::Updated);
+    Ok(Json(updated))
+}
+
+fn publish(state: &AppState, city: String, change: CityChange) {
+    state.events.publish(UpdateEvent::CityChanged {
+        city,
+        change,
+        at: Utc::now(),
+    });
+}
+
+/// Parse, normalize and validate an admin payload
+///
+/// `key` is the lowercase city name from the URL, if any, which the payload's
+/// name must match.
+fn validated(
+    payload: Result<Json<CityRecord>, JsonRejection>,
+    key: Option<&str>,
+) -> Result<CityRecord, AdminError> {
+    let Json(mut record) = payload
+        .map_err(|rejection| AdminError::Status(StatusCode::BAD_REQUEST, rejection.body_text()))?;
+    record.normalize();
+
+    let mut fields = record.validate().err().unwrap_or_default();
+    if let Some(key) = key {
+        if !record.name.is_empty() && record.name.to_lowercase() != key {
+            fields.push(FieldError {
+                field: "name",
+                message: format!("must match the city in the URL ('{}')", key),
+            });
+        }
+    }
+    if fields.is_empty() {
+        Ok(record)
+    } else {
+        Err(AdminError::Validation(fields))
+    }
+}
+
+/// Soft-delete a city: it disappears from public endpoints but can be restored
+pub async fn delete_city(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let key = name.to_lowercase();
+    let deleted_at = state
+        .cities
+        .modify(|db| db.soft_delete(&key).and_then(|record| record.deleted_at))
+        .ok_or_else(|| unknown_city(&name))?;
+
+    println!("🗑️  Admin: soft-deleted {}", key);
+    publish(&state, key.clone(), CityChange::Deleted);
+
+    Ok(Json(serde_json::json!({
+        "city": key,
+        "deleted": true,
+        "deleted_at": deleted_at.to_rfc3339()
+    })))
+}
+
+/// Restore a soft-deleted city
+pub async fn restore_city(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let key = name.to_lowercase();
+    match state.cities.modify(|db| db.restore(&key)) {
+        None => Err(unknown_city(&name)),
+        Some(false) => Err(error_response(
+            StatusCode::CONFLICT,
+            format!("City '{}' is not deleted", name),
+        )),
+        Some(true) => {
+            println!("♻️  Admin: restored {}", key);
+            publish(&state, key.clone(), CityChange::Restored);
+            Ok(Json(serde_json::json!({ "city": key, "deleted": false })))
+        }
+    }
+}
+
+/// Download the whole city database, soft deletes included
+pub async fn snapshot(State(state): State<AppState>) -> impl IntoResponse {
+    println!("🛠️  Admin: taking snapshot");
+
+    let database = state.cities.current();
+    let mut entries: Vec<(&str, &CityRecord)> = database.iter_all().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let taken_at = Utc::now();
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        taken_at,
+        deleted: entries
+            .iter()
+            .filter_map(|(key, record)| Some((key.to_string(), record.deleted_at?)))
+            .collect(),
+        cities: entries
+            .into_iter()
+            .map(|(_, record)| CityRecord {
+                deleted_at: None,
+                ..record.clone()
+            })
+            .collect(),
+    };
+    let filename = format!("cities-{}.json", taken_at.format("%Y%m%dT%H%M%SZ"));
+
+    (
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )],
+        Json(snapshot),
+    )
+}
+
+/// Replace the whole city database with a snapshot
+///
+/// Every city is validated first; nothing changes unless all of them pass.
+pub async fn restore_snapshot(
+    State(state): State<AppState>,
+    payload: Result<Json<Snapshot>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, AdminError> {
+    let Json(snapshot) = payload
+        .map_err(|rejection| AdminError::Status(StatusCode::BAD_REQUEST, rejection.body_text()))?;
+    let invalid = |message: String| AdminError::Status(StatusCode::UNPROCESSABLE_ENTITY, message);
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(invalid(format!(
+            "Unsupported snapshot version {} (expected {})",
+            snapshot.version, SNAPSHOT_VERSION
+        )));
+    }
+    let mut database = CityDatabase::from_records(snapshot.cities).map_err(|(entry, reason)| {
+        if entry == 0 {
+            invalid("Snapshot has no cities".to_string())
+        } else {
+            invalid(format!("Invalid city #{} in snapshot: {}", entry, reason))
+        }
+    })?;
+    for (key, deleted_at) in &snapshot.deleted {
+        if !database.set_deleted(&key.to_lowercase(), *deleted_at) {
+            return Err(invalid(format!(
+                "Deleted city '{}' is not in the snapshot",
+                key
+            )));
+        }
+    }
+
+    let cities = database.len();
+    let deleted = snapshot.deleted.len();
+    state.cities.restore(database);
+    state.events.publish(UpdateEvent::CitiesReplaced {
+        cities,
+        at: Utc::now(),
+    });
+    println!(
+        "♻️  Admin: restored snapshot from {} ({} cities, {} deleted)",
+        snapshot.taken_at.to_rfc3339(),
+        cities,
+        deleted
+    );
+
+    Ok(Json(serde_json::json!({
+        "restored": true,
+        "taken_at": snapshot.taken_at.to_rfc3339(),
+        "cities": cities,
+        "deleted": deleted
+    })))
+}
+
+/// Most requested cities and request volumes per hour
+pub async fn city_analytics(
+    State(state): State<AppState>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Json<UsageReport> {
+    println!("🛠️  Admin: city analytics");
+
+    Json(
+        state
+            .analytics
+            .report(query.limit.unwrap_or(10), Utc::now()),
+    )
+}
+
+/// Requests per API key against its quotas
+pub async fn api_key_usage(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, AdminError> {
+    println!("🛠️  Admin: API key usage");
+
+    let api_keys = state.api_keys.as_ref().ok_or_else(|| {
+        AdminError::Status(
+            StatusCode::NOT_FOUND,
+            "API keys are not enabled (start the server with --api-keys-file)".to_string(),
+        )
+    })?;
+    let keys = api_keys.report();
+
+    Ok(Json(serde_json::json!({
+        "count": keys.len(),
+        "keys": keys
+    })))
+}
+
+/// Runtime health: uptime, memory, cache, background jobs and connections
+pub async fn runtime_status(State(state): State<AppState>) -> Json<serde_json::Value> {
+    println!("🛠️  Admin: runtime status");
+
+    let now = Utc::now();
+    let cache = state.idempotency.stats();
+    let lookups = cache.hits + cache.misses;
+
+    // The sampler is stuck if it missed more than one interval
+    let interval = state.config.history_interval;
+    let last_sample = state.history.last_recorded();
+    let sampler_healthy =
+        last_sample.is_some_and(|at| (now - at).to_std().unwrap_or_default() <= interval * 2);
+    let subscribers = state.events.stats();
+
+    Json(serde_json::json!({
+        "started_at": state.started_at.to_rfc3339(),
+        "uptime_seconds": (now - state.started_at).num_seconds(),
+        "memory": {
+            "resident_bytes": resident_memory_bytes()
+        },
+        "idempotency_cache": {
+            "entries": cache.entries,
+            "hits": cache.hits,
+            "misses": cache.misses,
+            "hit_ratio": (lookups > 0).then(|| cache.hits as f64 / lookups as f64)
+        },
+        "jobs": {
+            "history_sampler": {
+                "healthy": sampler_healthy,
+                "interval_seconds": interval.as_secs_f64(),
+                "last_run": last_sample.map(|at| at.to_rfc3339())
+            },
+            "history_compactor": {
+                "interval_seconds": state.config.history_compaction_interval.as_secs_f64(),
+                "runs": state.history.compactions(),
+                "last_run": state.history.last_compaction().map(|at| at.to_rfc3339())
+            },
+            "cities_file_watcher": {
+                "enabled": state.config.cities_file.is_some(),
+                "last_reload": state.cities.last_reload().map(|at| at.to_rfc3339())
+            }
+        },
+        "connections": {
+            "active": state.connections.active(),
+            "total": state.connections.total(),
+            "max": state.connections.max(),
+            // `GET /events` streams, and events or subscribers dropped for being slow
+            "event_subscribers": {
+                "active": subscribers.active(),
+                "dropped_events": subscribers.dropped(),
+                "disconnected": subscribers.disconnected()
+            }
+        }
+    }))
+}
+
+/// Resident set size of the server process, where the OS exposes it
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+fn unknown_city(name: &str) -> (StatusCode, Json<ErrorResponse>) {
+    error_response(StatusCode::NOT_FOUND, format!("Unknown city '{}'", name))
+}
+
+/// Compare secrets without leaking how many leading bytes matched
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("s3cret", "s3cret"));
+        assert!(!constant_time_eq("s3cret", "s3cres"));
+        assert!(!constant_time_eq("s3cret", "s3cret!"));
+        assert!(!constant_time_eq("", "x"));
+    }
+
+    #[tokio::test]
+    async fn test_runtime_status_counts_event_subscribers() {
+        use crate::cities::{CityDatabase, CityStore};
+        use clap::Parser;
+
+        let state = AppState::new(
+            crate::config::ServerConfig::parse_from(["server"]),
+            std::sync::Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let subscribers =
+            |status: &serde_json::Value| status["connections"]["event_subscribers"].clone();
+
+        let Json(status) = runtime_status(State(state.clone())).await;
+        assert_eq!(
+            subscribers(&status),
+            serde_json::json!({"active": 0, "dropped_events": 0, "disconnected": 0})
+        );
+
+        let _stream = state.events.subscribe_queued(|_| true);
+        let Json(status) = runtime_status(State(state.clone())).await;
+        assert_eq!(subscribers(&status)["active"], 1);
+        assert!(status["connections"]["active"].is_number());
+    }
+}
@@ -0,0 +1,235 @@
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::RwLock;
+
+use crate::config::{AlertRule, Change};
+use crate::history::{Sample, WeatherHistory, METRICS};
+use crate::tenants::Scoped;
+
+/// A city whose reading changed faster than a rule allows
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub city: String,
+    /// The rule that fired, as configured (`temperature-5/3h`)
+    pub rule: String,
+    pub metric: &'static str,
+    /// The earlier reading the latest one is compared with
+    pub from: i32,
+    pub to: i32,
+    /// When the earlier reading was recorded
+    pub since: DateTime<Utc>,
+    pub at: DateTime<Utc>,
+}
+
+/// Outcome of the most recent evaluation
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AlertReport {
+    pub checked_at: Option<DateTime<Utc>>,
+    pub alerts: Vec<Alert>,
+}
+
+/// Evaluates `--alert-rule` delta conditions against the recorded history
+///
+/// Absolute thresholds miss fast-moving weather, so each rule compares a
+/// city's latest reading with the extreme of its earlier readings inside the
+/// rule's window: the highest for drops, the lowest for rises and factors.
+#[derive(Debug)]
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    latest: RwLock<AlertReport>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        AlertEngine {
+            rules,
+            latest: RwLock::new(AlertReport::default()),
+        }
+    }
+
+    /// Check every city against every rule, returning the alerts that were
+    /// not already firing in the previous evaluation
+    pub fn evaluate(&self, history: &WeatherHistory) -> Vec<Alert> {
+        let mut alerts: Vec<Alert> = history
+            .all()
+            .iter()
+            .flat_map(|(city, samples)| {
+                self.rules
+                    .iter()
+                    .filter_map(move |rule| check(rule, city, samples))
+            })
+            .collect();
+        alerts.sort_by(|a, b| a.city.cmp(&b.city).then_with(|| a.rule.cmp(&b.rule)));
+
+        for alert in &alerts {
+            println!(
+                "🚨 Alert {}: {} {} went from {} to {} since {}",
+                alert.rule,
+                alert.city,
+                alert.metric,
+                alert.from,
+                alert.to,
+                alert.since.format("%H:%M")
+            );
+        }
+
+        let mut latest = self.latest.write().unwrap_or_else(|e| e.into_inner());
+        let fired: Vec<Alert> = alerts
+            .iter()
+            .filter(|alert| {
+                !latest
+                    .alerts
+                    .iter()
+                    .any(|firing| firing.city == alert.city && firing.rule == alert.rule)
+            })
+            .cloned()
+            .collect();
+        *latest = AlertReport {
+            checked_at: Some(Utc::now()),
+            alerts,
+        };
+        fired
+    }
+
+    pub fn latest(&self) -> AlertReport {
+        self.latest
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+fn check(rule: &AlertRule, city: &str, samples: &[Sample]) -> Option<Alert> {
+    let (latest, earlier) = samples.split_last()?;
+    let value_of = METRICS.iter().find(|(name, _)| *name == rule.metric)?.1;
+    // A window reaching past the earliest date covers the whole history
+    let start = chrono::Duration::from_std(rule.window)
+        .ok()
+        .and_then(|window| latest.at.checked_sub_signed(window))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let in_window = earlier.iter().filter(|s| s.at >= start);
+    let to = value_of(latest);
+
+    let baseline = match rule.change {
+        Change::Drop(amount) => in_window
+            .max_by_key(|s| value_of(s))
+            .filter(|s| (value_of(s) - to) as f64 > amount),
+        Change::Rise(amount) => in_window
+            .min_by_key(|s| value_of(s))
+            .filter(|s| (to - value_of(s)) as f64 > amount),
+        // A zero reading (calm wind) has no multiple to compare with
+        Change::Factor(factor) => in_window
+            .filter(|s| value_of(s) > 0)
+            .min_by_key(|s| value_of(s))
+            .filter(|s| to as f64 >= value_of(s) as f64 * factor),
+    }?;
+
+    Some(Alert {
+        city: city.to_string(),
+        rule: rule.spec.clone(),
+        metric: rule.metric,
+        from: value_of(baseline),
+        to,
+        since: baseline.at,
+        at: latest.at,
+    })
+}
+
+/// Rate-of-change alerts firing in the most recent evaluation
+pub async fn get_alerts(Scoped(state): Scoped) -> impl IntoResponse {
+    println!("🚨 Received alerts request");
+
+    let mut report = state.alerts.latest();
+    // The history covers global cities only
+    report.alerts.retain(|alert| state.shares(&alert.city));
+    let rules: Vec<&str> = state.alerts.rules.iter().map(|r| r.spec.as_str()).collect();
+    Json(serde_json::json!({
+        "rules": rules,
+        "checked_at": report.checked_at.map(|at| at.to_rfc3339()),
+        "count": report.alerts.len(),
+        "alerts": report.alerts
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::Condition;
+    use crate::WeatherData;
+    use std::time::Duration;
+
+    fn rule(spec: &str, metric: &'static str, change: Change, hours: u64) -> AlertRule {
+        AlertRule {
+            spec: spec.to_string(),
+            metric,
+            change,
+            window: Duration::from_secs(hours * 3600),
+        }
+    }
+
+    fn samples(start: DateTime<Utc>, readings: &[(i32, i32)]) -> Vec<Sample> {
+        readings
+            .iter()
+            .enumerate()
+            .map(|(hour, &(temperature, wind_speed))| Sample {
+                at: start + chrono::Duration::hours(hour as i64),
+                temperature,
+                humidity: 50,
+                wind_speed,
+                condition: Condition::Clear,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_delta_rules() {
+        let start = Utc::now();
+        let drop = rule("temperature-5/3h", "temperature", Change::Drop(5.0), 3);
+        let doubled = rule("wind_speed*2/1h", "wind_speed", Change::Factor(2.0), 1);
+
+        // 16 -> 10 within three hours, wind 10 -> 20 in the last hour
+        let fast = samples(start, &[(12, 5), (16, 8), (14, 10), (12, 10), (10, 20)]);
+        let alert = check(&drop, "oslo", &fast).unwrap();
+        assert_eq!((alert.from, alert.to), (16, 10));
+        assert_eq!(alert.since, start + chrono::Duration::hours(1));
+        assert_eq!(check(&doubled, "oslo", &fast).unwrap().from, 10);
+
+        // The same 6° drop spread over five hours is not an alert
+        let slow = samples(
+            start,
+            &[(16, 10), (15, 10), (14, 10), (13, 10), (12, 10), (10, 15)],
+        );
+        assert!(check(&drop, "oslo", &slow).is_none());
+        assert!(check(&doubled, "oslo", &slow).is_none());
+
+        let rise = rule("temperature+5/3h", "temperature", Change::Rise(5.0), 3);
+        assert!(check(&rise, "oslo", &fast).is_none());
+        assert!(check(&drop, "oslo", &fast[..1]).is_none());
+
+        let endless = AlertRule {
+            window: crate::duration::parse_duration("99999999d").unwrap(),
+            ..rule(
+                "temperature-5/99999999d",
+                "temperature",
+                Change::Drop(5.0),
+                0,
+            )
+        };
+        assert_eq!(check(&endless, "oslo", &slow).unwrap().from, 16);
+
+        // Evaluating again only reports alerts that were not firing already
+        let engine = AlertEngine::new(vec![drop]);
+        let history = WeatherHistory::new(100, crate::history::Retention::default());
+        for sample in &fast {
+            let reading = WeatherData {
+                temperature: sample.temperature,
+                ..WeatherData::unknown("oslo")
+            };
+            history.record(&[reading], sample.at);
+        }
+        assert_eq!(engine.evaluate(&history).len(), 1);
+        assert!(engine.evaluate(&history).is_empty());
+        assert_eq!(engine.latest().alerts.len(), 1);
+    }
+}
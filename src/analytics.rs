@@ -0,0 +1,170 @@
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Hourly request volumes kept for the report
+const HOURS_KEPT: usize = 24;
+
+/// Requests for one city
+#[derive(Debug, Clone, Serialize)]
+pub struct CityUsage {
+    pub city: String,
+    /// Requests since the server started
+    pub requests: u64,
+    /// Requests in the last 24 hours
+    pub last_24h: u64,
+}
+
+/// Requests for all cities in one clock hour
+#[derive(Debug, Clone, Serialize)]
+pub struct HourlyVolume {
+    pub hour: DateTime<Utc>,
+    pub requests: u64,
+}
+
+/// Snapshot of the usage counters
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub since: DateTime<Utc>,
+    pub total_requests: u64,
+    /// Requests for cities not in the database, counted but not broken down
+    pub unknown_city_requests: u64,
+    /// Most requested cities, most requested first
+    pub top: Vec<CityUsage>,
+    /// Volume per hour over the last 24 hours, oldest first
+    pub hourly: Vec<HourlyVolume>,
+}
+
+#[derive(Debug)]
+struct HourBucket {
+    hour: DateTime<Utc>,
+    requests: u64,
+    cities: HashMap<String, u64>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    totals: HashMap<String, u64>,
+    unknown: u64,
+    hourly: VecDeque<HourBucket>,
+}
+
+/// How often each city is requested, in memory since startup
+#[derive(Debug)]
+pub struct CityAnalytics {
+    since: DateTime<Utc>,
+    counters: Mutex<Counters>,
+}
+
+impl CityAnalytics {
+    pub fn new() -> Self {
+        CityAnalytics {
+            since: Utc::now(),
+            counters: Mutex::new(Counters::default()),
+        }
+    }
+
+    /// Count one request for a known city (lowercase name), or for an
+    /// unknown one when `city` is `None`
+    ///
+    /// Unknown names are not stored, so clients cannot grow the counters
+    /// without bound.
+    pub fn record(&self, city: Option<&str>, at: DateTime<Utc>) {
+        let hour = at.duration_trunc(TimeDelta::hours(1)).unwrap_or(at);
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+
+        if counters.hourly.back().map(|bucket| bucket.hour) != Some(hour) {
+            if counters.hourly.len() == HOURS_KEPT {
+                counters.hourly.pop_front();
+            }
+            counters.hourly.push_back(HourBucket {
+                hour,
+                requests: 0,
+                cities: HashMap::new(),
+            });
+        }
+        if let Some(bucket) = counters.hourly.back_mut() {
+            bucket.requests += 1;
+            if let Some(city) = city {
+                *bucket.cities.entry(city.to_string()).or_default() += 1;
+            }
+        }
+
+        match city {
+            Some(city) => *counters.totals.entry(city.to_string()).or_default() += 1,
+            None => counters.unknown += 1,
+        }
+    }
+
+    /// The `limit` most requested cities and the hourly volumes
+    pub fn report(&self, limit: usize, now: DateTime<Utc>) -> UsageReport {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let day_ago = now - TimeDelta::hours(24);
+        let recent: Vec<&HourBucket> = counters
+            .hourly
+            .iter()
+            .filter(|bucket| bucket.hour > day_ago)
+            .collect();
+
+        let mut top: Vec<CityUsage> = counters
+            .totals
+            .iter()
+            .map(|(city, requests)| CityUsage {
+                city: city.clone(),
+                requests: *requests,
+                last_24h: recent.iter().filter_map(|b| b.cities.get(city)).sum(),
+            })
+            .collect();
+        top.sort_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.city.cmp(&b.city))
+        });
+        top.truncate(limit);
+
+        UsageReport {
+            since: self.since,
+            total_requests: counters.totals.values().sum::<u64>() + counters.unknown,
+            unknown_city_requests: counters.unknown,
+            top,
+            hourly: recent
+                .iter()
+                .map(|bucket| HourlyVolume {
+                    hour: bucket.hour,
+                    requests: bucket.requests,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_ranks_cities_and_buckets_by_hour() {
+        let analytics = CityAnalytics::new();
+        let now = Utc::now();
+        let yesterday = now - TimeDelta::hours(30);
+
+        analytics.record(Some("oslo"), yesterday);
+        analytics.record(Some("paris"), now);
+        analytics.record(Some("oslo"), now);
+        analytics.record(Some("oslo"), now);
+        analytics.record(None, now);
+
+        let report = analytics.report(1, now);
+        assert_eq!(report.total_requests, 5);
+        assert_eq!(report.unknown_city_requests, 1);
+        assert_eq!(report.top.len(), 1);
+        assert_eq!(
+            (report.top[0].city.as_str(), report.top[0].requests),
+            ("oslo", 3)
+        );
+        assert_eq!(report.top[0].last_24h, 2);
+        assert_eq!(report.hourly.len(), 1);
+        assert_eq!(report.hourly[0].requests, 4);
+    }
+}
@@ -0,0 +1,194 @@
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::RwLock;
+
+use crate::history::{Sample, WeatherHistory, METRICS};
+use crate::rounding::TemperatureRounding;
+use crate::tenants::Scoped;
+
+/// Earlier samples a city needs before its latest reading is judged
+const MIN_BASELINE_SAMPLES: usize = 12;
+
+/// A reading far outside the city's recent range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub city: String,
+    /// `temperature`, `humidity` or `wind_speed`
+    pub metric: &'static str,
+    pub value: i32,
+    /// Mean of the earlier samples
+    pub mean: f64,
+    /// Standard deviation of the earlier samples
+    pub stddev: f64,
+    /// Distance from the mean in standard deviations (signed)
+    pub z_score: f64,
+    pub at: DateTime<Utc>,
+}
+
+/// Outcome of the most recent analysis
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AnomalyReport {
+    pub checked_at: Option<DateTime<Utc>>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// Flags readings more than `threshold` standard deviations from the
+/// city's mean over the recorded history
+#[derive(Debug)]
+pub struct AnomalyDetector {
+    threshold: f64,
+    /// Applied to temperature means
+    rounding: TemperatureRounding,
+    latest: RwLock<AnomalyReport>,
+}
+
+impl AnomalyDetector {
+    pub fn new(threshold: f64, rounding: TemperatureRounding) -> Self {
+        AnomalyDetector {
+            threshold,
+            rounding,
+            latest: RwLock::new(AnomalyReport::default()),
+        }
+    }
+
+    /// Check every city's latest sample against its earlier ones, returning
+    /// the anomalies found
+    pub fn analyze(&self, history: &WeatherHistory) -> Vec<Anomaly> {
+        let mut anomalies: Vec<Anomaly> = history
+            .all()
+            .iter()
+            .flat_map(|(city, samples)| self.detect(city, samples))
+            .collect();
+        anomalies.sort_by(|a, b| b.z_score.abs().total_cmp(&a.z_score.abs()));
+
+        for anomaly in &anomalies {
+            println!(
+                "⚠️  Anomaly: {} {} {} ({:+.1}σ from mean {:.1})",
+                anomaly.city, anomaly.metric, anomaly.value, anomaly.z_score, anomaly.mean
+            );
+        }
+
+        *self.latest.write().unwrap_or_else(|e| e.into_inner()) = AnomalyReport {
+            checked_at: Some(Utc::now()),
+            anomalies: anomalies.clone(),
+        };
+        anomalies
+    }
+
+    fn detect(&self, city: &str, samples: &[Sample]) -> Vec<Anomaly> {
+        let Some((latest, baseline)) = samples.split_last() else {
+            return Vec::new();
+        };
+        if baseline.len() < MIN_BASELINE_SAMPLES {
+            return Vec::new();
+        }
+
+        METRICS
+            .iter()
+            .filter_map(|&(metric, value_of)| {
+                let values: Vec<f64> = baseline.iter().map(|s| value_of(s) as f64).collect();
+                let mean = values.iter().sum::<f64>() / values.len() as f64;
+                let variance =
+                    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+                let stddev = variance.sqrt();
+                // A flat history (static data) has nothing to deviate from
+                if stddev == 0.0 {
+                    return None;
+                }
+
+                let value = value_of(latest);
+                let z_score = (value as f64 - mean) / stddev;
+                (z_score.abs() > self.threshold).then(|| Anomaly {
+                    city: city.to_string(),
+                    metric,
+                    value,
+                    mean: if metric == "temperature" {
+                        self.rounding.round(mean)
+                    } else {
+                        (mean * 10.0).round() / 10.0
+                    },
+                    stddev: (stddev * 100.0).round() / 100.0,
+                    z_score: (z_score * 100.0).round() / 100.0,
+                    at: latest.at,
+                })
+            })
+            .collect()
+    }
+
+    pub fn latest(&self) -> AnomalyReport {
+        self.latest
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Readings flagged by the most recent anomaly analysis
+pub async fn get_anomalies(Scoped(state): Scoped) -> impl IntoResponse {
+    println!("🔎 Received anomalies request");
+
+    let mut report = state.anomalies.latest();
+    // The history covers global cities only
+    report
+        .anomalies
+        .retain(|anomaly| state.shares(&anomaly.city));
+    Json(serde_json::json!({
+        "threshold_sigma": state.anomalies.threshold,
+        "checked_at": report.checked_at.map(|at| at.to_rfc3339()),
+        "count": report.anomalies.len(),
+        "anomalies": report.anomalies
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Retention;
+    use crate::WeatherData;
+
+    #[test]
+    fn test_flags_outliers_only() {
+        let history = WeatherHistory::new(100, Retention::default());
+        for i in 0..20 {
+            let temperature = if i % 2 == 0 { 10 } else { 12 };
+            let readings = [
+                WeatherData {
+                    temperature,
+                    ..WeatherData::unknown("oslo")
+                },
+                WeatherData {
+                    temperature,
+                    ..WeatherData::unknown("rome")
+                },
+            ];
+            history.record(&readings, Utc::now());
+        }
+        history.record(
+            &[
+                WeatherData {
+                    temperature: 25,
+                    ..WeatherData::unknown("oslo")
+                },
+                WeatherData {
+                    temperature: 12,
+                    ..WeatherData::unknown("rome")
+                },
+            ],
+            Utc::now(),
+        );
+
+        let detector = AnomalyDetector::new(3.0, TemperatureRounding::default());
+        let found = detector.analyze(&history);
+        let report = detector.latest();
+        assert_eq!(found, report.anomalies);
+
+        assert_eq!(report.anomalies.len(), 1);
+        let anomaly = &report.anomalies[0];
+        assert_eq!(
+            (anomaly.city.as_str(), anomaly.metric, anomaly.value),
+            ("oslo", "temperature", 25)
+        );
+        assert!(anomaly.z_score > 3.0);
+    }
+}
@@ -0,0 +1,443 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::admin::constant_time_eq;
+use crate::cities::CityDatabase;
+use crate::tenants::TenantCities;
+use crate::{error_response, AppState};
+
+/// Request header carrying the client's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// The authenticated key, added to the request extensions for handlers
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// The key's name from `--api-keys-file`
+    pub name: String,
+    /// The key's own cities, if it has any
+    pub tenant: Option<Arc<TenantCities>>,
+}
+
+/// One key from `--api-keys-file`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    /// Name shown in usage reports and logs instead of the key itself
+    pub name: String,
+    pub key: String,
+    /// Requests allowed per UTC day (unlimited when unset)
+    pub daily_quota: Option<u64>,
+    /// Requests allowed per UTC calendar month (unlimited when unset)
+    pub monthly_quota: Option<u64>,
+    /// Global cities this key sees (all when unset)
+    #[serde(default)]
+    pub cities: Option<Vec<String>>,
+    /// Cities only this key sees, replacing global ones of the same name;
+    /// a JSON or CSV file like `--cities-file`, relative to the keys file
+    #[serde(default)]
+    pub cities_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeysFile {
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+}
+
+#[derive(Debug)]
+pub enum ApiKeysError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ApiKeysError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiKeysError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ApiKeysError::Parse(path, e) => {
+                write!(f, "invalid API keys file {}: {}", path.display(), e)
+            }
+            ApiKeysError::Invalid(path, reason) => {
+                write!(f, "invalid API keys file {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl Error for ApiKeysError {}
+
+/// Consumption of one key in the current day and month
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    day: NaiveDate,
+    today: u64,
+    month: (i32, u32),
+    this_month: u64,
+    total: u64,
+    last_used: DateTime<Utc>,
+}
+
+/// The quota closest to running out, reported in response headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct QuotaStatus {
+    limit: u64,
+    remaining: u64,
+    /// When the quota's period ends
+    reset: DateTime<Utc>,
+}
+
+/// Usage of one key, as reported by `GET /admin/usage`
+#[derive(Debug, Serialize)]
+pub struct KeyUsage {
+    name: String,
+    daily_quota: Option<u64>,
+    monthly_quota: Option<u64>,
+    today: u64,
+    this_month: u64,
+    total: u64,
+    last_used: Option<DateTime<Utc>>,
+}
+
+/// Known API keys and their request counts since startup
+#[derive(Debug)]
+pub struct ApiKeys {
+    keys: Vec<ApiKey>,
+    usage: Mutex<HashMap<String, Usage>>,
+    /// City sets of the keys that have `cities` or `cities_file`, by name
+    tenants: HashMap<String, Arc<TenantCities>>,
+}
+
+impl ApiKeys {
+    pub fn load(path: &Path) -> Result<Self, ApiKeysError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| ApiKeysError::Read(path.into(), e))?;
+        let file: ApiKeysFile =
+            toml::from_str(&contents).map_err(|e| ApiKeysError::Parse(path.into(), e))?;
+        let mut keys =
+            Self::new(file.keys).map_err(|reason| ApiKeysError::Invalid(path.into(), reason))?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for key in &keys.keys {
+            if key.cities.is_none() && key.cities_file.is_none() {
+                continue;
+            }
+            let overrides = match &key.cities_file {
+                Some(file) => Some(CityDatabase::load(&dir.join(file)).map_err(|e| {
+                    ApiKeysError::Invalid(path.into(), format!("key '{}': {}", key.name, e))
+                })?),
+                None => None,
+            };
+            let tenant = TenantCities::new(key.cities.clone(), overrides);
+            keys.tenants.insert(key.name.clone(), Arc::new(tenant));
+        }
+        Ok(keys)
+    }
+
+    fn new(keys: Vec<ApiKey>) -> Result<Self, String> {
+        if keys.is_empty() {
+            return Err("no keys defined".to_string());
+        }
+        let mut names = HashSet::new();
+        let mut secrets = HashSet::new();
+        for key in &keys {
+            if key.name.trim().is_empty() || key.key.is_empty() {
+                return Err("every key needs a non-empty name and key".to_string());
+            }
+            if !names.insert(key.name.as_str()) {
+                return Err(format!("duplicate key name '{}'", key.name));
+            }
+            if !secrets.insert(key.key.as_str()) {
+                return Err(format!("key '{}' reuses another key's secret", key.name));
+            }
+        }
+
+        Ok(ApiKeys {
+            keys,
+            usage: Mutex::new(HashMap::new()),
+            tenants: HashMap::new(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// The own cities of the key with this name, if it has any
+    pub fn tenant(&self, name: &str) -> Option<Arc<TenantCities>> {
+        self.tenants.get(name).cloned()
+    }
+
+    /// Names of the keys with their own cities, with the tenant's cities, sorted
+    pub fn tenants(&self) -> Vec<(&str, &TenantCities)> {
+        let mut tenants: Vec<_> = self
+            .tenants
+            .iter()
+            .map(|(name, tenant)| (name.as_str(), tenant.as_ref()))
+            .collect();
+        tenants.sort_by_key(|(name, _)| *name);
+        tenants
+    }
+
+    /// The key matching a secret sent by a client
+    fn find(&self, secret: &str) -> Option<&ApiKey> {
+        // Check every key so the timing does not reveal which one matched
+        self.keys.iter().fold(None, |found, key| {
+            if constant_time_eq(secret, &key.key) {
+                Some(key)
+            } else {
+                found
+            }
+        })
+    }
+
+    /// Count a request against the key's quotas
+    ///
+    /// Returns the quota closest to running out (`None` if the key is
+    /// unlimited), or the exhausted quota's status without counting the
+    /// request.
+    fn consume(
+        &self,
+        key: &ApiKey,
+        now: DateTime<Utc>,
+    ) -> Result<Option<QuotaStatus>, QuotaStatus> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = usage.entry(key.name.clone()).or_insert(Usage {
+            day: now.date_naive(),
+            today: 0,
+            month: (now.year(), now.month()),
+            this_month: 0,
+            total: 0,
+            last_used: now,
+        });
+        if usage.day != now.date_naive() {
+            usage.day = now.date_naive();
+            usage.today = 0;
+        }
+        if usage.month != (now.year(), now.month()) {
+            usage.month = (now.year(), now.month());
+            usage.this_month = 0;
+        }
+
+        let quotas = [
+            key.daily_quota
+                .map(|limit| (limit, usage.today, next_day(now))),
+            key.monthly_quota
+                .map(|limit| (limit, usage.this_month, next_month(now))),
+        ];
+        if let Some((limit, _, reset)) = quotas
+            .iter()
+            .flatten()
+            .find(|(limit, used, _)| used >= limit)
+        {
+            return Err(QuotaStatus {
+                limit: *limit,
+                remaining: 0,
+                reset: *reset,
+            });
+        }
+
+        usage.today += 1;
+        usage.this_month += 1;
+        usage.total += 1;
+        usage.last_used = now;
+
+        Ok(quotas
+            .iter()
+            .flatten()
+            .map(|(limit, used, reset)| QuotaStatus {
+                limit: *limit,
+                remaining: limit - used - 1,
+                reset: *reset,
+            })
+            .min_by_key(|status| status.remaining))
+    }
+
+    /// Consumption of every key, by name
+    pub fn report(&self) -> Vec<KeyUsage> {
+        let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let today = Utc::now();
+        let mut report: Vec<KeyUsage> = self
+            .keys
+            .iter()
+            .map(|key| {
+                let usage = usage.get(&key.name);
+                KeyUsage {
+                    name: key.name.clone(),
+                    daily_quota: key.daily_quota,
+                    monthly_quota: key.monthly_quota,
+                    today: usage
+                        .filter(|u| u.day == today.date_naive())
+                        .map_or(0, |u| u.today),
+                    this_month: usage
+                        .filter(|u| u.month == (today.year(), today.month()))
+                        .map_or(0, |u| u.this_month),
+                    total: usage.map_or(0, |u| u.total),
+                    last_used: usage.map(|u| u.last_used),
+                }
+            })
+            .collect();
+        report.sort_by(|a, b| a.name.cmp(&b.name));
+        report
+    }
+}
+
+fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive().succ_opt().unwrap_or(now.date_naive());
+    Utc.from_utc_datetime(&tomorrow.and_time(Default::default()))
+}
+
+fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = match now.month() {
+        12 => (now.year() + 1, 1),
+        month => (now.year(), month + 1),
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+/// Middleware requiring a known `X-API-Key` and enforcing its quotas
+///
+/// Does nothing unless `--api-keys-file` is set. The health check and the
+/// readiness probe stay open so load balancers can probe the server without
+/// a key.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(api_keys) = &state.api_keys else {
+        return next.run(request).await;
+    };
+    if matches!(request.uri().path(), "/" | "/ready") {
+        return next.run(request).await;
+    }
+
+    let key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|secret| api_keys.find(secret));
+    let Some(key) = key else {
+        println!(
+            "🔒 Rejected request to {} without a valid API key",
+            request.uri().path()
+        );
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid API key (send it as X-API-Key)".to_string(),
+        )
+        .into_response();
+    };
+
+    let now = Utc::now();
+    match api_keys.consume(key, now) {
+        Ok(status) => {
+            request.extensions_mut().insert(Caller {
+                name: key.name.clone(),
+                tenant: api_keys.tenant(&key.name),
+            });
+            let mut response = next.run(request).await;
+            if let Some(status) = status {
+                quota_headers(response.headers_mut(), status, now);
+            }
+            response
+        }
+        Err(status) => {
+            println!(
+                "🚫 Quota of {} exhausted ({} requests)",
+                key.name, status.limit
+            );
+            let mut response = error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Quota of {} requests exceeded for API key '{}'; resets at {}",
+                    status.limit,
+                    key.name,
+                    status.reset.to_rfc3339()
+                ),
+            )
+            .into_response();
+            let headers = response.headers_mut();
+            quota_headers(headers, status, now);
+            headers.insert(
+                "retry-after",
+                HeaderValue::from(seconds_until(status.reset, now)),
+            );
+            response
+        }
+    }
+}
+
+fn quota_headers(headers: &mut HeaderMap, status: QuotaStatus, now: DateTime<Utc>) {
+    headers.insert("x-quota-limit", HeaderValue::from(status.limit));
+    headers.insert("x-quota-remaining", HeaderValue::from(status.remaining));
+    headers.insert(
+        "x-quota-reset",
+        HeaderValue::from(seconds_until(status.reset, now)),
+    );
+}
+
+fn seconds_until(reset: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    (reset - now).num_seconds().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str, daily: Option<u64>, monthly: Option<u64>) -> ApiKey {
+        ApiKey {
+            name: name.to_string(),
+            key: format!("{}-secret", name),
+            daily_quota: daily,
+            monthly_quota: monthly,
+            cities: None,
+            cities_file: None,
+        }
+    }
+
+    #[test]
+    fn test_quotas_reset_with_their_period() {
+        let keys = ApiKeys::new(vec![key("app", Some(2), Some(3))]).unwrap();
+        let app = keys.find("app-secret").unwrap().clone();
+        let day1 = Utc.with_ymd_and_hms(2026, 1, 31, 10, 0, 0).unwrap();
+        let day2 =
+            Utc.with_ymd_and_hms(2026, 1, 31, 23, 0, 0).unwrap() + chrono::Duration::hours(2);
+
+        assert_eq!(keys.consume(&app, day1).unwrap().unwrap().remaining, 1);
+        assert_eq!(keys.consume(&app, day1).unwrap().unwrap().remaining, 0);
+        let exceeded = keys.consume(&app, day1).unwrap_err();
+        assert_eq!(
+            exceeded.reset,
+            Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap()
+        );
+
+        // A new day and a new month
+        assert_eq!(keys.consume(&app, day2).unwrap().unwrap().remaining, 1);
+        assert_eq!(keys.report()[0].total, 3);
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_duplicate_keys() {
+        let keys = ApiKeys::new(vec![key("a", None, None), key("b", None, None)]).unwrap();
+        assert!(keys.find("nope").is_none());
+        assert_eq!(keys.find("b-secret").map(|k| k.name.as_str()), Some("b"));
+
+        assert!(ApiKeys::new(vec![key("a", None, None), key("a", None, None)]).is_err());
+        assert!(ApiKeys::new(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use axum::{error_handling::HandleErrorLayer, middleware, Extension, Router};
+use std::sync::Arc;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::ServiceBuilder;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::services::{ServeDir, ServeFile};
+use tower_http::trace::TraceLayer;
+
+#[cfg(feature = "dashboard")]
+use crate::dashboard;
+#[cfg(feature = "geoip")]
+use crate::geoip;
+use crate::manifest::{self, delete, get, post, put, Auth, Manifest, Routes};
+use crate::state::AppState;
+use crate::{
+    admin, alerts, anomalies, api_keys, bulk, chaos, diff, digests, events, favorites, history,
+    idempotency, locale, mcp_api, mcp_session, normals, ranking, regions, request_log, signing,
+    stations, streaks, sync, timeouts, trace, warmup, webhooks,
+};
+use crate::{
+    get_cities, get_nearby, get_stats, get_weather, get_weather_by_query, health_check,
+    method_not_allowed, metrics, not_found, overloaded,
+};
+
+/// The whole API (routes, middleware and state) as one `Router`, with the
+/// manifest of its endpoints
+///
+/// A `Router` is a tower `Service`, so besides `serve::serve` it can be
+/// mounted in any tower-compatible host, or driven one request at a time
+/// without a listener (see the `lambda` feature). Which routes exist
+/// depends on the configuration in `state`, as for the server. Building it
+/// starts nothing: the digest scheduler and the webhook dispatcher are
+/// started by [`spawn_jobs`].
+pub fn router(state: AppState) -> (Router, Arc<Manifest>) {
+    let config = state.config.clone();
+    let public = if state.api_keys.is_some() {
+        Auth::ApiKey
+    } else {
+        Auth::None
+    };
+
+    let mut mcp_tools = Routes::new(public)
+        .route(
+            "/mcp/tool/weather_info",
+            [post(mcp_api::weather_info_mcp, "MCP weather tool")],
+        )
+        .route(
+            "/mcp/tool/compare_weather",
+            [post(
+                mcp_api::compare_weather_mcp,
+                "MCP weather comparison tool",
+            )],
+        );
+    if config.sign_mcp {
+        mcp_tools = mcp_tools.map(|router| {
+            router.route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                signing::sign_response,
+            ))
+        });
+    }
+
+    // Build our application with routes
+    let mut app = Routes::new(public)
+        // Standard API routes
+        .open_route("/", [get(health_check, "Health check")])
+        .open_route("/ready", [get(warmup::ready, "Readiness probe")])
+        .route(
+            "/weather",
+            [
+                get(
+                    get_weather_by_query,
+                    "Get weather info (?cities=stockholm,paris)",
+                ),
+                post(get_weather, "Get weather info"),
+            ],
+        )
+        .route(
+            "/weather/nearby",
+            [get(
+                get_nearby,
+                "Weather for cities around a point (?lat=&lon=&radius_km=)",
+            )],
+        )
+        .route(
+            "/weather/all",
+            [get(
+                bulk::get_all_weather,
+                "Weather for every city (?limit=&offset=)",
+            )],
+        )
+        .route(
+            "/sync",
+            [get(
+                sync::get_sync,
+                "Cities changed since the last sync (?since=)",
+            )],
+        )
+        .route(
+            "/events",
+            [get(
+                events::stream_events,
+                "Server-sent stream of data updates",
+            )],
+        )
+        .route("/stats", [get(get_stats, "Weather statistics")])
+        .route(
+            "/stats/regions",
+            [get(
+                regions::get_region_stats,
+                "Statistics by continent and country",
+            )],
+        )
+        .route("/cities", [get(get_cities, "List all cities")])
+        .route(
+            "/metrics",
+            [get(metrics, "Connection metrics (Prometheus format)")],
+        )
+        .route(
+            "/anomalies",
+            [get(
+                anomalies::get_anomalies,
+                "Unusual readings in the recorded history",
+            )],
+        )
+        .route(
+            "/alerts",
+            [get(
+                alerts::get_alerts,
+                "Fast changes in the recorded history",
+            )],
+        )
+        .route(
+            "/rank",
+            [get(
+                ranking::get_rank,
+                "Cities ranked by closeness to ideal weather",
+            )],
+        )
+        .route(
+            "/normals/:city",
+            [get(
+                normals::get_normals,
+                "Monthly climate normals (?month=1-12)",
+            )],
+        )
+        .route(
+            "/history/:city",
+            [get(
+                history::get_history,
+                "Recorded history (?resolution=raw|hourly|daily)",
+            )],
+        )
+        .route(
+            "/conditions/:city/streak",
+            [get(
+                streaks::get_streak,
+                "How long the current condition has lasted",
+            )],
+        )
+        .route(
+            "/diff/:city",
+            [get(
+                diff::get_diff,
+                "Changes between two points (?from=&to=)",
+            )],
+        )
+        .route(
+            "/stations/:city",
+            [get(stations::get_stations, "Readings of a city's stations")],
+        )
+        // MCP routes
+        .route("/mcp", [get(mcp_api::mcp_health_check, "MCP health check")])
+        .route(
+            "/mcp/initialize",
+            [post(
+                mcp_session::initialize,
+                "Start an MCP session (protocol version negotiation)",
+            )],
+        )
+        .route(
+            "/mcp/session",
+            [delete(mcp_session::end_session, "End an MCP session")],
+        )
+        .merge(mcp_tools);
+
+    #[cfg(feature = "dashboard")]
+    {
+        app = app.route("/dashboard", [get(dashboard::dashboard, "HTML dashboard")]);
+    }
+
+    #[cfg(feature = "geoip")]
+    if state.geoip.is_some() {
+        app = app.route(
+            "/weather/me",
+            [get(
+                geoip::weather_for_client,
+                "Weather at the caller's location",
+            )],
+        );
+    }
+
+    // Favorites and digests belong to an API key, so they need --api-keys-file
+    if state.api_keys.is_some() {
+        app = app
+            .route(
+                "/me/favorites",
+                [
+                    get(favorites::list_favorites, "Your favorite cities"),
+                    post(favorites::add_favorite, "Add a favorite city"),
+                    put(favorites::replace_favorites, "Replace your favorite cities"),
+                ],
+            )
+            .route(
+                "/me/favorites/:city",
+                [delete(favorites::remove_favorite, "Remove a favorite city")],
+            )
+            .route(
+                "/weather/favorites",
+                [get(
+                    favorites::weather_for_favorites,
+                    "Weather for your favorite cities",
+                )],
+            )
+            .route(
+                "/me/digest",
+                [
+                    get(digests::get_digest, "Your daily digest settings"),
+                    put(digests::put_digest, "Schedule a daily digest"),
+                    delete(digests::delete_digest, "Stop your daily digest"),
+                ],
+            );
+    }
+
+    // API keys and quotas cover the public routes; admin routes use the admin token
+    app = app.map(|router| {
+        router.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api_keys::require_api_key,
+        ))
+    });
+
+    // Admin API, only available when an admin token is configured
+    if config.admin_token.is_some() {
+        let admin_routes = Routes::new(Auth::AdminToken)
+            .route(
+                "/admin/cities",
+                [
+                    get(admin::list_cities, "Every city, including deleted ones"),
+                    post(admin::create_city, "Add a city"),
+                ],
+            )
+            .route(
+                "/admin/cities/:name",
+                [
+                    put(admin::update_city, "Edit a city"),
+                    delete(admin::delete_city, "Soft-delete a city"),
+                ],
+            )
+            .route(
+                "/admin/cities/:name/restore",
+                [post(admin::restore_city, "Restore a deleted city")],
+            )
+            .route(
+                "/admin/analytics/cities",
+                [get(admin::city_analytics, "Requests per city")],
+            )
+            .route(
+                "/admin/usage",
+                [get(admin::api_key_usage, "Quota usage per API key")],
+            )
+            .route(
+                "/admin/status",
+                [get(admin::runtime_status, "Background jobs and caches")],
+            )
+            .route(
+                "/admin/requests",
+                [get(request_log::search, "Search the request log")],
+            )
+            .route(
+                "/admin/snapshot",
+                [get(admin::snapshot, "Export the city database")],
+            )
+            .route(
+                "/admin/restore",
+                [post(admin::restore_snapshot, "Import a snapshot")],
+            )
+            .route(
+                "/admin/signing-keys",
+                [
+                    get(signing::list_keys, "Signing keys"),
+                    post(signing::rotate_key, "Rotate the signing key"),
+                ],
+            )
+            .route(
+                "/admin/signing-keys/:id",
+                [delete(signing::retire_key, "Retire a signing key")],
+            )
+            .route(
+                "/admin/webhooks",
+                [
+                    get(webhooks::list_webhooks, "City change webhooks"),
+                    post(webhooks::create_webhook, "Register a city change webhook"),
+                ],
+            )
+            .route(
+                "/admin/webhooks/:id",
+                [delete(
+                    webhooks::delete_webhook,
+                    "Remove a city change webhook",
+                )],
+            )
+            .map(|router| {
+                // The last layer added runs first: authenticate before consulting the replay cache
+                router
+                    .route_layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        idempotency::idempotency,
+                    ))
+                    .route_layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        admin::require_admin,
+                    ))
+            });
+        app = app.merge(admin_routes);
+    }
+
+    // Optional static file serving for a bundled web UI
+    if let Some(dir) = &config.static_dir {
+        let serve_dir = ServeDir::new(dir);
+        app = if config.spa_fallback {
+            app.nest_service(
+                "/static",
+                "Bundled web UI",
+                serve_dir.fallback(ServeFile::new(dir.join("index.html"))),
+            )
+        } else {
+            app.nest_service("/static", "Bundled web UI", serve_dir)
+        };
+    }
+
+    // Discovery routes, open even with API keys
+    let (app, manifest) = app
+        .open_route(
+            "/.well-known/api-manifest",
+            [get(manifest::get_manifest, "This list of endpoints")],
+        )
+        .open_route(
+            "/openapi.json",
+            [get(manifest::get_openapi, "OpenAPI description")],
+        )
+        .finish();
+    let manifest = Arc::new(manifest);
+    let app = app
+        .fallback(not_found)
+        .method_not_allowed_fallback(method_not_allowed);
+
+    let timeout_policy = Arc::new(timeouts::TimeoutPolicy::from_config(&config));
+
+    let mut app = app.with_state(state.clone());
+    // Innermost, so injected latency counts against the timeouts below
+    if config.chaos {
+        let chaos = Arc::new(chaos::Chaos::from_config(&config));
+        app = app.layer(middleware::from_fn_with_state(chaos, chaos::inject));
+    }
+
+    let app = app
+        // Read by the discovery routes and the 404 and 405 fallbacks
+        .layer(Extension(manifest.clone()))
+        .layer(middleware::from_fn(locale::negotiate))
+        .layer(middleware::from_fn_with_state(
+            timeout_policy,
+            timeouts::enforce,
+        ))
+        // Reject requests beyond the concurrency limit right away instead of queueing them.
+        // The limit is global: axum applies layers to each route separately.
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(overloaded))
+                .load_shed()
+                .layer(GlobalConcurrencyLimitLayer::new(
+                    config.max_concurrency.get(),
+                )),
+        )
+        // Outside the limits above, so timeouts and shed requests are logged too
+        .layer(middleware::from_fn_with_state(state, request_log::record))
+        // Before the request log, which records the trace id
+        .layer(middleware::from_fn(trace::join))
+        .layer(TraceLayer::new_for_http())
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any),
+        );
+    (app, manifest)
+}
+
+/// Start the background jobs behind the routes [`router`] adds for `state`,
+/// and log which optional parts are on
+///
+/// That is the digest scheduler with API keys, and the webhook dispatcher
+/// with the admin API or webhooks restored from `--data-file`.
+pub fn spawn_jobs(state: &AppState) {
+    let config = &state.config;
+    if config.signing_secret.is_some() || config.sign_mcp {
+        let signed = if config.sign_mcp {
+            "webhooks and MCP results"
+        } else {
+            "webhooks"
+        };
+        println!(
+            "🔏 Signing {} ({} header)",
+            signed,
+            signing::SIGNATURE_HEADER
+        );
+        println!();
+    }
+    if state.api_keys.is_some() {
+        digests::spawn_scheduler(state.clone());
+        println!("⭐ Favorites and digests: ENABLED (per API key)");
+        println!();
+    }
+    if config.admin_token.is_some() {
+        println!("🛠️  Admin API: ENABLED (Authorization: Bearer <admin token>)");
+        println!();
+    }
+    // Webhooks restored from --data-file are delivered even without the admin API
+    if config.admin_token.is_some() || !state.webhooks.list().is_empty() {
+        webhooks::spawn_dispatcher(state.clone());
+    }
+    if let Some(dir) = &config.static_dir {
+        println!("📁 Serving {} under /static", dir.display());
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cities::{CityDatabase, CityStore};
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_building_the_router_starts_nothing() {
+        let state = AppState::new(
+            crate::config::ServerConfig::parse_from(["server", "--admin-token", "t0k"]),
+            Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let tasks = || {
+            tokio::runtime::Handle::current()
+                .metrics()
+                .num_alive_tasks()
+        };
+        let before = tasks();
+
+        let (_app, manifest) = router(state.clone());
+        assert_eq!(tasks(), before);
+        assert!(manifest
+            .endpoints
+            .iter()
+            .any(|e| e.path == "/admin/webhooks"));
+
+        // The webhook dispatcher, for the admin API
+        spawn_jobs(&state);
+        assert_eq!(tasks(), before + 1);
+    }
+}
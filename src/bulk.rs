@@ -0,0 +1,149 @@
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::tenants::Scoped;
+use crate::{ErrorResponse, WeatherData};
+
+/// Cities per page when `limit` is not given
+const DEFAULT_PAGE_SIZE: usize = 100;
+/// Largest `limit` accepted by `/weather/all`
+const MAX_PAGE_SIZE: usize = 500;
+/// Readings are taken at the start of each period, so a page stays the same
+/// (and cacheable) until the next one
+const READING_PERIOD_SECS: i64 = 60;
+
+#[derive(Debug, Deserialize)]
+pub struct AllWeatherQuery {
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct AllWeatherPage {
+    /// Known cities in total, across all pages
+    total: usize,
+    offset: usize,
+    limit: usize,
+    count: usize,
+    /// `offset` of the next page, absent on the last one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_offset: Option<usize>,
+    data: Vec<WeatherData>,
+}
+
+/// Current weather for every known city, sorted by name and paginated
+///
+/// Responses carry an `ETag` and may be cached until the readings are next
+/// refreshed; `If-None-Match` with a current tag gets `304 Not Modified`.
+pub async fn get_all_weather(
+    Scoped(state): Scoped,
+    Query(query): Query<AllWeatherQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("limit must be between 1 and {}", MAX_PAGE_SIZE),
+                code: 400,
+            }),
+        ));
+    }
+    println!(
+        "🌍 Received weather request for all cities (offset {}, limit {})",
+        query.offset, limit
+    );
+
+    let now = Utc::now();
+    let at = reading_time(now);
+    let database = state.cities.current();
+    let mut cities: Vec<_> = database.keyed().collect();
+    cities.sort_by(|a, b| a.0.cmp(b.0));
+
+    let total = cities.len();
+    let data: Vec<WeatherData> = cities
+        .into_iter()
+        .skip(query.offset)
+        .take(limit)
+        .map(|(key, record)| state.weather(key, key.clone(), record, at))
+        .collect();
+    let end = query.offset.saturating_add(data.len());
+    let page = AllWeatherPage {
+        total,
+        offset: query.offset,
+        limit,
+        count: data.len(),
+        next_offset: (end < total).then_some(end),
+        data,
+    };
+
+    let body = serde_json::to_vec(&page).unwrap_or_default();
+    let etag = format!("\"{:016x}\"", fnv1a(&body));
+    let max_age = (at + TimeDelta::seconds(READING_PERIOD_SECS) - now)
+        .num_seconds()
+        .max(0);
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", max_age),
+        ),
+    ];
+
+    let matches = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| {
+            tags.split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    if matches {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    Ok((
+        cache_headers,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )],
+        body,
+    )
+        .into_response())
+}
+
+/// Start of the reading period containing `now`
+pub fn reading_time(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.duration_trunc(TimeDelta::seconds(READING_PERIOD_SECS))
+        .unwrap_or(now)
+}
+
+/// 64-bit FNV-1a, stable across builds and processes so replicas agree on tags
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readings_are_aligned_to_the_period() {
+        let now = DateTime::parse_from_rfc3339("2026-10-16T18:12:45.5Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(reading_time(now).to_rfc3339(), "2026-10-16T18:12:00+00:00");
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_ne!(fnv1a(b"oslo"), fnv1a(b"rome"));
+    }
+}
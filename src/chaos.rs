@@ -0,0 +1,173 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::ServerConfig;
+use crate::simulation::splitmix64;
+use crate::ErrorResponse;
+
+/// Response header naming the fault injected into a response
+pub const CHAOS_HEADER: &str = "x-chaos";
+/// Largest response body the malformed-payload fault reads
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Fault injection for `--chaos`
+#[derive(Debug)]
+pub struct Chaos {
+    latency: Option<Duration>,
+    /// Probabilities in `[0, 1]`
+    error_rate: f64,
+    malformed_rate: f64,
+    seed: u64,
+    rolls: AtomicU64,
+}
+
+/// What happens to one response
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fault {
+    None,
+    Error,
+    Malformed,
+}
+
+impl Chaos {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Chaos {
+            latency: config.chaos_latency,
+            error_rate: config.chaos_error_rate / 100.0,
+            malformed_rate: config.chaos_malformed_rate / 100.0,
+            seed: config.seed,
+            rolls: AtomicU64::new(0),
+        }
+    }
+
+    /// Uniform pseudo-random number in `[0, 1)`
+    fn roll(&self) -> f64 {
+        let n = self.rolls.fetch_add(1, Ordering::Relaxed);
+        (splitmix64(self.seed ^ n) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn delay(&self) -> Option<Duration> {
+        self.latency.map(|max| max.mul_f64(self.roll()))
+    }
+
+    fn fault(&self) -> Fault {
+        let roll = self.roll();
+        if roll < self.error_rate {
+            Fault::Error
+        } else if roll < self.error_rate + self.malformed_rate {
+            Fault::Malformed
+        } else {
+            Fault::None
+        }
+    }
+}
+
+/// Middleware injecting faults into responses when `--chaos` is on
+///
+/// The health check and the admin API are left alone so the server stays
+/// observable and manageable while clients are being tested. Event streams
+/// never get a malformed body, as they have no end to cut in half.
+pub async fn inject(State(chaos): State<Arc<Chaos>>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if path == "/" || path.starts_with("/admin") {
+        return next.run(request).await;
+    }
+
+    if let Some(delay) = chaos.delay() {
+        tokio::time::sleep(delay).await;
+    }
+
+    match chaos.fault() {
+        Fault::None => next.run(request).await,
+        Fault::Error => {
+            println!("🐒 Chaos: injected 500 for {}", path);
+            let mut response = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Injected failure (chaos mode)".to_string(),
+                    code: 500,
+                }),
+            )
+                .into_response();
+            response
+                .headers_mut()
+                .insert(CHAOS_HEADER, HeaderValue::from_static("error"));
+            response
+        }
+        Fault::Malformed => {
+            let path = path.to_string();
+            let response = next.run(request).await;
+            let streaming = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("text/event-stream"));
+            if streaming {
+                return response;
+            }
+            println!("🐒 Chaos: truncated response body for {}", path);
+            let (mut parts, body) = response.into_parts();
+            let Ok(bytes) = to_bytes(body, MAX_BODY_BYTES).await else {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Response body too large")
+                    .into_response();
+            };
+            // Keep the status and content type; cut the body off halfway
+            let truncated = bytes.slice(..bytes.len() / 2);
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts
+                .headers
+                .insert(CHAOS_HEADER, HeaderValue::from_static("malformed"));
+            Response::from_parts(parts, Body::from(truncated))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chaos(error_rate: f64, malformed_rate: f64) -> Chaos {
+        Chaos {
+            latency: Some(Duration::from_millis(100)),
+            error_rate,
+            malformed_rate,
+            seed: 42,
+            rolls: AtomicU64::new(0),
+        }
+    }
+
+    #[test]
+    fn test_fault_rates() {
+        let never = chaos(0.0, 0.0);
+        assert!((0..1000).all(|_| never.fault() == Fault::None));
+
+        let always = chaos(1.0, 0.0);
+        assert!((0..1000).all(|_| always.fault() == Fault::Error));
+
+        let some = chaos(0.2, 0.1);
+        let faults: Vec<Fault> = (0..10_000).map(|_| some.fault()).collect();
+        let errors = faults.iter().filter(|f| **f == Fault::Error).count();
+        let malformed = faults.iter().filter(|f| **f == Fault::Malformed).count();
+        assert!((1800..2200).contains(&errors), "got {} errors", errors);
+        assert!(
+            (800..1200).contains(&malformed),
+            "got {} malformed",
+            malformed
+        );
+    }
+
+    #[test]
+    fn test_delay_is_bounded() {
+        let chaos = chaos(0.0, 0.0);
+        assert!((0..1000).all(|_| chaos.delay().unwrap() < Duration::from_millis(100)));
+    }
+}
@@ -0,0 +1,868 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::conditions::Condition;
+use crate::stations::{Aggregation, Station, MAX_STATIONS};
+
+/// A city compiled into the server from `data/cities.csv` (see `build.rs`)
+#[derive(Debug)]
+pub struct BuiltinCity {
+    /// Lowercase name used for lookups
+    pub key: &'static str,
+    pub name: &'static str,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: &'static str,
+    pub continent: &'static str,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// IANA time zone, e.g. `Europe/Stockholm`
+    pub timezone: &'static str,
+    pub temperature: i32,
+    pub condition: &'static str,
+    pub humidity: i32,
+    pub wind_speed: i32,
+}
+
+/// Every built-in city, generated at compile time
+pub static BUILTIN_CITIES: &[BuiltinCity] =
+    include!(concat!(env!("OUT_DIR"), "/builtin_cities.rs"));
+
+/// One city as described in a `--cities-file`
+///
+/// JSON files hold an array of these objects; CSV files use the field names
+/// as the header row. Coordinates are optional, but cities without them are
+/// not varied by the simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CityRecordInput")]
+pub struct CityRecord {
+    pub name: String,
+    /// ISO 3166-1 alpha-2 country code
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub continent: Option<String>,
+    /// IANA time zone, e.g. `Europe/Stockholm`
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Temperature in °C
+    pub temperature: i32,
+    pub condition: Condition,
+    /// Free-text wording finer than `condition`, e.g. "Hot & Sunny";
+    /// borrowed from the compiled-in table for built-in cities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Cow<'static, str>>,
+    /// Relative humidity in percent (0-100)
+    pub humidity: i32,
+    /// Wind speed in km/h
+    pub wind_speed: i32,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    /// Weather stations reporting for the city (JSON only); when present,
+    /// the city's reading is aggregated from theirs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stations: Vec<Station>,
+    /// How `stations` are aggregated; `--station-aggregation` when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<Aggregation>,
+    /// Set when an admin soft-deletes the city; deleted cities are hidden
+    /// from every public endpoint until restored
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A [`CityRecord`] as written in files and admin payloads
+///
+/// `condition` may be a condition code or any free text; text that is not a
+/// code or label becomes the description (unless one is given) and is
+/// classified into a [`Condition`].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CityRecordInput {
+    name: String,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    continent: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+    temperature: i32,
+    condition: String,
+    #[serde(default)]
+    description: Option<String>,
+    humidity: i32,
+    wind_speed: i32,
+    #[serde(default)]
+    latitude: Option<f64>,
+    #[serde(default)]
+    longitude: Option<f64>,
+    #[serde(default)]
+    stations: Vec<Station>,
+    #[serde(default)]
+    aggregation: Option<Aggregation>,
+}
+
+impl From<CityRecordInput> for CityRecord {
+    fn from(input: CityRecordInput) -> Self {
+        let (condition, text) = Condition::classify(Cow::Owned(input.condition));
+        CityRecord {
+            name: input.name,
+            country: input.country,
+            continent: input.continent,
+            timezone: input.timezone,
+            temperature: input.temperature,
+            condition,
+            description: input.description.map(Cow::Owned).or(text),
+            humidity: input.humidity,
+            wind_speed: input.wind_speed,
+            latitude: input.latitude,
+            longitude: input.longitude,
+            stations: input.stations,
+            aggregation: input.aggregation,
+            deleted_at: None,
+        }
+    }
+}
+
+impl CityRecord {
+    /// Coordinates, if the record has both of them
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    /// Trim free-text fields and upper-case the country code
+    pub fn normalize(&mut self) {
+        self.name = self.name.trim().to_string();
+        if let Some(description) = &mut self.description {
+            *description = Cow::Owned(description.trim().to_string());
+        }
+        if let Some(country) = &mut self.country {
+            *country = country.trim().to_ascii_uppercase();
+        }
+        if let Some(continent) = &mut self.continent {
+            *continent = continent.trim().to_string();
+        }
+        if let Some(timezone) = &mut self.timezone {
+            *timezone = timezone.trim().to_string();
+        }
+        for station in &mut self.stations {
+            station.id = station.id.trim().to_string();
+        }
+    }
+
+    /// Check every field, reporting all problems rather than just the first
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, field: &'static str, message: String| {
+            if !ok {
+                errors.push(FieldError { field, message });
+            }
+        };
+
+        check(
+            !self.name.trim().is_empty(),
+            "name",
+            "must not be empty".to_string(),
+        );
+        check(
+            self.description
+                .as_deref()
+                .is_none_or(|d| !d.trim().is_empty()),
+            "condition",
+            "must not be empty".to_string(),
+        );
+        check(
+            (-90..=60).contains(&self.temperature),
+            "temperature",
+            format!("{} is outside -90..=60 °C", self.temperature),
+        );
+        check(
+            (0..=100).contains(&self.humidity),
+            "humidity",
+            format!("{} is outside 0..=100 %", self.humidity),
+        );
+        check(
+            (0..=500).contains(&self.wind_speed),
+            "wind_speed",
+            format!("{} is outside 0..=500 km/h", self.wind_speed),
+        );
+        if let Some(country) = &self.country {
+            check(
+                COUNTRY_CODES.contains(&country.as_str()),
+                "country",
+                format!(
+                    "'{}' is not an ISO 3166-1 alpha-2 code such as 'SE'",
+                    country
+                ),
+            );
+        }
+        if let Some(continent) = &self.continent {
+            check(
+                CONTINENTS.contains(&continent.as_str()),
+                "continent",
+                format!("'{}' is not one of: {}", continent, CONTINENTS.join(", ")),
+            );
+        }
+        if let Some(timezone) = &self.timezone {
+            check(
+                !timezone.trim().is_empty(),
+                "timezone",
+                "must not be empty".to_string(),
+            );
+        }
+        if let Some(lat) = self.latitude {
+            check(
+                (-90.0..=90.0).contains(&lat),
+                "latitude",
+                format!("{} is outside -90..=90", lat),
+            );
+        }
+        if let Some(lon) = self.longitude {
+            check(
+                (-180.0..=180.0).contains(&lon),
+                "longitude",
+                format!("{} is outside -180..=180", lon),
+            );
+        }
+        match (self.latitude, self.longitude) {
+            (Some(_), None) => check(
+                false,
+                "longitude",
+                "required when latitude is given".to_string(),
+            ),
+            (None, Some(_)) => check(
+                false,
+                "latitude",
+                "required when longitude is given".to_string(),
+            ),
+            _ => {}
+        }
+        check(
+            self.stations.len() <= MAX_STATIONS,
+            "stations",
+            format!(
+                "{} stations, at most {} are allowed",
+                self.stations.len(),
+                MAX_STATIONS
+            ),
+        );
+        for (index, station) in self.stations.iter().enumerate() {
+            let mut station_check = |ok: bool, message: String| {
+                check(ok, "stations", format!("'{}': {}", station.id, message))
+            };
+            station_check(!station.id.is_empty(), "id must not be empty".to_string());
+            station_check(
+                !self.stations[..index]
+                    .iter()
+                    .any(|other| other.id == station.id),
+                "id is used by another station".to_string(),
+            );
+            station_check(
+                (-90..=60).contains(&station.temperature),
+                format!("temperature {} is outside -90..=60 °C", station.temperature),
+            );
+            station_check(
+                (0..=100).contains(&station.humidity),
+                format!("humidity {} is outside 0..=100 %", station.humidity),
+            );
+            station_check(
+                (0..=500).contains(&station.wind_speed),
+                format!("wind_speed {} is outside 0..=500 km/h", station.wind_speed),
+            );
+            match (station.latitude, station.longitude) {
+                (Some(lat), Some(lon)) => station_check(
+                    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon),
+                    format!("{}, {} are not valid coordinates", lat, lon),
+                ),
+                (None, None) => {}
+                _ => station_check(false, "latitude and longitude go together".to_string()),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A validation problem with one field of a [`CityRecord`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+/// Continent names accepted in city data
+pub const CONTINENTS: &[&str] = &[
+    "Africa",
+    "Antarctica",
+    "Asia",
+    "Europe",
+    "North America",
+    "Oceania",
+    "South America",
+];
+
+/// ISO 3166-1 alpha-2 country codes
+const COUNTRY_CODES: &[&str] = &[
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+    "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+    "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+    "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+    "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+    "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+    "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+    "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+    "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+    "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+    "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+    "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+    "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+    "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+    "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+
+#[derive(Debug)]
+pub enum CitiesError {
+    Read(PathBuf, std::io::Error),
+    UnsupportedFormat(PathBuf),
+    Parse(PathBuf, String),
+    /// A record failed validation; `entry` is 1-based (the CSV data row or JSON array index + 1)
+    Invalid {
+        path: PathBuf,
+        entry: usize,
+        reason: String,
+    },
+    Empty(PathBuf),
+}
+
+impl fmt::Display for CitiesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CitiesError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            CitiesError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported cities file {}: expected a .json or .csv extension",
+                path.display()
+            ),
+            CitiesError::Parse(path, e) => {
+                write!(f, "invalid cities file {}: {}", path.display(), e)
+            }
+            CitiesError::Invalid {
+                path,
+                entry,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "invalid city #{} in {}: {}",
+                    entry,
+                    path.display(),
+                    reason
+                )
+            }
+            CitiesError::Empty(path) => {
+                write!(f, "cities file {} contains no cities", path.display())
+            }
+        }
+    }
+}
+
+impl Error for CitiesError {}
+
+/// The set of cities the server knows about, keyed by lowercase name
+///
+/// Built-in keys borrow the compiled-in names, so responses can reuse them
+/// without allocating.
+#[derive(Debug, Clone)]
+pub struct CityDatabase {
+    cities: HashMap<Cow<'static, str>, CityRecord>,
+}
+
+impl CityDatabase {
+    /// The cities compiled into the server
+    pub fn builtin() -> Self {
+        let cities = BUILTIN_CITIES
+            .iter()
+            .map(|city| {
+                let (condition, description) = Condition::classify(Cow::Borrowed(city.condition));
+                let record = CityRecord {
+                    name: city.name.to_string(),
+                    country: Some(city.country.to_string()),
+                    continent: Some(city.continent.to_string()),
+                    timezone: Some(city.timezone.to_string()),
+                    temperature: city.temperature,
+                    condition,
+                    description,
+                    humidity: city.humidity,
+                    wind_speed: city.wind_speed,
+                    latitude: Some(city.latitude),
+                    longitude: Some(city.longitude),
+                    stations: Vec::new(),
+                    aggregation: None,
+                    deleted_at: None,
+                };
+                (Cow::Borrowed(city.key), record)
+            })
+            .collect();
+
+        CityDatabase { cities }
+    }
+
+    /// Load and validate a JSON or CSV cities file, chosen by its extension
+    pub fn load(path: &Path) -> Result<Self, CitiesError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| CitiesError::Read(path.into(), e))?;
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        let records = match extension.as_deref() {
+            Some("json") => serde_json::from_str::<Vec<CityRecord>>(&contents)
+                .map_err(|e| CitiesError::Parse(path.into(), e.to_string()))?,
+            Some("csv") => parse_csv(&contents).map_err(|e| CitiesError::Parse(path.into(), e))?,
+            _ => return Err(CitiesError::UnsupportedFormat(path.into())),
+        };
+
+        Self::from_records(records).map_err(|(entry, reason)| {
+            if entry == 0 {
+                CitiesError::Empty(path.into())
+            } else {
+                CitiesError::Invalid {
+                    path: path.into(),
+                    entry,
+                    reason,
+                }
+            }
+        })
+    }
+
+    /// Validate records and index them by lowercase name
+    ///
+    /// On failure returns the 1-based entry number and the reason, or entry
+    /// 0 when there are no records at all.
+    pub fn from_records(records: Vec<CityRecord>) -> Result<Self, (usize, String)> {
+        if records.is_empty() {
+            return Err((0, "no cities".to_string()));
+        }
+
+        let mut cities = HashMap::with_capacity(records.len());
+        for (index, mut record) in records.into_iter().enumerate() {
+            record.normalize();
+            record.validate().map_err(|errors| {
+                let reasons: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                (index + 1, reasons.join("; "))
+            })?;
+
+            let key = record.name.to_lowercase();
+            if cities.contains_key(key.as_str()) {
+                return Err((index + 1, format!("duplicate city '{}'", record.name)));
+            }
+            cities.insert(Cow::Owned(key), record);
+        }
+
+        Ok(CityDatabase { cities })
+    }
+
+    /// A tenant's view of this database: only the cities in `only` (all when
+    /// `None`), with `overrides` added or replacing cities of the same name
+    pub fn layered(
+        &self,
+        only: Option<&HashSet<String>>,
+        overrides: Option<&CityDatabase>,
+    ) -> Self {
+        let mut cities: HashMap<_, _> = self
+            .cities
+            .iter()
+            .filter(|(name, _)| only.is_none_or(|only| only.contains(name.as_ref())))
+            .map(|(name, record)| (name.clone(), record.clone()))
+            .collect();
+        if let Some(overrides) = overrides {
+            cities.extend(
+                overrides
+                    .cities
+                    .iter()
+                    .map(|(name, record)| (name.clone(), record.clone())),
+            );
+        }
+        CityDatabase { cities }
+    }
+
+    /// Look up a city by lowercase name, including soft-deleted ones
+    pub fn get_any(&self, city: &str) -> Option<&CityRecord> {
+        self.cities.get(city)
+    }
+
+    /// Insert or replace a city, returning the stored record
+    pub fn upsert(&mut self, city: &str, record: CityRecord) -> CityRecord {
+        self.cities
+            .insert(Cow::Owned(city.to_string()), record.clone());
+        record
+    }
+
+    /// Look up an active (not deleted) city by lowercase name
+    pub fn get(&self, city: &str) -> Option<&CityRecord> {
+        self.cities
+            .get(city)
+            .filter(|record| record.deleted_at.is_none())
+    }
+
+    /// Lowercase names of every active city, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(name, _)| name)
+    }
+
+    /// Every active city with its lowercase name, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CityRecord)> {
+        self.iter_all()
+            .filter(|(_, record)| record.deleted_at.is_none())
+    }
+
+    /// Every city including soft-deleted ones, in no particular order
+    pub fn iter_all(&self) -> impl Iterator<Item = (&str, &CityRecord)> {
+        self.cities
+            .iter()
+            .map(|(name, record)| (name.as_ref(), record))
+    }
+
+    /// Every active city with its key, which can be cloned without
+    /// allocating for built-in cities
+    pub fn keyed(&self) -> impl Iterator<Item = (&Cow<'static, str>, &CityRecord)> {
+        self.cities
+            .iter()
+            .filter(|(_, record)| record.deleted_at.is_none())
+    }
+
+    /// Number of active cities
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Mark a city as deleted, keeping its data so it can be restored
+    ///
+    /// Deleting an already deleted city keeps the original deletion time.
+    /// Returns `None` if the city does not exist.
+    pub fn soft_delete(&mut self, city: &str) -> Option<&CityRecord> {
+        let record = self.cities.get_mut(city)?;
+        record.deleted_at.get_or_insert_with(Utc::now);
+        Some(record)
+    }
+
+    /// Mark a city as deleted at a given time, e.g. when restoring a snapshot
+    ///
+    /// Returns false if the city does not exist.
+    pub fn set_deleted(&mut self, city: &str, at: DateTime<Utc>) -> bool {
+        let Some(record) = self.cities.get_mut(city) else {
+            return false;
+        };
+        record.deleted_at = Some(at);
+        true
+    }
+
+    /// Undo a soft delete
+    ///
+    /// Returns `None` if the city does not exist and `Some(false)` if it was
+    /// not deleted.
+    pub fn restore(&mut self, city: &str) -> Option<bool> {
+        let record = self.cities.get_mut(city)?;
+        Some(record.deleted_at.take().is_some())
+    }
+}
+
+/// Shared handle to the current city database
+///
+/// Handlers take a snapshot with [`CityStore::current`]; a reload swaps in a
+/// whole new database, so a request never sees a half-updated list.
+#[derive(Debug)]
+pub struct CityStore {
+    current: RwLock<Arc<CityDatabase>>,
+    last_reload: RwLock<Option<DateTime<Utc>>>,
+}
+
+impl CityStore {
+    pub fn new(cities: CityDatabase) -> Self {
+        CityStore {
+            current: RwLock::new(Arc::new(cities)),
+            last_reload: RwLock::new(None),
+        }
+    }
+
+    /// The database as of now; later reloads do not affect the snapshot
+    pub fn current(&self) -> Arc<CityDatabase> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Atomically replace the database with a reloaded one
+    ///
+    /// Soft deletes are kept for cities that are still present, so a reload
+    /// does not bring back cities an admin removed.
+    pub fn replace(&self, mut cities: CityDatabase) {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        for (name, record) in current.iter_all() {
+            if let (Some(deleted_at), Some(new)) = (record.deleted_at, cities.cities.get_mut(name))
+            {
+                new.deleted_at = Some(deleted_at);
+            }
+        }
+        *current = Arc::new(cities);
+        *self.last_reload.write().unwrap_or_else(|e| e.into_inner()) = Some(Utc::now());
+    }
+
+    /// Atomically replace the database as given, e.g. with an admin snapshot
+    ///
+    /// Unlike [`CityStore::replace`], soft deletes come from the new database.
+    pub fn restore(&self, cities: CityDatabase) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(cities);
+    }
+
+    /// Apply a change to a copy of the database and swap it in
+    ///
+    /// Writers are serialized; readers keep using their snapshots meanwhile.
+    pub fn modify<T>(&self, change: impl FnOnce(&mut CityDatabase) -> T) -> T {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        let mut cities = CityDatabase::clone(&current);
+        let result = change(&mut cities);
+        *current = Arc::new(cities);
+        result
+    }
+
+    /// When the database was last replaced by a reload, if ever
+    pub fn last_reload(&self) -> Option<DateTime<Utc>> {
+        *self.last_reload.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<CityRecord>, String> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes())
+        .deserialize()
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_cities_have_coordinates() {
+        let db = CityDatabase::builtin();
+        assert_eq!(
+            db.len(),
+            BUILTIN_CITIES.len(),
+            "built-in city keys should be unique"
+        );
+        assert!(db.len() >= 500);
+        assert!(db
+            .names()
+            .all(|name| db.get(name).unwrap().coordinates().is_some()));
+    }
+
+    #[test]
+    fn test_builtin_cities_keep_the_original_values() {
+        let db = CityDatabase::builtin();
+        let stockholm = db.get("stockholm").unwrap();
+        assert_eq!(stockholm.temperature, 15);
+        assert_eq!(stockholm.condition, Condition::Cloudy);
+        assert_eq!(
+            db.get("riyadh").unwrap().description.as_deref(),
+            Some("Very Hot")
+        );
+        assert_eq!(stockholm.country.as_deref(), Some("SE"));
+        assert_eq!(stockholm.timezone.as_deref(), Some("Europe/Stockholm"));
+        assert_eq!(db.get("new york").unwrap().wind_speed, 22);
+    }
+
+    #[test]
+    fn test_builtin_strings_are_borrowed() {
+        let db = CityDatabase::builtin();
+        assert!(db.keyed().all(|(key, record)| {
+            matches!(key, Cow::Borrowed(_))
+                && record
+                    .description
+                    .as_ref()
+                    .is_none_or(|d| matches!(d, Cow::Borrowed(_)))
+        }));
+    }
+
+    #[test]
+    fn test_parse_csv_with_optional_coordinates() {
+        let records = parse_csv(
+            "name,temperature,condition,humidity,wind_speed,latitude,longitude\n\
+             Reykjavik,5,Windy,80,30,64.15,-21.94\n\
+             Atlantis, 18 ,Misty,95,4,,\n",
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].coordinates(), Some((64.15, -21.94)));
+        assert_eq!(records[1].temperature, 18);
+        assert_eq!(records[1].condition, Condition::Unknown);
+        assert_eq!(records[1].description.as_deref(), Some("Misty"));
+        assert_eq!(records[1].coordinates(), None);
+    }
+
+    #[test]
+    fn test_records_are_validated() {
+        let city = CityRecord {
+            name: "Reykjavik".to_string(),
+            country: None,
+            continent: None,
+            timezone: None,
+            temperature: 5,
+            condition: Condition::Wind,
+            description: None,
+            humidity: 80,
+            wind_speed: 30,
+            latitude: None,
+            longitude: None,
+            stations: Vec::new(),
+            aggregation: None,
+            deleted_at: None,
+        };
+
+        let humid = CityRecord {
+            humidity: 120,
+            ..city.clone()
+        };
+        let (entry, reason) = CityDatabase::from_records(vec![city.clone(), humid]).unwrap_err();
+        assert_eq!(entry, 2);
+        assert!(reason.contains("humidity"));
+
+        let half_located = CityRecord {
+            latitude: Some(64.15),
+            ..city.clone()
+        };
+        assert!(CityDatabase::from_records(vec![half_located]).is_err());
+
+        let duplicate = CityRecord {
+            name: "REYKJAVIK".to_string(),
+            ..city.clone()
+        };
+        let (_, reason) = CityDatabase::from_records(vec![city, duplicate]).unwrap_err();
+        assert!(reason.contains("duplicate"));
+
+        assert_eq!(CityDatabase::from_records(vec![]).unwrap_err().0, 0);
+    }
+
+    #[test]
+    fn test_validation_reports_every_field() {
+        let mut city = CityRecord {
+            name: "  ".to_string(),
+            country: Some("se".to_string()),
+            continent: Some("Atlantis".to_string()),
+            timezone: None,
+            temperature: 99,
+            condition: Condition::Wind,
+            description: None,
+            humidity: 80,
+            wind_speed: 30,
+            latitude: None,
+            longitude: None,
+            stations: Vec::new(),
+            aggregation: None,
+            deleted_at: None,
+        };
+        city.normalize();
+        assert_eq!(city.country.as_deref(), Some("SE"));
+
+        let fields: Vec<_> = city
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, ["name", "temperature", "continent"]);
+
+        let station = Station {
+            id: " harbor ".to_string(),
+            temperature: 5,
+            humidity: 80,
+            wind_speed: 30,
+            latitude: None,
+            longitude: None,
+        };
+        city.stations = vec![
+            station.clone(),
+            Station {
+                humidity: 120,
+                ..station
+            },
+        ];
+        city.normalize();
+        let errors = city.validate().unwrap_err();
+        let stations: Vec<_> = errors.iter().filter(|e| e.field == "stations").collect();
+        assert_eq!(
+            stations[0].message,
+            "'harbor': id is used by another station"
+        );
+        assert_eq!(
+            stations[1].message,
+            "'harbor': humidity 120 is outside 0..=100 %"
+        );
+    }
+
+    #[test]
+    fn test_store_swaps_whole_database() {
+        let store = CityStore::new(CityDatabase::builtin());
+        let snapshot = store.current();
+        assert!(store.last_reload().is_none());
+
+        let records =
+            parse_csv("name,temperature,condition,humidity,wind_speed\nAtlantis,18,Misty,95,4\n")
+                .unwrap();
+        store.replace(CityDatabase::from_records(records).unwrap());
+
+        assert_eq!(store.current().len(), 1);
+        assert!(store.last_reload().is_some());
+        // Snapshots taken before the reload are unaffected
+        assert!(snapshot.get("stockholm").is_some());
+    }
+
+    #[test]
+    fn test_soft_delete_and_restore() {
+        let store = CityStore::new(CityDatabase::builtin());
+        let total = store.current().len();
+
+        assert!(store.modify(|db| db.soft_delete("paris").is_some()));
+        assert!(store.modify(|db| db.soft_delete("atlantis").is_none()));
+
+        let cities = store.current();
+        assert!(cities.get("paris").is_none());
+        assert_eq!(cities.len(), total - 1);
+        assert_eq!(cities.iter_all().count(), total);
+
+        // Reloads keep the deletion
+        store.replace(CityDatabase::builtin());
+        assert!(store.current().get("paris").is_none());
+
+        assert_eq!(store.modify(|db| db.restore("paris")), Some(true));
+        assert_eq!(store.modify(|db| db.restore("paris")), Some(false));
+        assert!(store.current().get("paris").is_some());
+    }
+}
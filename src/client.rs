@@ -1,7 +1,25 @@
-use reqwest;
+// Load-testing subcommand
+mod client_bench;
+// Client configuration profiles
+mod client_config;
+// Terminal dashboard subcommand
+#[cfg(feature = "tui")]
+mod client_dashboard;
+// Error classes and exit codes
+mod client_error;
+// Duration parsing shared with the server
+mod duration;
+// W3C traceparent headers, shared with the server
+mod traceparent;
+
+use clap::{Parser, Subcommand};
+use client_config::{ClientConfig, OutputFormat, Overrides, Secret, Settings, Units};
+use client_error::CliError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use traceparent::{TraceParent, TRACEPARENT};
 
 #[derive(Debug, Serialize)]
 struct WeatherRequest {
@@ -17,17 +35,32 @@ struct WeatherResponse {
 struct WeatherData {
     city: String,
     temperature: i32,
+    /// Condition code, e.g. `partly_cloudy`
     condition: String,
+    /// Finer wording from the city data, e.g. "Hot & Sunny"
+    #[serde(default)]
+    description: Option<String>,
     humidity: i32,
     wind_speed: i32,
 }
 
+impl WeatherData {
+    /// The description when there is one, otherwise the condition code
+    fn condition_text(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.condition)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct StatsResponse {
     total_cities: usize,
-    average_temp: f32,
-    hottest_city: String,
-    coldest_city: String,
+    /// Absent when the server has no cities
+    average_temp: Option<f32>,
+    hottest_city: Option<String>,
+    coldest_city: Option<String>,
+    /// Absent from older servers as well
+    most_humid_city: Option<String>,
+    windiest_city: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,74 +69,340 @@ struct CitiesResponse {
     cities: Vec<String>,
 }
 
+/// Command-line options for the weather API client
+#[derive(Debug, Parser)]
+#[command(name = "weather-cli", version, about = "Rust Weather API client")]
+struct Cli {
+    /// Profile from the config file to use
+    #[arg(long, short)]
+    profile: Option<String>,
+
+    /// Path to the config file (default: ~/.config/weather-cli/config.toml)
+    #[arg(long, env = "WEATHER_CLI_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Server URL, overriding the profile
+    #[arg(long)]
+    server: Option<String>,
+
+    /// API key sent as `X-API-Key`, overriding the profile
+    #[arg(long, env = "WEATHER_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Bearer token sent as `Authorization: Bearer`, overriding the profile
+    #[arg(long, env = "WEATHER_API_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Units used to display results, overriding the profile
+    #[arg(long, value_enum)]
+    units: Option<Units>,
+
+    /// Proxy for all requests, e.g. `http://proxy:8080` or `socks5h://proxy:1080`
+    ///
+    /// Without this flag the standard `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `NO_PROXY` environment variables are honored.
+    #[arg(long, conflicts_with = "no_proxy")]
+    proxy: Option<String>,
+
+    /// Ignore proxy settings from the profile and the environment
+    #[arg(long)]
+    no_proxy: bool,
+
+    /// Output format, overriding the profile
+    #[arg(long, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Print the resolved settings (credentials are redacted)
+    #[arg(long, short, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Only print results and errors, no banner or progress messages
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// Fail with exit code 5 if the server does not know a requested city
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Trace to continue, as a W3C `traceparent` value (a new trace is
+    /// started when unset or invalid)
+    #[arg(long, env = "TRACEPARENT", global = true)]
+    traceparent: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the end-to-end test suite against the server (default)
+    Test,
+    /// Get the current weather for one or more cities
+    Weather {
+        /// City names, e.g. `Stockholm "New York"`
+        #[arg(required = true, num_args = 1..=20)]
+        cities: Vec<String>,
+    },
+    /// Drive load against the server and report latency percentiles
+    Bench(client_bench::BenchArgs),
+    /// Live terminal dashboard of tracked cities
+    #[cfg(feature = "tui")]
+    Dashboard(client_dashboard::DashboardArgs),
+}
+
 /// Main entry point for the weather API client
+///
+/// Exits non-zero with a distinct code per failure class (see [`CliError`]).
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    println!("🦀 Rust Weather API Client v0.2.0");
-    println!("====================================\n");
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // Needed to format errors even when the config itself fails to load
+    let json_errors = cli.output == Some(OutputFormat::Json);
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if json_errors {
+                println!("{}", error.to_json());
+            } else {
+                eprintln!("❌ ERROR: {}", error);
+                if let CliError::Connection(_) = error {
+                    eprintln!("\n💡 Please start the server first:");
+                    eprintln!("   cargo run --bin server\n");
+                }
+            }
+            error.exit_code()
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), CliError> {
+    let config = match cli.config.clone().or_else(ClientConfig::default_path) {
+        Some(path) => ClientConfig::load(&path)?,
+        None => ClientConfig::default(),
+    };
+    let profile = config.select(cli.profile.as_deref())?;
+    let settings = Settings::resolve(
+        profile,
+        Overrides {
+            server_url: cli.server,
+            api_key: cli.api_key.map(Secret::new),
+            token: cli.token.map(Secret::new),
+            proxy: cli.proxy.map(Secret::new),
+            units: cli.units,
+            output: cli.output,
+        },
+    );
+
+    if !cli.quiet {
+        println!("🦀 Rust Weather API Client v0.2.0");
+        println!("====================================\n");
+    }
+
+    // One span for the whole run; the server's spans become its children
+    let span = cli
+        .traceparent
+        .as_deref()
+        .and_then(TraceParent::parse)
+        .map_or_else(TraceParent::root, |parent| parent.child());
 
-    let server_url = "http://localhost:3000";
-    let client = reqwest::Client::new();
+    if cli.verbose {
+        println!("⚙️  Settings: {:#?}\n", settings);
+        println!("🔎 Trace ID: {}\n", span.trace_id_hex());
+    }
+
+    let server_url = settings.server_url.as_str();
+    let client = build_http_client(&settings, cli.no_proxy, span)?;
 
     // Check if server is running
-    println!("🔍 Checking if server is ready...");
-    match check_server_health(server_url).await {
-        Ok(_) => println!("✅ Server is ready!\n"),
-        Err(_) => {
-            println!("❌ ERROR: Server is not running!");
-            println!("\n💡 Please start the server first:");
-            println!("   cargo run --bin server\n");
-            std::process::exit(1);
-        }
+    if !cli.quiet {
+        println!("🔍 Checking if server is ready at {}...", server_url);
+    }
+    check_server_health(&client, server_url).await?;
+    if !cli.quiet {
+        println!("✅ Server is ready!\n");
     }
 
+    match cli.command.unwrap_or(Command::Test) {
+        Command::Test => run_tests(&client, &settings).await,
+        Command::Weather { cities } => get_weather(&client, &settings, cities, cli.strict).await,
+        Command::Bench(args) => client_bench::run(&client, &settings, &args).await,
+        #[cfg(feature = "tui")]
+        Command::Dashboard(args) => client_dashboard::run(&client, &settings, &args).await,
+    }
+}
+
+/// Run the end-to-end test suite
+async fn run_tests(client: &reqwest::Client, settings: &Settings) -> Result<(), CliError> {
     println!("{}", "=".repeat(60));
     println!("TEST 1: Get list of all available cities");
     println!("{}", "=".repeat(60));
-    test_get_cities(&client, server_url).await?;
+    test_get_cities(client, settings).await?;
 
     println!("\n{}", "=".repeat(60));
     println!("TEST 2: Get weather for specific cities");
     println!("{}", "=".repeat(60));
-    test_get_weather(&client, server_url).await?;
+    test_get_weather(client, settings).await?;
 
     println!("\n{}", "=".repeat(60));
     println!("TEST 3: Get weather statistics");
     println!("{}", "=".repeat(60));
-    test_get_stats(&client, server_url).await?;
+    test_get_stats(client, settings).await?;
 
     println!("\n{}", "=".repeat(60));
     println!("TEST 4: Test error handling (empty request)");
     println!("{}", "=".repeat(60));
-    test_error_handling(&client, server_url).await?;
+    test_error_handling(client, settings).await?;
 
     println!("\n🎉 All tests completed successfully!\n");
 
     Ok(())
 }
 
+/// Build the HTTP client shared by all requests
+fn build_http_client(
+    settings: &Settings,
+    no_proxy: bool,
+    span: TraceParent,
+) -> Result<reqwest::Client, CliError> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        TRACEPARENT,
+        reqwest::header::HeaderValue::from_str(&span.to_string())?,
+    );
+    if let Some(api_key) = &settings.api_key {
+        let mut value = reqwest::header::HeaderValue::from_str(api_key.expose())?;
+        value.set_sensitive(true);
+        headers.insert("x-api-key", value);
+    }
+    if let Some(token) = &settings.token {
+        let mut value =
+            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token.expose()))?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+
+    let mut builder = reqwest::Client::builder().default_headers(headers);
+    if no_proxy {
+        builder = builder.no_proxy();
+    } else if let Some(proxy) = &settings.proxy {
+        // An explicit proxy replaces the environment-derived ones
+        builder = builder.proxy(reqwest::Proxy::all(proxy.expose())?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Format a Celsius temperature in the selected units
+fn format_temp(celsius: f32, units: Units) -> String {
+    match units {
+        Units::Metric => format!("{:.1}°C", celsius),
+        Units::Imperial => format!("{:.1}°F", celsius * 9.0 / 5.0 + 32.0),
+    }
+}
+
+/// Format a km/h wind speed in the selected units
+fn format_wind(kmh: i32, units: Units) -> String {
+    match units {
+        Units::Metric => format!("{} km/h", kmh),
+        Units::Imperial => format!("{:.0} mph", kmh as f32 * 0.621_371),
+    }
+}
+
+/// Print a raw JSON response body when JSON output is selected
+fn print_json(value: &serde_json::Value) -> Result<(), CliError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Get the weather for the cities given on the command line
+///
+/// In strict mode, cities the server does not know (reported with an
+/// `Unknown` condition) turn into an error after the results are printed.
+async fn get_weather(
+    client: &reqwest::Client,
+    settings: &Settings,
+    cities: Vec<String>,
+    strict: bool,
+) -> Result<(), CliError> {
+    let response = client
+        .post(format!("{}/weather", settings.server_url))
+        .json(&WeatherRequest {
+            cities: cities.clone(),
+        })
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(CliError::from_response(response).await);
+    }
+
+    let body: serde_json::Value = response.json().await?;
+    let weather_data: WeatherResponse = serde_json::from_value(body.clone())?;
+
+    if settings.output == OutputFormat::Json {
+        print_json(&body)?;
+    } else {
+        for city in &cities {
+            if let Some(data) = weather_data.data.get(city) {
+                println!(
+                    "{}: {}, {}, humidity {}%, wind {}",
+                    data.city,
+                    format_temp(data.temperature as f32, settings.units),
+                    data.condition_text(),
+                    data.humidity,
+                    format_wind(data.wind_speed, settings.units)
+                );
+            }
+        }
+    }
+
+    if strict {
+        let unknown: Vec<String> = cities
+            .into_iter()
+            .filter(|city| {
+                weather_data
+                    .data
+                    .get(city)
+                    .is_none_or(|data| data.condition.eq_ignore_ascii_case("unknown"))
+            })
+            .collect();
+        if !unknown.is_empty() {
+            return Err(CliError::UnknownCities(unknown));
+        }
+    }
+
+    Ok(())
+}
+
 /// Test 1: Get all available cities
-async fn test_get_cities(client: &reqwest::Client, server_url: &str) -> Result<(), Box<dyn Error>> {
+async fn test_get_cities(client: &reqwest::Client, settings: &Settings) -> Result<(), CliError> {
     let response = client
-        .get(format!("{}/cities", server_url))
+        .get(format!("{}/cities", settings.server_url))
         .send()
         .await?;
 
     if response.status().is_success() {
-        let cities_data: CitiesResponse = response.json().await?;
+        let body: serde_json::Value = response.json().await?;
+        if settings.output == OutputFormat::Json {
+            return print_json(&body);
+        }
+
+        let cities_data: CitiesResponse = serde_json::from_value(body)?;
         println!("📍 Total cities available: {}", cities_data.count);
-        println!("🌍 Cities: {}", cities_data.cities[..10].join(", "));
-        println!("   ... and {} more", cities_data.count - 10);
+        let shown = cities_data.cities.len().min(10);
+        println!("🌍 Cities: {}", cities_data.cities[..shown].join(", "));
+        if cities_data.count > shown {
+            println!("   ... and {} more", cities_data.count - shown);
+        }
+    } else {
+        return Err(CliError::from_response(response).await);
     }
 
     Ok(())
 }
 
 /// Test 2: Get weather for specific cities
-async fn test_get_weather(
-    client: &reqwest::Client,
-    server_url: &str,
-) -> Result<(), Box<dyn Error>> {
+async fn test_get_weather(client: &reqwest::Client, settings: &Settings) -> Result<(), CliError> {
     let cities = vec![
         "Stockholm".to_string(),
         "Gaza".to_string(),
@@ -116,13 +415,18 @@ async fn test_get_weather(
 
     let request = WeatherRequest { cities };
     let response = client
-        .post(format!("{}/weather", server_url))
+        .post(format!("{}/weather", settings.server_url))
         .json(&request)
         .send()
         .await?;
 
     if response.status().is_success() {
-        let weather_data: WeatherResponse = response.json().await?;
+        let body: serde_json::Value = response.json().await?;
+        if settings.output == OutputFormat::Json {
+            return print_json(&body);
+        }
+
+        let weather_data: WeatherResponse = serde_json::from_value(body)?;
 
         println!("\n📊 Weather Details:\n");
 
@@ -131,32 +435,65 @@ async fn test_get_weather(
 
         for (_city, data) in cities {
             println!("   🏙️  {}", data.city);
-            println!("      🌡️  Temperature: {}°C", data.temperature);
-            println!("      ☁️  Condition: {}", data.condition);
+            println!(
+                "      🌡️  Temperature: {}",
+                format_temp(data.temperature as f32, settings.units)
+            );
+            println!("      ☁️  Condition: {}", data.condition_text());
             println!("      💧 Humidity: {}%", data.humidity);
-            println!("      💨 Wind Speed: {} km/h", data.wind_speed);
+            println!(
+                "      💨 Wind Speed: {}",
+                format_wind(data.wind_speed, settings.units)
+            );
             println!();
         }
+    } else {
+        return Err(CliError::from_response(response).await);
     }
 
     Ok(())
 }
 
 /// Test 3: Get weather statistics
-async fn test_get_stats(client: &reqwest::Client, server_url: &str) -> Result<(), Box<dyn Error>> {
+async fn test_get_stats(client: &reqwest::Client, settings: &Settings) -> Result<(), CliError> {
     let response = client
-        .get(format!("{}/stats?sort=temp", server_url))
+        .get(format!("{}/stats?sort=temp", settings.server_url))
         .send()
         .await?;
 
     if response.status().is_success() {
-        let stats: StatsResponse = response.json().await?;
+        let body: serde_json::Value = response.json().await?;
+        if settings.output == OutputFormat::Json {
+            return print_json(&body);
+        }
+
+        let stats: StatsResponse = serde_json::from_value(body)?;
 
         println!("📊 Weather Statistics:");
         println!("   📍 Total cities: {}", stats.total_cities);
-        println!("   🌡️  Average temperature: {:.1}°C", stats.average_temp);
-        println!("   🔥 Hottest city: {}", stats.hottest_city);
-        println!("   ❄️  Coldest city: {}", stats.coldest_city);
+        let Some(average_temp) = stats.average_temp else {
+            return Ok(());
+        };
+        println!(
+            "   🌡️  Average temperature: {}",
+            format_temp(average_temp, settings.units)
+        );
+        println!(
+            "   🔥 Hottest city: {}",
+            stats.hottest_city.unwrap_or_default()
+        );
+        println!(
+            "   ❄️  Coldest city: {}",
+            stats.coldest_city.unwrap_or_default()
+        );
+        if let Some(city) = stats.most_humid_city {
+            println!("   💧 Most humid city: {}", city);
+        }
+        if let Some(city) = stats.windiest_city {
+            println!("   💨 Windiest city: {}", city);
+        }
+    } else {
+        return Err(CliError::from_response(response).await);
     }
 
     Ok(())
@@ -165,12 +502,12 @@ async fn test_get_stats(client: &reqwest::Client, server_url: &str) -> Result<()
 /// Test 4: Test error handling
 async fn test_error_handling(
     client: &reqwest::Client,
-    server_url: &str,
-) -> Result<(), Box<dyn Error>> {
+    settings: &Settings,
+) -> Result<(), CliError> {
     let empty_request = WeatherRequest { cities: vec![] };
 
     let response = client
-        .post(format!("{}/weather", server_url))
+        .post(format!("{}/weather", settings.server_url))
         .json(&empty_request)
         .send()
         .await?;
@@ -180,15 +517,17 @@ async fn test_error_handling(
         println!("✅ Error handling works correctly!");
         println!("   Response: {}", error_text);
     } else {
-        println!("❌ Expected error but got success");
+        return Err(CliError::InvalidResponse(format!(
+            "expected a client error for an empty request, got HTTP {}",
+            response.status()
+        )));
     }
 
     Ok(())
 }
 
 /// Check if the server is running and healthy
-async fn check_server_health(server_url: &str) -> Result<(), Box<dyn Error>> {
-    let client = reqwest::Client::new();
+async fn check_server_health(client: &reqwest::Client, server_url: &str) -> Result<(), CliError> {
     let response = client
         .get(format!("{}/", server_url))
         .timeout(std::time::Duration::from_secs(2))
@@ -198,6 +537,6 @@ async fn check_server_health(server_url: &str) -> Result<(), Box<dyn Error>> {
     if response.status().is_success() {
         Ok(())
     } else {
-        Err("Server not healthy".into())
+        Err(CliError::from_response(response).await)
     }
 }
@@ -0,0 +1,247 @@
+use crate::client_config::{OutputFormat, Settings};
+use crate::client_error::CliError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+
+/// Upper bound for a single benchmark request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Options for the `bench` subcommand
+#[derive(Debug, clap::Args)]
+pub struct BenchArgs {
+    /// Target request rate (requests per second)
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..=100_000))]
+    pub rps: u32,
+
+    /// How long to generate load, e.g. `30s`, `2m`, `500ms`
+    #[arg(long, default_value = "10s", value_parser = crate::duration::parse_duration)]
+    pub duration: Duration,
+
+    /// Number of cities per `/weather` request
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..=20))]
+    pub cities: u32,
+}
+
+/// Outcome of a single request
+enum Outcome {
+    Ok(Duration),
+    Status(u16),
+    Failed(String),
+}
+
+/// Summary printed at the end of a run
+#[derive(Debug, Serialize)]
+struct BenchReport {
+    target_rps: u32,
+    duration_secs: f64,
+    requests: usize,
+    succeeded: usize,
+    failed: usize,
+    error_rate: f64,
+    achieved_rps: f64,
+    latency_ms: Option<LatencySummary>,
+    errors: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct LatencySummary {
+    min: f64,
+    p50: f64,
+    p90: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+}
+
+/// Nearest-rank percentile over sorted samples
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+}
+
+/// Drive open-loop load against `/weather` and report latency percentiles
+pub async fn run(
+    client: &reqwest::Client,
+    settings: &Settings,
+    args: &BenchArgs,
+) -> Result<(), CliError> {
+    let cities = fetch_city_names(client, &settings.server_url).await?;
+    if cities.is_empty() {
+        return Err(CliError::InvalidResponse(
+            "server reported no cities to benchmark".to_string(),
+        ));
+    }
+
+    println!(
+        "🏋️  Benchmarking {}/weather: {} req/s for {:?}, {} cities per request",
+        settings.server_url, args.rps, args.duration, args.cities
+    );
+
+    let url = format!("{}/weather", settings.server_url);
+    let per_request = args.cities as usize;
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / args.rps as f64));
+    // Keep the schedule even if the client falls behind, so slow responses
+    // show up as latency rather than as a silently reduced request rate.
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let mut tasks = JoinSet::new();
+    let started = Instant::now();
+    let mut sent = 0usize;
+
+    while started.elapsed() < args.duration {
+        ticker.tick().await;
+
+        let batch: Vec<String> = (0..per_request)
+            .map(|i| cities[(sent * per_request + i) % cities.len()].clone())
+            .collect();
+        let request = client
+            .post(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .json(&serde_json::json!({ "cities": batch }));
+
+        tasks.spawn(async move {
+            let start = Instant::now();
+            match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    // Read the body so latency includes the full response
+                    let body = response.bytes().await;
+                    let elapsed = start.elapsed();
+                    match body {
+                        Ok(_) if status.is_success() => Outcome::Ok(elapsed),
+                        Ok(_) => Outcome::Status(status.as_u16()),
+                        Err(e) => Outcome::Failed(classify(&e)),
+                    }
+                }
+                Err(e) => Outcome::Failed(classify(&e)),
+            }
+        });
+        sent += 1;
+    }
+    let elapsed = started.elapsed();
+
+    let mut latencies = Vec::with_capacity(sent);
+    let mut errors: BTreeMap<String, usize> = BTreeMap::new();
+    while let Some(result) = tasks.join_next().await {
+        match result? {
+            Outcome::Ok(latency) => latencies.push(latency),
+            Outcome::Status(code) => *errors.entry(format!("http {}", code)).or_default() += 1,
+            Outcome::Failed(kind) => *errors.entry(kind).or_default() += 1,
+        }
+    }
+    latencies.sort();
+
+    let failed: usize = errors.values().sum();
+    let report = BenchReport {
+        target_rps: args.rps,
+        duration_secs: elapsed.as_secs_f64(),
+        requests: sent,
+        succeeded: latencies.len(),
+        failed,
+        error_rate: if sent == 0 {
+            0.0
+        } else {
+            failed as f64 / sent as f64
+        },
+        achieved_rps: sent as f64 / elapsed.as_secs_f64(),
+        latency_ms: (!latencies.is_empty()).then(|| LatencySummary {
+            min: millis(latencies[0]),
+            p50: millis(percentile(&latencies, 50.0)),
+            p90: millis(percentile(&latencies, 90.0)),
+            p95: millis(percentile(&latencies, 95.0)),
+            p99: millis(percentile(&latencies, 99.0)),
+            max: millis(latencies[latencies.len() - 1]),
+        }),
+        errors,
+    };
+
+    match settings.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Text => print_report(&report),
+    }
+
+    Ok(())
+}
+
+fn classify(error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        "timeout".to_string()
+    } else if error.is_connect() {
+        "connection refused".to_string()
+    } else {
+        "request error".to_string()
+    }
+}
+
+async fn fetch_city_names(
+    client: &reqwest::Client,
+    server_url: &str,
+) -> Result<Vec<String>, CliError> {
+    let body: serde_json::Value = client
+        .get(format!("{}/cities", server_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(body["cities"]
+        .as_array()
+        .map(|cities| {
+            cities
+                .iter()
+                .filter_map(|c| c.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn print_report(report: &BenchReport) {
+    println!("\n📊 Results:");
+    println!(
+        "   Requests: {} sent, {} ok, {} failed ({:.2}% errors)",
+        report.requests,
+        report.succeeded,
+        report.failed,
+        report.error_rate * 100.0
+    );
+    println!(
+        "   Throughput: {:.1} req/s (target {})",
+        report.achieved_rps, report.target_rps
+    );
+
+    if let Some(latency) = &report.latency_ms {
+        println!("   Latency (ms):");
+        println!("      min {:>8.2}", latency.min);
+        println!("      p50 {:>8.2}", latency.p50);
+        println!("      p90 {:>8.2}", latency.p90);
+        println!("      p95 {:>8.2}", latency.p95);
+        println!("      p99 {:>8.2}", latency.p99);
+        println!("      max {:>8.2}", latency.max);
+    }
+
+    for (kind, count) in &report.errors {
+        println!("   ❌ {}: {}", kind, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&samples, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&samples[..1], 99.0), Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,277 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Server used when neither a profile nor a flag provides one
+pub const DEFAULT_SERVER_URL: &str = "http://localhost:3000";
+
+/// Temperature and wind units used when displaying results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+/// How the client prints results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// A credential that never shows up in `Debug` output or logs
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// Access the raw value, e.g. to put it in a request header
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(\"***\")")
+    }
+}
+
+/// A named set of client settings from the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub server_url: Option<String>,
+    pub api_key: Option<Secret>,
+    pub token: Option<Secret>,
+    pub proxy: Option<Secret>,
+    pub units: Option<Units>,
+    pub output: Option<OutputFormat>,
+}
+
+/// Contents of `~/.config/weather-cli/config.toml`
+///
+/// ```toml
+/// default_profile = "local"
+///
+/// [profiles.local]
+/// server_url = "http://localhost:3000"
+///
+/// [profiles.prod]
+/// server_url = "https://weather.example.com"
+/// api_key = "..."        # sent as `X-API-Key`
+/// token = "..."          # sent as `Authorization: Bearer`
+/// proxy = "socks5h://proxy.corp:1080"
+/// units = "imperial"
+/// output = "json"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    UnknownProfile(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config {}: {}", path.display(), e),
+            ConfigError::UnknownProfile(name) => {
+                write!(f, "profile '{}' not found in config", name)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl ClientConfig {
+    /// Default location of the config file
+    ///
+    /// Honors `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("weather-cli").join("config.toml"))
+    }
+
+    /// Load the config file, returning an empty config if it does not exist
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents).map_err(|e| ConfigError::Parse(path.into(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ConfigError::Read(path.into(), e)),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Pick the requested profile, or the default one if none was requested
+    ///
+    /// Asking for a profile that does not exist is an error; a missing
+    /// default profile simply means "no profile".
+    pub fn select(&self, requested: Option<&str>) -> Result<Option<&Profile>, ConfigError> {
+        match requested {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| ConfigError::UnknownProfile(name.to_string())),
+            None => Ok(self
+                .default_profile
+                .as_deref()
+                .or(Some("default"))
+                .and_then(|name| self.profiles.get(name))),
+        }
+    }
+}
+
+/// Values given on the command line (or via environment variables)
+///
+/// Every field is optional; anything left unset falls back to the profile.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub server_url: Option<String>,
+    pub api_key: Option<Secret>,
+    pub token: Option<Secret>,
+    pub proxy: Option<Secret>,
+    pub units: Option<Units>,
+    pub output: Option<OutputFormat>,
+}
+
+/// Effective client settings after merging flags over the selected profile
+///
+/// Credentials are wrapped in [`Secret`], so `{:?}` is safe to print.
+/// Proxy URLs may embed a username and password, so they are secrets too.
+#[derive(Debug)]
+pub struct Settings {
+    pub server_url: String,
+    pub api_key: Option<Secret>,
+    pub token: Option<Secret>,
+    pub proxy: Option<Secret>,
+    pub units: Units,
+    pub output: OutputFormat,
+}
+
+impl Settings {
+    pub fn resolve(profile: Option<&Profile>, overrides: Overrides) -> Self {
+        let Overrides {
+            server_url,
+            api_key,
+            token,
+            proxy,
+            units,
+            output,
+        } = overrides;
+
+        Settings {
+            server_url: server_url
+                .or_else(|| profile.and_then(|p| p.server_url.clone()))
+                .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: api_key.or_else(|| profile.and_then(|p| p.api_key.clone())),
+            token: token.or_else(|| profile.and_then(|p| p.token.clone())),
+            proxy: proxy.or_else(|| profile.and_then(|p| p.proxy.clone())),
+            units: units.or(profile.and_then(|p| p.units)).unwrap_or_default(),
+            output: output
+                .or(profile.and_then(|p| p.output))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        default_profile = "local"
+
+        [profiles.local]
+        server_url = "http://localhost:3000"
+
+        [profiles.prod]
+        server_url = "https://weather.example.com/"
+        api_key = "secret"
+        units = "imperial"
+        output = "json"
+    "#;
+
+    #[test]
+    fn test_select_named_and_default_profile() {
+        let config = ClientConfig::parse(SAMPLE).unwrap();
+
+        let prod = config.select(Some("prod")).unwrap().unwrap();
+        assert_eq!(prod.units, Some(Units::Imperial));
+
+        let default = config.select(None).unwrap().unwrap();
+        assert_eq!(default.server_url.as_deref(), Some("http://localhost:3000"));
+
+        assert!(config.select(Some("staging")).is_err());
+    }
+
+    #[test]
+    fn test_flags_override_profile() {
+        let config = ClientConfig::parse(SAMPLE).unwrap();
+        let prod = config.select(Some("prod")).unwrap();
+
+        let overrides = Overrides {
+            units: Some(Units::Metric),
+            token: Some(Secret::new("flag-token")),
+            ..Overrides::default()
+        };
+        let settings = Settings::resolve(prod, overrides);
+        assert_eq!(settings.server_url, "https://weather.example.com");
+        assert_eq!(settings.units, Units::Metric);
+        assert_eq!(settings.output, OutputFormat::Json);
+        assert_eq!(
+            settings.api_key.as_ref().map(Secret::expose),
+            Some("secret")
+        );
+        assert_eq!(
+            settings.token.as_ref().map(Secret::expose),
+            Some("flag-token")
+        );
+    }
+
+    #[test]
+    fn test_debug_output_redacts_credentials() {
+        let config = ClientConfig::parse(SAMPLE).unwrap();
+        let settings =
+            Settings::resolve(config.select(Some("prod")).unwrap(), Overrides::default());
+
+        let debug = format!("{:?}", settings);
+        assert!(!debug.contains("secret"), "credential leaked: {}", debug);
+        assert!(debug.contains("***"));
+    }
+
+    #[test]
+    fn test_no_profile_uses_localhost() {
+        let settings = Settings::resolve(None, Overrides::default());
+        assert_eq!(settings.server_url, DEFAULT_SERVER_URL);
+        assert_eq!(settings.units, Units::Metric);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::client_config::Settings;
+use crate::client_error::CliError;
+use crate::{format_temp, format_wind, WeatherData, WeatherRequest, WeatherResponse};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Number of samples kept per city for the sparkline
+const HISTORY_LEN: usize = 120;
+/// Server events after which the table is fetched again
+const REFRESH_EVENTS: &[&str] = &[
+    "history_recorded",
+    "city_changed",
+    "cities_replaced",
+    "lagged",
+];
+
+/// Options for the `dashboard` subcommand
+#[derive(Debug, clap::Args)]
+pub struct DashboardArgs {
+    /// Cities to track (default: every city the server knows)
+    #[arg(num_args = 0..=20)]
+    pub cities: Vec<String>,
+
+    /// How often to refresh while live updates from `GET /events` are
+    /// unavailable, e.g. `5s`, `1m`
+    #[arg(long, default_value = "5s", value_parser = crate::duration::parse_duration)]
+    pub interval: Duration,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsCities {
+    cities: Vec<WeatherData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    City,
+    Temperature,
+    Humidity,
+    Wind,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::City => SortKey::Temperature,
+            SortKey::Temperature => SortKey::Humidity,
+            SortKey::Humidity => SortKey::Wind,
+            SortKey::Wind => SortKey::City,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::City => "city",
+            SortKey::Temperature => "temperature",
+            SortKey::Humidity => "humidity",
+            SortKey::Wind => "wind",
+        }
+    }
+}
+
+/// Dashboard state between redraws
+struct App {
+    rows: Vec<WeatherData>,
+    history: HashMap<String, VecDeque<i32>>,
+    sort: SortKey,
+    descending: bool,
+    table: TableState,
+    last_update: Option<chrono::DateTime<chrono::Local>>,
+    error: Option<String>,
+    /// Whether the event stream is connected (otherwise the table is polled)
+    live: bool,
+}
+
+impl App {
+    fn new() -> Self {
+        App {
+            rows: Vec::new(),
+            history: HashMap::new(),
+            sort: SortKey::City,
+            descending: false,
+            table: TableState::default().with_selected(0),
+            last_update: None,
+            error: None,
+            live: false,
+        }
+    }
+
+    fn update(&mut self, rows: Vec<WeatherData>) {
+        for row in &rows {
+            let samples = self.history.entry(row.city.clone()).or_default();
+            if samples.len() == HISTORY_LEN {
+                samples.pop_front();
+            }
+            samples.push_back(row.temperature);
+        }
+        self.rows = rows;
+        self.last_update = Some(chrono::Local::now());
+        self.error = None;
+        self.sort_rows();
+    }
+
+    fn sort_rows(&mut self) {
+        // Keep the same city selected across re-sorts
+        let selected = self.selected().map(|row| row.city.clone());
+
+        match self.sort {
+            SortKey::City => self.rows.sort_by(|a, b| a.city.cmp(&b.city)),
+            SortKey::Temperature => self.rows.sort_by_key(|c| c.temperature),
+            SortKey::Humidity => self.rows.sort_by_key(|c| c.humidity),
+            SortKey::Wind => self.rows.sort_by_key(|c| c.wind_speed),
+        }
+        if self.descending {
+            self.rows.reverse();
+        }
+
+        let index = selected
+            .and_then(|city| self.rows.iter().position(|row| row.city == city))
+            .unwrap_or(0);
+        self.table.select(Some(index));
+    }
+
+    fn selected(&self) -> Option<&WeatherData> {
+        self.table.selected().and_then(|i| self.rows.get(i))
+    }
+}
+
+/// What the event stream tells the dashboard
+#[derive(Debug, Clone, PartialEq)]
+enum Live {
+    Connected,
+    /// The data may have changed: fetch it again
+    Changed,
+    /// The stream ended or could not be opened; poll until it is back
+    Lost,
+}
+
+/// Server-sent events split out of the bytes of a stream, as they arrive
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+    data: String,
+}
+
+impl SseParser {
+    /// The events completed by `chunk`, as `(type, data)`
+    fn feed(&mut self, chunk: &[u8]) -> Vec<(String, String)> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                // A blank line ends an event; keep-alive comments have no fields
+                if !self.event.is_empty() || !self.data.is_empty() {
+                    let event = match std::mem::take(&mut self.event) {
+                        event if event.is_empty() => "message".to_string(),
+                        event => event,
+                    };
+                    events.push((event, std::mem::take(&mut self.data)));
+                }
+            } else if let Some(value) = line.strip_prefix("event:") {
+                self.event = value.trim_start().to_string();
+            } else if let Some(value) = line.strip_prefix("data:") {
+                if !self.data.is_empty() {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value.strip_prefix(' ').unwrap_or(value));
+            }
+        }
+        events
+    }
+}
+
+/// Whether an event calls for fetching the tracked cities again
+fn refreshes(event: &str, data: &str, cities: &[String]) -> bool {
+    if !REFRESH_EVENTS.contains(&event) {
+        return false;
+    }
+    if cities.is_empty() {
+        return true;
+    }
+    // Events about one city only matter when it is tracked
+    let city = serde_json::from_str::<serde_json::Value>(data)
+        .ok()
+        .and_then(|data| data["city"].as_str().map(str::to_string));
+    city.is_none_or(|city| {
+        cities
+            .iter()
+            .any(|tracked| tracked.eq_ignore_ascii_case(&city))
+    })
+}
+
+/// Follow `GET /events`, reconnecting after `retry` whenever the stream is lost
+fn spawn_event_reader(
+    client: reqwest::Client,
+    url: String,
+    cities: Vec<String>,
+    retry: Duration,
+) -> mpsc::UnboundedReceiver<Live> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            // Why the stream was lost does not matter: polling takes over
+            let _ = listen(&client, &url, &cities, &tx).await;
+            if tx.send(Live::Lost).is_err() {
+                return;
+            }
+            tokio::time::sleep(retry).await;
+        }
+    });
+    rx
+}
+
+async fn listen(
+    client: &reqwest::Client,
+    url: &str,
+    cities: &[String],
+    tx: &mpsc::UnboundedSender<Live>,
+) -> Result<(), CliError> {
+    let mut response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "text/event-stream")
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(CliError::from_response(response).await);
+    }
+    if tx.send(Live::Connected).is_err() {
+        return Ok(());
+    }
+    let mut parser = SseParser::default();
+    while let Some(chunk) = response.chunk().await? {
+        for (event, data) in parser.feed(&chunk) {
+            if refreshes(&event, &data, cities) && tx.send(Live::Changed).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run the interactive dashboard until the user quits
+///
+/// The table is fetched again whenever `GET /events` reports new data, and
+/// polled at `--interval` while the stream is unavailable (e.g. an older
+/// server, or a proxy that buffers responses).
+pub async fn run(
+    client: &reqwest::Client,
+    settings: &Settings,
+    args: &DashboardArgs,
+) -> Result<(), CliError> {
+    let mut ticker = tokio::time::interval(args.interval);
+    let mut keys = spawn_key_reader();
+    let mut live = spawn_event_reader(
+        client.clone(),
+        format!("{}/events", settings.server_url),
+        args.cities.clone(),
+        args.interval,
+    );
+    let mut app = App::new();
+
+    let mut terminal = ratatui::init();
+    let result = async {
+        loop {
+            draw(&mut terminal, settings, args, &mut app)?;
+
+            let mut refresh = false;
+            tokio::select! {
+                _ = ticker.tick(), if !app.live => refresh = true,
+                Some(update) = live.recv() => match update {
+                    Live::Connected => {
+                        app.live = true;
+                        refresh = true;
+                    }
+                    Live::Changed => refresh = true,
+                    Live::Lost => {
+                        app.live = false;
+                        ticker.reset();
+                    }
+                },
+                key = keys.recv() => match key {
+                    None | Some(KeyCode::Char('q')) | Some(KeyCode::Esc) => break,
+                    Some(KeyCode::Char('s')) => {
+                        app.sort = app.sort.next();
+                        app.sort_rows();
+                    }
+                    Some(KeyCode::Char('r')) => {
+                        app.descending = !app.descending;
+                        app.sort_rows();
+                    }
+                    Some(KeyCode::Down) | Some(KeyCode::Char('j')) => app.table.select_next(),
+                    Some(KeyCode::Up) | Some(KeyCode::Char('k')) => app.table.select_previous(),
+                    Some(KeyCode::Char('u')) => refresh = true,
+                    Some(_) => {}
+                },
+            }
+            if refresh {
+                match fetch(client, settings, &args.cities).await {
+                    Ok(rows) => app.update(rows),
+                    Err(e) => app.error = Some(e.to_string()),
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+    ratatui::restore();
+
+    result
+}
+
+/// Forward key presses from a blocking reader thread
+fn spawn_key_reader() -> mpsc::UnboundedReceiver<KeyCode> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if tx.send(key.code).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    });
+    rx
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    settings: &Settings,
+    cities: &[String],
+) -> Result<Vec<WeatherData>, CliError> {
+    if cities.is_empty() {
+        let response = client
+            .get(format!("{}/stats", settings.server_url))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(CliError::from_response(response).await);
+        }
+        let stats: StatsCities = response.json().await?;
+        return Ok(stats.cities);
+    }
+
+    let response = client
+        .post(format!("{}/weather", settings.server_url))
+        .json(&WeatherRequest {
+            cities: cities.to_vec(),
+        })
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(CliError::from_response(response).await);
+    }
+    let weather: WeatherResponse = response.json().await?;
+    Ok(weather.data.into_values().collect())
+}
+
+fn draw(
+    terminal: &mut DefaultTerminal,
+    settings: &Settings,
+    args: &DashboardArgs,
+    app: &mut App,
+) -> Result<(), CliError> {
+    terminal
+        .draw(|frame| render(frame, settings, args, app))
+        .map_err(|e| CliError::Internal(format!("cannot draw dashboard: {}", e)))?;
+    Ok(())
+}
+
+fn render(frame: &mut Frame, settings: &Settings, args: &DashboardArgs, app: &mut App) {
+    let [header, table_area, chart_area, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(5),
+        Constraint::Length(8),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let updated = app
+        .last_update
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".to_string());
+    let status = match &app.error {
+        Some(error) => format!("  ❌ {}", error).red(),
+        None => "".into(),
+    };
+    frame.render_widget(
+        Line::from(vec![
+            "🦀 Weather dashboard ".bold(),
+            format!(
+                "{} · {} · updated {} · sorted by {}{}",
+                settings.server_url,
+                if app.live {
+                    "live".to_string()
+                } else {
+                    format!("every {:?}", args.interval)
+                },
+                updated,
+                app.sort.label(),
+                if app.descending { " ↓" } else { " ↑" }
+            )
+            .into(),
+            status,
+        ]),
+        header,
+    );
+
+    let rows = app.rows.iter().map(|data| {
+        Row::new(vec![
+            Cell::from(data.city.clone()),
+            Cell::from(format_temp(data.temperature as f32, settings.units)),
+            Cell::from(data.condition_text().to_string()),
+            Cell::from(format!("{}%", data.humidity)),
+            Cell::from(format_wind(data.wind_speed, settings.units)),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(25),
+            Constraint::Percentage(15),
+            Constraint::Percentage(30),
+            Constraint::Percentage(12),
+            Constraint::Percentage(18),
+        ],
+    )
+    .header(
+        Row::new(["City", "Temperature", "Condition", "Humidity", "Wind"])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+    .block(Block::bordered().title(format!(" {} cities ", app.rows.len())));
+    frame.render_stateful_widget(table, table_area, &mut app.table);
+
+    let (title, samples) = match app.selected() {
+        Some(row) => {
+            let history = app.history.get(&row.city);
+            let min = history.and_then(|h| h.iter().min().copied()).unwrap_or(0);
+            let max = history.and_then(|h| h.iter().max().copied()).unwrap_or(0);
+            // Sparklines need non-negative values; shift so the minimum is 1
+            let samples: Vec<u64> = history
+                .map(|h| h.iter().map(|t| (t - min + 1) as u64).collect())
+                .unwrap_or_default();
+            (
+                format!(
+                    " {} temperature (min {}, max {}) ",
+                    row.city,
+                    format_temp(min as f32, settings.units),
+                    format_temp(max as f32, settings.units)
+                ),
+                samples,
+            )
+        }
+        None => (" temperature ".to_string(), Vec::new()),
+    };
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(title))
+            .data(&samples),
+        chart_area,
+    );
+
+    frame.render_widget(
+        Paragraph::new("q quit · ↑/↓ select · s sort column · r reverse · u refresh now").dim(),
+        footer,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_events_are_parsed_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser
+            .feed(b"event: city_changed\r\ndata: {\"city\":")
+            .is_empty());
+        let events = parser.feed(b"\"rome\"}\r\n\r\n: keep-alive\n\nevent: lagged\ndata: {}\n\n");
+        assert_eq!(
+            events,
+            vec![
+                (
+                    "city_changed".to_string(),
+                    "{\"city\":\"rome\"}".to_string()
+                ),
+                ("lagged".to_string(), "{}".to_string()),
+            ]
+        );
+
+        let tracked = ["Rome".to_string()];
+        assert!(refreshes("city_changed", &events[0].1, &tracked));
+        assert!(!refreshes("city_changed", "{\"city\":\"oslo\"}", &tracked));
+        assert!(refreshes("history_recorded", "{\"cities\":3}", &tracked));
+        assert!(!refreshes("alert_fired", "{\"city\":\"rome\"}", &[]));
+    }
+}
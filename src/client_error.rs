@@ -0,0 +1,172 @@
+use crate::client_config::ConfigError;
+use serde_json::json;
+use std::fmt;
+use std::process::ExitCode;
+
+/// Everything that can make the client fail, grouped by how scripts should react
+///
+/// Each variant maps to a distinct, stable process exit code:
+///
+/// | code | meaning                                   |
+/// |------|-------------------------------------------|
+/// | 0    | success                                   |
+/// | 1    | unexpected internal error                 |
+/// | 2    | invalid usage or configuration            |
+/// | 3    | server unreachable (connect/timeout)      |
+/// | 4    | server answered with an HTTP error status |
+/// | 5    | unknown city requested in `--strict` mode |
+/// | 6    | server response could not be decoded      |
+#[derive(Debug)]
+pub enum CliError {
+    Config(String),
+    Connection(String),
+    Http { status: u16, body: String },
+    UnknownCities(Vec<String>),
+    InvalidResponse(String),
+    Internal(String),
+}
+
+impl CliError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::Config(_) => "config",
+            CliError::Connection(_) => "connection",
+            CliError::Http { .. } => "http",
+            CliError::UnknownCities(_) => "unknown_city",
+            CliError::InvalidResponse(_) => "invalid_response",
+            CliError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            CliError::Internal(_) => 1,
+            CliError::Config(_) => 2,
+            CliError::Connection(_) => 3,
+            CliError::Http { .. } => 4,
+            CliError::UnknownCities(_) => 5,
+            CliError::InvalidResponse(_) => 6,
+        }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.code())
+    }
+
+    /// Machine-readable form used with `--output json`
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut error = json!({
+            "kind": self.kind(),
+            "message": self.to_string(),
+            "exit_code": self.code(),
+        });
+        match self {
+            CliError::Http { status, .. } => error["status"] = json!(status),
+            CliError::UnknownCities(cities) => error["cities"] = json!(cities),
+            _ => {}
+        }
+        json!({ "error": error })
+    }
+
+    /// Turn an error response from the server into a `CliError`
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        CliError::Http { status, body }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Config(msg) => write!(f, "configuration error: {}", msg),
+            CliError::Connection(msg) => write!(f, "cannot reach server: {}", msg),
+            CliError::Http { status, body } if body.is_empty() => {
+                write!(f, "server returned HTTP {}", status)
+            }
+            CliError::Http { status, body } => {
+                write!(f, "server returned HTTP {}: {}", status, body)
+            }
+            CliError::UnknownCities(cities) => write!(f, "unknown cities: {}", cities.join(", ")),
+            CliError::InvalidResponse(msg) => write!(f, "invalid server response: {}", msg),
+            CliError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<reqwest::Error> for CliError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_connect() || error.is_timeout() {
+            CliError::Connection(error.to_string())
+        } else if let Some(status) = error.status() {
+            CliError::Http {
+                status: status.as_u16(),
+                body: String::new(),
+            }
+        } else if error.is_decode() {
+            CliError::InvalidResponse(error.to_string())
+        } else if error.is_builder() {
+            CliError::Config(error.to_string())
+        } else {
+            CliError::Connection(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(error: serde_json::Error) -> Self {
+        CliError::InvalidResponse(error.to_string())
+    }
+}
+
+impl From<ConfigError> for CliError {
+    fn from(error: ConfigError) -> Self {
+        CliError::Config(error.to_string())
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for CliError {
+    fn from(error: reqwest::header::InvalidHeaderValue) -> Self {
+        CliError::Config(format!("credential is not a valid header value: {}", error))
+    }
+}
+
+impl From<tokio::task::JoinError> for CliError {
+    fn from(error: tokio::task::JoinError) -> Self {
+        CliError::Internal(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            CliError::Internal(String::new()),
+            CliError::Config(String::new()),
+            CliError::Connection(String::new()),
+            CliError::Http {
+                status: 500,
+                body: String::new(),
+            },
+            CliError::UnknownCities(vec![]),
+            CliError::InvalidResponse(String::new()),
+        ];
+        let mut codes: Vec<u8> = errors.iter().map(CliError::code).collect();
+        codes.dedup();
+        assert_eq!(codes, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_json_error_format() {
+        let error = CliError::UnknownCities(vec!["Atlantis".to_string()]);
+        let json = error.to_json();
+        assert_eq!(json["error"]["kind"], "unknown_city");
+        assert_eq!(json["error"]["exit_code"], 5);
+        assert_eq!(json["error"]["cities"][0], "Atlantis");
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::icons::Icon;
+
+/// Weather condition taxonomy
+///
+/// Serialized as a stable snake_case code (`partly_cloudy`). Finer free-text
+/// wording, such as the built-in data's "Hot & Sunny", is kept separately as
+/// a description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Overcast,
+    Rain,
+    Snow,
+    Fog,
+    Haze,
+    Wind,
+    Hot,
+    Humid,
+    Cold,
+    Variable,
+    Unknown,
+}
+
+/// Free-text conditions of the city data that are not a condition's own
+/// label, with the condition each one belongs to
+const LEGACY_LABELS: &[(&str, Condition)] = &[
+    ("Sunny", Condition::Clear),
+    ("Pleasant", Condition::Clear),
+    ("Warm", Condition::Clear),
+    ("Rainy", Condition::Rain),
+    ("Snowy", Condition::Snow),
+    ("Foggy", Condition::Fog),
+    ("Hazy", Condition::Haze),
+    ("Smoggy", Condition::Haze),
+    ("Windy", Condition::Wind),
+    ("Hot & Sunny", Condition::Hot),
+    ("Very Hot", Condition::Hot),
+    ("Hot & Humid", Condition::Humid),
+    ("Tropical", Condition::Humid),
+];
+
+impl Condition {
+    pub const ALL: [Condition; 14] = [
+        Condition::Clear,
+        Condition::PartlyCloudy,
+        Condition::Cloudy,
+        Condition::Overcast,
+        Condition::Rain,
+        Condition::Snow,
+        Condition::Fog,
+        Condition::Haze,
+        Condition::Wind,
+        Condition::Hot,
+        Condition::Humid,
+        Condition::Cold,
+        Condition::Variable,
+        Condition::Unknown,
+    ];
+
+    /// Stable machine-readable code, as serialized
+    pub fn code(self) -> &'static str {
+        match self {
+            Condition::Clear => "clear",
+            Condition::PartlyCloudy => "partly_cloudy",
+            Condition::Cloudy => "cloudy",
+            Condition::Overcast => "overcast",
+            Condition::Rain => "rain",
+            Condition::Snow => "snow",
+            Condition::Fog => "fog",
+            Condition::Haze => "haze",
+            Condition::Wind => "wind",
+            Condition::Hot => "hot",
+            Condition::Humid => "humid",
+            Condition::Cold => "cold",
+            Condition::Variable => "variable",
+            Condition::Unknown => "unknown",
+        }
+    }
+
+    /// English display name
+    pub fn label(self) -> &'static str {
+        match self {
+            Condition::Clear => "Clear",
+            Condition::PartlyCloudy => "Partly Cloudy",
+            Condition::Cloudy => "Cloudy",
+            Condition::Overcast => "Overcast",
+            Condition::Rain => "Rain",
+            Condition::Snow => "Snow",
+            Condition::Fog => "Fog",
+            Condition::Haze => "Haze",
+            Condition::Wind => "Wind",
+            Condition::Hot => "Hot",
+            Condition::Humid => "Humid",
+            Condition::Cold => "Cold",
+            Condition::Variable => "Variable",
+            Condition::Unknown => "Unknown",
+        }
+    }
+
+    pub fn icon(self) -> Icon {
+        match self {
+            Condition::Clear => Icon::Clear,
+            Condition::PartlyCloudy => Icon::PartlyCloudy,
+            Condition::Cloudy | Condition::Overcast => Icon::Cloudy,
+            Condition::Rain => Icon::Rain,
+            Condition::Snow => Icon::Snow,
+            Condition::Fog => Icon::Fog,
+            Condition::Haze => Icon::Haze,
+            Condition::Wind => Icon::Wind,
+            Condition::Hot => Icon::Hot,
+            Condition::Humid => Icon::Humid,
+            Condition::Cold => Icon::Cold,
+            Condition::Variable => Icon::Variable,
+            Condition::Unknown => Icon::Unknown,
+        }
+    }
+
+    /// A code or label, ignoring case (`partly_cloudy`, `Partly Cloudy`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Condition::ALL.into_iter().find(|condition| {
+            condition.code().eq_ignore_ascii_case(name)
+                || condition.label().eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Classify free text from city data
+    ///
+    /// Returns the condition and, unless the text is just the condition's
+    /// code or label, the text itself as the description. Text that matches
+    /// nothing is `Unknown`, described by the text.
+    pub fn classify(text: Cow<'static, str>) -> (Self, Option<Cow<'static, str>>) {
+        if let Some(condition) = Condition::from_name(&text) {
+            return (condition, None);
+        }
+        let condition = LEGACY_LABELS
+            .iter()
+            .find(|(label, _)| label.eq_ignore_ascii_case(text.trim()))
+            .map_or(Condition::Unknown, |(_, condition)| *condition);
+        (condition, Some(text))
+    }
+
+    /// The most frequent of `conditions` (first by code on ties)
+    pub fn most_common(conditions: impl IntoIterator<Item = Condition>) -> Option<Self> {
+        let mut counts: HashMap<Condition, usize> = HashMap::new();
+        for condition in conditions {
+            *counts.entry(condition).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.code().cmp(a.0.code())))
+            .map(|(condition, _)| condition)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_free_text() {
+        assert_eq!(
+            Condition::classify("Partly Cloudy".into()),
+            (Condition::PartlyCloudy, None)
+        );
+        assert_eq!(
+            Condition::classify("partly_cloudy".into()),
+            (Condition::PartlyCloudy, None)
+        );
+        assert_eq!(
+            Condition::classify("Hot & Sunny".into()),
+            (Condition::Hot, Some("Hot & Sunny".into()))
+        );
+        assert_eq!(
+            Condition::classify("Misty".into()),
+            (Condition::Unknown, Some("Misty".into()))
+        );
+        assert_eq!(
+            serde_json::to_value(Condition::PartlyCloudy).unwrap(),
+            "partly_cloudy"
+        );
+    }
+}
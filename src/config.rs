@@ -0,0 +1,443 @@
+use clap::Parser;
+use serde::Serialize;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::duration::parse_duration;
+use crate::events::SlowSubscriberPolicy;
+use crate::history::METRICS;
+use crate::mcp_session::Envelope;
+use crate::rounding::{RoundingMode, TemperatureRounding};
+use crate::stations::Aggregation;
+
+/// Server configuration, read from command-line flags or `WEATHER_*` environment variables
+#[derive(Debug, Clone, Parser)]
+#[command(name = "server", version, about = "Rust Weather API server")]
+pub struct ServerConfig {
+    /// Directory served under `/static` (disabled when unset)
+    #[arg(long, env = "WEATHER_STATIC_DIR")]
+    pub static_dir: Option<PathBuf>,
+
+    /// Serve `<static-dir>/index.html` for unknown paths under `/static`,
+    /// so single-page apps can use client-side routing
+    #[arg(long, env = "WEATHER_STATIC_SPA", requires = "static_dir")]
+    pub spa_fallback: bool,
+
+    /// Vary the built-in values with a deterministic weather simulation
+    /// (seasons, day/night cycle and a bounded random walk)
+    #[arg(long, env = "WEATHER_SIMULATE")]
+    pub simulate: bool,
+
+    /// Seed for the simulation and any other randomized behavior;
+    /// the same seed always reproduces the same data
+    #[arg(long, env = "WEATHER_SEED", default_value_t = 0)]
+    pub seed: u64,
+
+    /// JSON or CSV file replacing the built-in city list
+    /// (fields: name, temperature, condition, humidity, wind_speed,
+    /// and optionally latitude and longitude)
+    #[arg(long, env = "WEATHER_CITIES_FILE")]
+    pub cities_file: Option<PathBuf>,
+
+    /// TOML file of API keys with optional daily and monthly quotas; when
+    /// set, every endpoint except the health check requires `X-API-Key`
+    #[arg(long, env = "WEATHER_API_KEYS_FILE")]
+    pub api_keys_file: Option<PathBuf>,
+
+    /// TOML file of `[[rule]]` entries replacing the built-in table that
+    /// infers conditions from raw measurements (simulated precipitation)
+    #[arg(long, env = "WEATHER_CONDITION_RULES")]
+    pub condition_rules: Option<PathBuf>,
+
+    /// JSON file keeping favorites, digests and webhooks across restarts;
+    /// created on the first change, kept in memory only when unset
+    #[arg(long, env = "WEATHER_DATA_FILE")]
+    pub data_file: Option<PathBuf>,
+
+    /// MaxMind GeoIP2/GeoLite2 City database enabling `GET /weather/me`
+    #[cfg(feature = "geoip")]
+    #[arg(long, env = "WEATHER_GEOIP_DB")]
+    pub geoip_db: Option<PathBuf>,
+
+    /// Locate `/weather/me` callers by the first `X-Forwarded-For` address;
+    /// only enable behind a proxy that sets it
+    #[cfg(feature = "geoip")]
+    #[arg(long, env = "WEATHER_TRUST_FORWARDED_FOR")]
+    pub trust_forwarded_for: bool,
+
+    /// Bearer token for the `/admin` endpoints (disabled when unset)
+    #[arg(long, env = "WEATHER_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// Secret of the first key signing webhook deliveries (and MCP results
+    /// with `--sign-mcp`); more keys can be added through the admin API
+    #[arg(long, env = "WEATHER_SIGNING_SECRET", hide_env_values = true)]
+    pub signing_secret: Option<String>,
+
+    /// Host allowed as a digest or city webhook even though it is not
+    /// public, e.g. `localhost` or an internal name (repeatable;
+    /// comma-separated in the environment variable)
+    #[arg(
+        long = "webhook-allow-host",
+        env = "WEATHER_WEBHOOK_ALLOW_HOSTS",
+        value_delimiter = ','
+    )]
+    pub webhook_allow_hosts: Vec<String>,
+
+    /// Sign MCP tool results as well as webhook deliveries
+    #[arg(long, env = "WEATHER_SIGN_MCP")]
+    pub sign_mcp: bool,
+
+    /// Shape of MCP tool results for clients that send neither a session
+    /// nor `MCP-Protocol-Version`: `flat` (the original `results` map) or
+    /// `content` (content blocks, MCP 2025-06-18)
+    #[arg(long, env = "WEATHER_MCP_ENVELOPE", value_enum, default_value_t = Envelope::Flat)]
+    pub mcp_envelope: Envelope,
+
+    /// How long (in seconds) responses to requests with an `Idempotency-Key`
+    /// are kept for replay
+    #[arg(long, env = "WEATHER_IDEMPOTENCY_TTL", default_value_t = 86_400)]
+    pub idempotency_ttl: u64,
+
+    /// Maximum number of requests handled at once; requests beyond it are
+    /// rejected with 503 and `Retry-After` instead of queueing
+    #[arg(long, env = "WEATHER_MAX_CONCURRENCY", default_value = "512")]
+    pub max_concurrency: NonZeroUsize,
+
+    /// Timeout for routes without a specific `--route-timeout`, e.g. `30s`
+    #[arg(long, env = "WEATHER_REQUEST_TIMEOUT", default_value = "30s", value_parser = parse_duration)]
+    pub request_timeout: Duration,
+
+    /// Per-route timeout as `<route>=<duration>`, e.g. `/weather=10s`
+    /// (repeatable; comma-separated in the environment variable)
+    #[arg(
+        long = "route-timeout",
+        env = "WEATHER_ROUTE_TIMEOUTS",
+        value_delimiter = ',',
+        value_parser = parse_route_timeout
+    )]
+    pub route_timeouts: Vec<RouteTimeout>,
+
+    /// Maximum number of open client connections; further clients wait in
+    /// the TCP backlog until one closes
+    #[arg(long, env = "WEATHER_MAX_CONNECTIONS", default_value = "1024")]
+    pub max_connections: NonZeroUsize,
+
+    /// How long an idle keep-alive connection is held open waiting for the
+    /// next request, e.g. `60s`
+    #[arg(long, env = "WEATHER_KEEP_ALIVE_TIMEOUT", default_value = "60s", value_parser = parse_duration)]
+    pub keep_alive_timeout: Duration,
+
+    /// Length of the kernel's queue of not-yet-accepted connections
+    #[arg(long, env = "WEATHER_TCP_BACKLOG", default_value_t = 1024)]
+    pub tcp_backlog: u32,
+
+    /// How often every city's weather is recorded into the history, e.g. `5m`
+    #[arg(long, env = "WEATHER_HISTORY_INTERVAL", default_value = "5m", value_parser = parse_duration)]
+    pub history_interval: Duration,
+
+    /// Samples kept per city (288 samples at 5m cover a day); older ones
+    /// are rolled up into hourly points
+    #[arg(long, env = "WEATHER_HISTORY_LEN", default_value = "288")]
+    pub history_len: NonZeroUsize,
+
+    /// How long raw history samples are kept before being rolled up into
+    /// hourly points (also bounded by `--history-len`)
+    #[arg(long, env = "WEATHER_HISTORY_RAW_RETENTION", default_value = "7d", value_parser = parse_duration)]
+    pub history_raw_retention: Duration,
+
+    /// How long hourly history points are kept before being rolled up into
+    /// daily points
+    #[arg(long, env = "WEATHER_HISTORY_HOURLY_RETENTION", default_value = "90d", value_parser = parse_duration)]
+    pub history_hourly_retention: Duration,
+
+    /// How long daily history points are kept (forever when unset)
+    #[arg(long, env = "WEATHER_HISTORY_DAILY_RETENTION", value_parser = parse_duration)]
+    pub history_daily_retention: Option<Duration>,
+
+    /// How often the history is compacted according to the retention settings
+    #[arg(long, env = "WEATHER_HISTORY_COMPACTION_INTERVAL", default_value = "1h", value_parser = parse_duration)]
+    pub history_compaction_interval: Duration,
+
+    /// Update events buffered for slow subscribers (such as `GET /events`);
+    /// those further behind skip the oldest ones
+    #[arg(long, env = "WEATHER_EVENT_BUFFER", default_value = "1024")]
+    pub event_buffer: NonZeroUsize,
+
+    /// Update events queued per client connection (`GET /events`); a client
+    /// with a full queue is too slow for the stream
+    #[arg(long, env = "WEATHER_EVENT_QUEUE", default_value = "64")]
+    pub event_queue: NonZeroUsize,
+
+    /// What to do with a client whose event queue is full: `drop` events
+    /// (it gets a `lagged` event) or `disconnect` it
+    #[arg(
+        long,
+        env = "WEATHER_SLOW_SUBSCRIBER_POLICY",
+        value_enum,
+        default_value_t = SlowSubscriberPolicy::Drop
+    )]
+    pub slow_subscriber_policy: SlowSubscriberPolicy,
+
+    /// Keep a summary of every request (route, query, status, latency and
+    /// error message) for `GET /admin/requests`
+    #[arg(long, env = "WEATHER_REQUEST_LOG")]
+    pub request_log: bool,
+
+    /// Precompute cached responses at startup; `GET /ready` answers 503
+    /// until this has finished
+    #[arg(long, env = "WEATHER_WARM_UP")]
+    pub warm_up: bool,
+
+    /// How long request summaries are kept, e.g. `1h`
+    #[arg(long, env = "WEATHER_REQUEST_LOG_RETENTION", default_value = "1h", value_parser = parse_duration)]
+    pub request_log_retention: Duration,
+
+    /// Most request summaries kept; the oldest are dropped first
+    #[arg(long, env = "WEATHER_REQUEST_LOG_MAX_ENTRIES", default_value = "10000")]
+    pub request_log_max_entries: NonZeroUsize,
+
+    /// Readings further than this many standard deviations from the city's
+    /// recent mean are reported at `/anomalies`
+    #[arg(long, env = "WEATHER_ANOMALY_THRESHOLD", default_value_t = 3.0)]
+    pub anomaly_threshold: f64,
+
+    /// Rate-of-change alert as `<metric><-|+|*><amount>/<window>`, e.g.
+    /// `temperature-5/3h` (dropped more than 5 in 3 hours) or
+    /// `wind_speed*2/1h` (at least doubled within an hour); reported at
+    /// `/alerts` (repeatable; comma-separated in the environment variable)
+    #[arg(
+        long = "alert-rule",
+        env = "WEATHER_ALERT_RULES",
+        value_delimiter = ',',
+        default_values = ["temperature-5/3h", "wind_speed*2/1h"],
+        value_parser = parse_alert_rule
+    )]
+    pub alert_rules: Vec<AlertRule>,
+
+    /// Default weights of `/rank` scores as `temperature=<w>,humidity=<w>,wind=<w>`
+    /// (omitted factors count zero; requests can override each weight)
+    #[arg(
+        long,
+        env = "WEATHER_RANK_WEIGHTS",
+        default_value = "temperature=3,humidity=1,wind=1",
+        value_parser = parse_rank_weights
+    )]
+    pub rank_weights: RankWeights,
+
+    /// Decimal places of computed temperatures: `/stats` and summary
+    /// averages, climate normals and anomaly means
+    #[arg(
+        long,
+        env = "WEATHER_TEMPERATURE_PRECISION",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(0..=3)
+    )]
+    pub temperature_precision: u8,
+
+    /// How temperature ties are rounded: `half-away-from-zero` (2.25 → 2.3)
+    /// or `half-even` (bankers rounding, 2.25 → 2.2)
+    #[arg(
+        long,
+        env = "WEATHER_TEMPERATURE_ROUNDING",
+        value_enum,
+        default_value_t = RoundingMode::HalfAwayFromZero
+    )]
+    pub temperature_rounding: RoundingMode,
+
+    /// How the readings of a city's stations become the city's reading:
+    /// `mean`, `median` or `primary` (the first station); cities can
+    /// choose their own with `aggregation`
+    #[arg(
+        long,
+        env = "WEATHER_STATION_AGGREGATION",
+        value_enum,
+        default_value_t = Aggregation::Mean
+    )]
+    pub station_aggregation: Aggregation,
+
+    /// Development only: inject latency, 500s and malformed bodies into
+    /// responses so clients can test their error handling
+    #[arg(long, env = "WEATHER_CHAOS")]
+    pub chaos: bool,
+
+    /// Longest delay added to each response in chaos mode, e.g. `500ms`
+    /// (each response waits a random time up to this)
+    #[arg(long, env = "WEATHER_CHAOS_LATENCY", value_parser = parse_duration, requires = "chaos")]
+    pub chaos_latency: Option<Duration>,
+
+    /// Percentage of responses replaced by a 500 error in chaos mode
+    #[arg(long, env = "WEATHER_CHAOS_ERROR_RATE", default_value = "10", value_parser = parse_percent)]
+    pub chaos_error_rate: f64,
+
+    /// Percentage of responses whose JSON body is truncated in chaos mode
+    #[arg(long, env = "WEATHER_CHAOS_MALFORMED_RATE", default_value = "5", value_parser = parse_percent)]
+    pub chaos_malformed_rate: f64,
+}
+
+fn parse_percent(input: &str) -> Result<f64, String> {
+    let value: f64 = input
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid percentage '{}'", input))?;
+    if !(0.0..=100.0).contains(&value) {
+        return Err(format!("percentage {} is outside 0..=100", value));
+    }
+    Ok(value)
+}
+
+/// Timeout override for one route, identified by its pattern (`/admin/cities/:name`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteTimeout {
+    pub path: String,
+    pub timeout: Duration,
+}
+
+fn parse_route_timeout(input: &str) -> Result<RouteTimeout, String> {
+    let (path, timeout) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected <route>=<duration>, got '{}'", input))?;
+    let path = path.trim();
+    if !path.starts_with('/') {
+        return Err(format!("route '{}' must start with '/'", path));
+    }
+
+    Ok(RouteTimeout {
+        path: path.to_string(),
+        timeout: parse_duration(timeout)?,
+    })
+}
+
+/// Rate-of-change condition on one history metric, e.g. `temperature-5/3h`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    /// The rule as written
+    pub spec: String,
+    /// `temperature`, `humidity` or `wind_speed`
+    pub metric: &'static str,
+    pub change: Change,
+    /// How far back the latest reading is compared
+    pub window: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// Fell by more than this
+    Drop(f64),
+    /// Rose by more than this
+    Rise(f64),
+    /// Grew to at least this multiple
+    Factor(f64),
+}
+
+fn parse_alert_rule(input: &str) -> Result<AlertRule, String> {
+    let spec = input.trim();
+    let invalid = || format!("expected <metric><-|+|*><amount>/<window>, got '{}'", spec);
+    let (condition, window) = spec.split_once('/').ok_or_else(invalid)?;
+    let (metric, amount) = condition
+        .find(['-', '+', '*'])
+        .map(|at| condition.split_at(at))
+        .ok_or_else(invalid)?;
+
+    let metric = METRICS
+        .iter()
+        .map(|(name, _)| *name)
+        .find(|name| *name == metric)
+        .ok_or_else(|| {
+            format!(
+                "unknown metric '{}' (use temperature, humidity or wind_speed)",
+                metric
+            )
+        })?;
+    let value: f64 = amount[1..]
+        .parse()
+        .map_err(|_| format!("invalid amount in '{}'", spec))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(format!("amount in '{}' must be greater than zero", spec));
+    }
+    let change = match &amount[..1] {
+        "-" => Change::Drop(value),
+        "+" => Change::Rise(value),
+        _ if value <= 1.0 => {
+            return Err(format!("factor in '{}' must be greater than 1", spec));
+        }
+        _ => Change::Factor(value),
+    };
+
+    Ok(AlertRule {
+        spec: spec.to_string(),
+        metric,
+        change,
+        window: parse_duration(window)?,
+    })
+}
+
+/// How much each factor counts towards a `/rank` score
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RankWeights {
+    /// Closeness to the ideal temperature
+    pub temperature: f64,
+    /// Closeness to the ideal humidity
+    pub humidity: f64,
+    /// Calm wind
+    pub wind: f64,
+}
+
+impl RankWeights {
+    /// An error unless every weight is non-negative and at least one counts
+    pub fn validate(&self) -> Result<(), String> {
+        let weights = [self.temperature, self.humidity, self.wind];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("weights must not be negative".to_string());
+        }
+        if weights.iter().sum::<f64>() == 0.0 {
+            return Err("at least one weight must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn parse_rank_weights(input: &str) -> Result<RankWeights, String> {
+    let mut weights = RankWeights {
+        temperature: 0.0,
+        humidity: 0.0,
+        wind: 0.0,
+    };
+    for pair in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (factor, weight) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected <factor>=<weight>, got '{}'", pair))?;
+        let weight: f64 = weight
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid weight in '{}'", pair))?;
+        match factor.trim() {
+            "temperature" => weights.temperature = weight,
+            "humidity" => weights.humidity = weight,
+            "wind" => weights.wind = weight,
+            other => {
+                return Err(format!(
+                    "unknown factor '{}' (use temperature, humidity or wind)",
+                    other
+                ))
+            }
+        }
+    }
+    weights.validate()?;
+    Ok(weights)
+}
+
+impl ServerConfig {
+    pub fn load() -> Self {
+        ServerConfig::parse()
+    }
+
+    pub fn rounding(&self) -> TemperatureRounding {
+        TemperatureRounding {
+            decimals: self.temperature_precision,
+            mode: self.temperature_rounding,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{header, request::Parts, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::api_keys::Caller;
+use crate::locale::Locale;
+use crate::{trace, AppState, ErrorResponse};
+
+/// Per-request options shared by every endpoint
+#[derive(Debug, Deserialize)]
+struct ContextQuery {
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    debug: bool,
+}
+
+/// How to answer one request, resolved once before the handler runs
+///
+/// Handlers take this instead of parsing `?strict=`, `?debug=`, the locale
+/// or the API key themselves:
+///
+/// - `locale`: language and units from `Accept-Language`, `?lang=` and
+///   `?units=` (see [`crate::locale::negotiate`])
+/// - `strict`: from `?strict=true` or `Prefer: handling=strict`
+/// - `debug`: from `?debug=true`, which needs the admin token
+/// - `caller`: the API key's name, when keys are configured
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequestContext {
+    pub locale: Locale,
+    /// Unknown cities are an error instead of default values
+    pub strict: bool,
+    /// Attach a trace of how the request was answered
+    pub debug: bool,
+    pub caller: Option<String>,
+}
+
+impl RequestContext {
+    /// ` (key: <name>)` for log lines, empty without API keys
+    pub fn caller_suffix(&self) -> String {
+        self.caller
+            .as_ref()
+            .map_or_else(String::new, |name| format!(" (key: {})", name))
+    }
+}
+
+/// Whether a `Prefer` header asks for `handling=strict` (RFC 7240)
+fn prefers_strict(prefer: &str) -> bool {
+    prefer
+        .split(',')
+        .any(|preference| preference.trim().eq_ignore_ascii_case("handling=strict"))
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for RequestContext {
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<ContextQuery>::try_from_uri(&parts.uri).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.body_text(),
+                    code: 400,
+                }),
+            )
+        })?;
+        let strict = query.strict
+            || parts
+                .headers
+                .get_all(header::HeaderName::from_static("prefer"))
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .any(prefers_strict);
+
+        Ok(RequestContext {
+            locale: parts
+                .extensions
+                .get::<Locale>()
+                .copied()
+                .unwrap_or_default(),
+            strict,
+            debug: trace::requested(state, &parts.headers, query.debug)?,
+            caller: parts
+                .extensions
+                .get::<Caller>()
+                .map(|caller| caller.name.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefer_handling_strict() {
+        assert!(prefers_strict("handling=strict"));
+        assert!(prefers_strict("return=minimal, Handling=Strict"));
+        assert!(!prefers_strict("handling=lenient"));
+        assert!(!prefers_strict("respond-async"));
+    }
+}
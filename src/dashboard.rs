@@ -0,0 +1,80 @@
+use chrono::Utc;
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+
+use crate::context::RequestContext;
+use crate::locale::Locale;
+
+// Stats are computed by the same code that serves GET /stats
+use crate::tenants::Scoped;
+use crate::{compute_stats, WeatherData};
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 0; background: #f5f8fb; color: #1d2b3a; }
+header { background: #1f4e79; color: #fff; padding: 1.5rem 2rem; }
+header h1 { margin: 0; font-size: 1.5rem; }
+header p { margin: 0.25rem 0 0; opacity: 0.8; }
+main { padding: 2rem; max-width: 1100px; margin: 0 auto; }
+table.stats { border-collapse: collapse; margin-bottom: 2rem; background: #fff; }
+table.stats th, table.stats td { text-align: left; padding: 0.5rem 1rem; border-bottom: 1px solid #e3e9ef; }
+.cards { display: grid; grid-template-columns: repeat(auto-fill, minmax(180px, 1fr)); gap: 1rem; }
+.card { background: #fff; border-radius: 8px; padding: 1rem; box-shadow: 0 1px 3px rgba(0,0,0,0.08); }
+.card h2 { margin: 0 0 0.5rem; font-size: 1.05rem; text-transform: capitalize; }
+.card .temp { font-size: 2rem; font-weight: 600; }
+.card .meta { color: #5b6b7b; font-size: 0.85rem; }
+"#;
+
+/// Server-rendered HTML dashboard with the current stats and per-city cards
+///
+/// Units, conditions and the date follow the request's locale.
+pub async fn dashboard(
+    Scoped(state): Scoped,
+    RequestContext { locale, .. }: RequestContext,
+) -> Markup {
+    println!("🖥️  Received dashboard request");
+
+    let stats = compute_stats(&state, "name");
+
+    html! {
+        (DOCTYPE)
+        html lang=(locale.language.code()) {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { "Rust Weather API – Dashboard" }
+                style { (PreEscaped(STYLE)) }
+            }
+            body {
+                header {
+                    h1 { "🦀 Rust Weather API" }
+                    p { "Live view of " (stats.total_cities) " cities · " (locale.date(Utc::now().date_naive())) }
+                }
+                main {
+                    table.stats {
+                        tr { th { "Cities" } td { (stats.total_cities) } }
+                        tr { th { "Average temperature" } td { (stats.average_temp.map_or("–".to_string(), |t| locale.average_temperature(t, state.config.temperature_precision))) } }
+                        tr { th { "Hottest city" } td { (stats.hottest_city.as_deref().unwrap_or("–")) } }
+                        tr { th { "Coldest city" } td { (stats.coldest_city.as_deref().unwrap_or("–")) } }
+                        tr { th { "Most humid city" } td { (stats.most_humid_city.as_deref().unwrap_or("–")) } }
+                        tr { th { "Windiest city" } td { (stats.windiest_city.as_deref().unwrap_or("–")) } }
+                    }
+                    div.cards {
+                        @for city in &stats.cities {
+                            (city_card(city, &locale))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn city_card(data: &WeatherData, locale: &Locale) -> Markup {
+    html! {
+        div.card {
+            h2 { (data.city) }
+            div.temp { (locale.temperature(data.temperature)) }
+            div { (data.icon.emoji()) " " (locale.condition(data.condition, data.description.as_deref())) }
+            div.meta { "💧 " (data.humidity) "% · 💨 " (locale.wind(data.wind_speed)) }
+        }
+    }
+}
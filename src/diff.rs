@@ -0,0 +1,373 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::conditions::Condition;
+use crate::duration::parse_duration;
+use crate::history::{CityTiers, Resolution, Rollup};
+use crate::rounding::TemperatureRounding;
+use crate::tenants::Scoped;
+use crate::{error_response, ErrorResponse};
+
+/// How far back `from` and `to` may reach; no history is kept that long
+const MAX_LOOKBACK_DAYS: i64 = 3650;
+
+/// The recorded point used for a requested instant
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Snapshot {
+    /// When the sample was taken, or the start of the hour or day
+    pub at: DateTime<Utc>,
+    pub resolution: Resolution,
+    /// Averages over the hour or day for compacted history
+    pub temperature: f64,
+    pub humidity: f64,
+    pub wind_speed: f64,
+    /// `None` when it changed within the hour or day
+    pub condition: Option<Condition>,
+}
+
+impl Snapshot {
+    fn of_rollup(rollup: &Rollup, resolution: Resolution) -> Self {
+        Snapshot {
+            at: rollup.start,
+            resolution,
+            temperature: rollup.temperature.average,
+            humidity: rollup.humidity.average,
+            wind_speed: rollup.wind_speed.average,
+            condition: rollup.condition,
+        }
+    }
+
+    /// When the point stops covering time: raw samples cover an instant
+    fn end(&self) -> DateTime<Utc> {
+        match self.resolution {
+            Resolution::Raw => self.at,
+            Resolution::Hourly => self.at + TimeDelta::hours(1),
+            Resolution::Daily => self.at + TimeDelta::days(1),
+        }
+    }
+
+    /// How far `instant` is from the time this point covers
+    fn distance(&self, instant: DateTime<Utc>) -> TimeDelta {
+        if instant < self.at {
+            self.at - instant
+        } else if instant > self.end() {
+            instant - self.end()
+        } else {
+            TimeDelta::zero()
+        }
+    }
+
+    fn rounded(mut self, rounding: TemperatureRounding) -> Self {
+        self.temperature = rounding.round(self.temperature);
+        self.humidity = (self.humidity * 10.0).round() / 10.0;
+        self.wind_speed = (self.wind_speed * 10.0).round() / 10.0;
+        self
+    }
+}
+
+/// The point of a city's history closest to `instant`
+///
+/// Points covering `instant` are used as is; on a tie the finer tier wins.
+fn nearest(tiers: &CityTiers, instant: DateTime<Utc>) -> Option<Snapshot> {
+    let daily = tiers
+        .daily
+        .iter()
+        .map(|r| Snapshot::of_rollup(r, Resolution::Daily));
+    let hourly = tiers
+        .hourly
+        .iter()
+        .map(|r| Snapshot::of_rollup(r, Resolution::Hourly));
+    let raw = tiers.raw.iter().map(|s| Snapshot {
+        at: s.at,
+        resolution: Resolution::Raw,
+        temperature: s.temperature as f64,
+        humidity: s.humidity as f64,
+        wind_speed: s.wind_speed as f64,
+        condition: Some(s.condition),
+    });
+    daily
+        .chain(hourly)
+        .chain(raw)
+        .rev()
+        .min_by_key(|snapshot| snapshot.distance(instant))
+}
+
+/// Change in each measurement from one snapshot to the other
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Changes {
+    pub temperature: f64,
+    pub humidity: f64,
+    pub wind_speed: f64,
+    pub condition_changed: bool,
+}
+
+impl Changes {
+    fn between(from: &Snapshot, to: &Snapshot, rounding: TemperatureRounding) -> Self {
+        Changes {
+            temperature: rounding.round(to.temperature - from.temperature),
+            humidity: ((to.humidity - from.humidity) * 10.0).round() / 10.0,
+            wind_speed: ((to.wind_speed - from.wind_speed) * 10.0).round() / 10.0,
+            condition_changed: from.condition != to.condition,
+        }
+    }
+}
+
+/// e.g. "3°C warmer, humidity down 5%, now Clear instead of Rain"
+fn summary(changes: &Changes, from: &Snapshot, to: &Snapshot) -> String {
+    let mut parts = Vec::new();
+    if changes.temperature != 0.0 {
+        let direction = if changes.temperature > 0.0 {
+            "warmer"
+        } else {
+            "colder"
+        };
+        parts.push(format!("{}°C {}", changes.temperature.abs(), direction));
+    }
+    if changes.humidity != 0.0 {
+        let direction = if changes.humidity > 0.0 { "up" } else { "down" };
+        parts.push(format!(
+            "humidity {} {}%",
+            direction,
+            changes.humidity.abs()
+        ));
+    }
+    if changes.wind_speed != 0.0 {
+        let direction = if changes.wind_speed > 0.0 {
+            "stronger"
+        } else {
+            "calmer"
+        };
+        parts.push(format!(
+            "wind {} km/h {}",
+            changes.wind_speed.abs(),
+            direction
+        ));
+    }
+    if let (true, Some(now), Some(before)) =
+        (changes.condition_changed, to.condition, from.condition)
+    {
+        parts.push(format!("now {} instead of {}", now.label(), before.label()));
+    }
+    if parts.is_empty() {
+        return "No change".to_string();
+    }
+    let mut text = parts.join(", ");
+    text[..1].make_ascii_uppercase();
+    text
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    /// RFC 3339 timestamp, or how long ago (`24h`, `7d`)
+    from: Option<String>,
+    /// Like `from`; defaults to now
+    to: Option<String>,
+}
+
+/// A timestamp, or a duration before `now`, within the last
+/// [`MAX_LOOKBACK_DAYS`] days
+fn parse_instant(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let out_of_range = || {
+        format!(
+            "Time '{}' is out of range (at most {} days ago, and not in the future)",
+            value, MAX_LOOKBACK_DAYS
+        )
+    };
+    let at = match DateTime::parse_from_rfc3339(value) {
+        Ok(at) => at.with_timezone(&Utc),
+        Err(_) => {
+            let ago = parse_duration(value).map_err(|e| {
+                format!(
+                    "Invalid time '{}' (expected an RFC 3339 timestamp or a duration such as 24h): {}",
+                    value, e
+                )
+            })?;
+            TimeDelta::from_std(ago)
+                .ok()
+                .and_then(|ago| now.checked_sub_signed(ago))
+                .ok_or_else(out_of_range)?
+        }
+    };
+    if at > now || now - at > TimeDelta::days(MAX_LOOKBACK_DAYS) {
+        return Err(out_of_range());
+    }
+    Ok(at)
+}
+
+/// How a city's weather changed between two instants of its recorded history
+///
+/// Each instant is matched to the nearest recorded point, which may be an
+/// hourly or daily average once the history is compacted; the points used
+/// are returned next to the changes.
+pub async fn get_diff(
+    Scoped(state): Scoped,
+    Path(city): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |message: String| error_response(StatusCode::BAD_REQUEST, message);
+
+    let now = Utc::now();
+    let from = query
+        .from
+        .as_deref()
+        .ok_or_else(|| bad_request("Missing 'from' (e.g. ?from=24h)".to_string()))
+        .and_then(|from| parse_instant(from, now).map_err(bad_request))?;
+    let to = match query.to.as_deref() {
+        Some(to) => parse_instant(to, now).map_err(bad_request)?,
+        None => now,
+    };
+    if from >= to {
+        return Err(bad_request(format!(
+            "'from' ({}) must be before 'to' ({})",
+            from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            to.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )));
+    }
+
+    let key = city.trim().to_lowercase();
+    println!(
+        "🔀 Received diff request for {} from {} to {}",
+        key, from, to
+    );
+
+    // The history covers global cities only
+    let tiers = state
+        .shares(&key)
+        .then(|| state.history.tiers(&key))
+        .flatten()
+        .ok_or_else(|| {
+            error_response(
+                StatusCode::NOT_FOUND,
+                format!("No history recorded for '{}'", city),
+            )
+        })?;
+    let (Some(before), Some(after)) = (nearest(&tiers, from), nearest(&tiers, to)) else {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("No history recorded for '{}'", city),
+        ));
+    };
+
+    let rounding = state.config.rounding();
+    let changes = Changes::between(&before, &after, rounding);
+    Ok(Json(serde_json::json!({
+        "city": key,
+        "from": {"requested": from, "point": before.rounded(rounding)},
+        "to": {"requested": to, "point": after.rounded(rounding)},
+        "changes": changes,
+        "summary": summary(&changes, &before, &after)
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{Retention, WeatherHistory};
+    use crate::WeatherData;
+
+    #[test]
+    fn test_diff_between_compacted_and_raw_points() {
+        let retention = Retention {
+            raw: std::time::Duration::from_secs(3 * 3600),
+            ..Retention::default()
+        };
+        let history = WeatherHistory::new(1000, retention);
+        let start = DateTime::parse_from_rfc3339("2026-10-16T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // Samples every 30 minutes for 12 hours, one degree warmer each hour
+        for step in 0..24 {
+            let reading = WeatherData {
+                temperature: step / 2,
+                condition: if step < 20 {
+                    Condition::Rain
+                } else {
+                    Condition::Clear
+                },
+                humidity: 80,
+                ..WeatherData::unknown("oslo")
+            };
+            history.record(&[reading], start + TimeDelta::minutes(30 * step as i64));
+        }
+        history.compact(start + TimeDelta::hours(12));
+        let tiers = history.tiers("oslo").unwrap();
+
+        // 01:15 falls in the compacted hour 01:00-02:00 (1° on average)
+        let before = nearest(&tiers, start + TimeDelta::minutes(75)).unwrap();
+        assert_eq!(before.resolution, Resolution::Hourly);
+        assert_eq!(before.at, start + TimeDelta::hours(1));
+        // Past the last sample: the last sample
+        let after = nearest(&tiers, start + TimeDelta::hours(20)).unwrap();
+        assert_eq!(after.resolution, Resolution::Raw);
+        assert_eq!(after.temperature, 11.0);
+
+        let changes = Changes::between(&before, &after, TemperatureRounding::default());
+        assert_eq!(changes.temperature, 10.0);
+        assert_eq!(changes.humidity, 0.0);
+        assert!(changes.condition_changed);
+        assert_eq!(
+            summary(&changes, &before, &after),
+            "10°C warmer, now Clear instead of Rain"
+        );
+
+        let now = start + TimeDelta::days(1);
+        assert_eq!(parse_instant("24h", now), Ok(start));
+        assert_eq!(parse_instant("2026-10-16T00:00:00Z", now), Ok(start));
+        assert!(parse_instant("yesterday", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_diff_rejects_bad_ranges() {
+        use crate::cities::{CityDatabase, CityStore};
+        use crate::config::ServerConfig;
+        use crate::AppState;
+        use clap::Parser;
+        use std::sync::Arc;
+
+        let config = ServerConfig::parse_from(["server"]);
+        let cities = Arc::new(CityStore::new(CityDatabase::builtin()));
+        let state = AppState::new(config, cities, None);
+        state
+            .history
+            .record(&[WeatherData::unknown("oslo")], Utc::now());
+        let diff = |from: &str, to: Option<&str>| {
+            get_diff(
+                Scoped(state.clone()),
+                Path("oslo".to_string()),
+                Query(DiffQuery {
+                    from: Some(from.to_string()),
+                    to: to.map(str::to_string),
+                }),
+            )
+        };
+        let status = |result: Result<_, (StatusCode, _)>| {
+            result
+                .map(|_| StatusCode::OK)
+                .unwrap_or_else(|(status, _)| status)
+        };
+
+        assert_eq!(status(diff("1h", None).await), StatusCode::OK);
+        for absurd in [
+            "10000000000000s",
+            "300000000000000d",
+            "3651d",
+            "9999-01-01T00:00:00Z",
+        ] {
+            assert_eq!(
+                status(diff(absurd, None).await),
+                StatusCode::BAD_REQUEST,
+                "{}",
+                absurd
+            );
+        }
+        assert_eq!(
+            status(diff("1h", Some("2h")).await),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
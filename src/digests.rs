@@ -0,0 +1,441 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::api_keys::Caller;
+use crate::context::RequestContext;
+use crate::favorites::MAX_FAVORITES;
+use crate::locale::Locale;
+use crate::store::SavedDigest;
+use crate::tenants::Scoped;
+use crate::webhook::{self, Delivery};
+use crate::{error_response, AppState, ErrorResponse, WeatherData};
+
+/// How often the scheduler looks for digests that are due
+const SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(30);
+
+type DigestError = (StatusCode, Json<ErrorResponse>);
+
+/// A daily digest of some cities' weather, posted to a webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub cities: Vec<String>,
+    /// Local delivery time, `HH:MM`
+    #[serde(serialize_with = "serialize_time")]
+    pub time: NaiveTime,
+    pub timezone: Tz,
+    pub webhook_url: String,
+    /// Units, condition language and date style of the message, from the
+    /// request that scheduled the digest
+    pub locale: Locale,
+    pub next_delivery: DateTime<Utc>,
+    pub last_delivery: Option<Delivery>,
+}
+
+fn serialize_time<S: serde::Serializer>(
+    time: &NaiveTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&time.format("%H:%M"))
+}
+
+/// Configured digests per API key name (one each)
+///
+/// Saved through [`crate::store`], so they survive restarts with `--data-file`.
+#[derive(Debug, Default)]
+pub struct Digests {
+    digests: Mutex<HashMap<String, Digest>>,
+}
+
+impl Digests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, owner: &str) -> Option<Digest> {
+        let digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        digests.get(owner).cloned()
+    }
+
+    fn set(&self, owner: &str, digest: Digest) {
+        let mut digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        digests.insert(owner.to_string(), digest);
+    }
+
+    fn remove(&self, owner: &str) -> bool {
+        let mut digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        digests.remove(owner).is_some()
+    }
+
+    /// Every digest without its delivery state, for saving
+    pub fn saved(&self) -> HashMap<String, SavedDigest> {
+        let digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        digests
+            .iter()
+            .map(|(owner, digest)| {
+                let saved = SavedDigest {
+                    cities: digest.cities.clone(),
+                    time: digest.time,
+                    timezone: digest.timezone,
+                    webhook_url: digest.webhook_url.clone(),
+                    language: digest.locale.language,
+                    units: digest.locale.units,
+                    month_first: digest.locale.month_first,
+                };
+                (owner.clone(), saved)
+            })
+            .collect()
+    }
+
+    /// Put back digests saved before a restart, next delivered after `now`
+    ///
+    /// A delivery missed while the server was down is not made up for.
+    pub fn restore(&self, saved: HashMap<String, SavedDigest>, now: DateTime<Utc>) {
+        let mut digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        *digests = saved
+            .into_iter()
+            .map(|(owner, saved)| {
+                let digest = Digest {
+                    cities: saved.cities,
+                    time: saved.time,
+                    timezone: saved.timezone,
+                    webhook_url: saved.webhook_url,
+                    locale: Locale {
+                        language: saved.language,
+                        units: saved.units,
+                        month_first: saved.month_first,
+                    },
+                    next_delivery: next_delivery(saved.time, saved.timezone, now),
+                    last_delivery: None,
+                };
+                (owner, digest)
+            })
+            .collect();
+    }
+
+    /// Digests due at `now`, scheduled for their next day as they are taken
+    fn take_due(&self, now: DateTime<Utc>) -> Vec<(String, Digest)> {
+        let mut digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        digests
+            .iter_mut()
+            .filter(|(_, digest)| digest.next_delivery <= now)
+            .map(|(owner, digest)| {
+                let due = digest.clone();
+                digest.next_delivery = next_delivery(digest.time, digest.timezone, now);
+                (owner.clone(), due)
+            })
+            .collect()
+    }
+
+    fn record_delivery(&self, owner: &str, delivery: Delivery) {
+        let mut digests = self.digests.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(digest) = digests.get_mut(owner) {
+            digest.last_delivery = Some(delivery);
+        }
+    }
+}
+
+/// The first instant after `after` when the clock in `timezone` shows `time`
+///
+/// A time skipped by a daylight saving change is delivered an hour later
+/// that day; a repeated time is delivered the first time it occurs.
+pub fn next_delivery(time: NaiveTime, timezone: Tz, after: DateTime<Utc>) -> DateTime<Utc> {
+    let today = after.with_timezone(&timezone).date_naive();
+    (0..3)
+        .filter_map(|days| today.checked_add_signed(Duration::days(days)))
+        .filter_map(|date| {
+            let local = date.and_time(time);
+            timezone.from_local_datetime(&local).earliest().or_else(|| {
+                timezone
+                    .from_local_datetime(&(local + Duration::hours(1)))
+                    .earliest()
+            })
+        })
+        .map(|at| at.with_timezone(&Utc))
+        .find(|at| *at > after)
+        .unwrap_or(after + Duration::days(1))
+}
+
+/// Plain-text summary posted as the message (`text`, as Slack expects)
+pub fn digest_text(date: &str, readings: &[WeatherData], locale: &Locale) -> String {
+    let mut text = format!("Weather digest for {}", date);
+    for reading in readings {
+        text.push_str(&format!(
+            "\n• {}: {}, {}, humidity {}%, wind {}",
+            reading.city,
+            locale.temperature(reading.temperature),
+            locale.condition(reading.condition, reading.description.as_deref()),
+            reading.humidity,
+            locale.wind(reading.wind_speed)
+        ));
+    }
+    text
+}
+
+/// Post a digest to its webhook
+async fn deliver(
+    state: &AppState,
+    client: &reqwest::Client,
+    owner: &str,
+    digest: &Digest,
+) -> Delivery {
+    let now = Utc::now();
+    // Checked again, as a restored digest may predate `--webhook-allow-host` changes
+    if let Err(e) = webhook::check_url(&digest.webhook_url, &state.config.webhook_allow_hosts) {
+        println!("⚠️  Not delivering digest of {}: {}", owner, e);
+        return Delivery {
+            at: now,
+            status: None,
+            error: Some(e),
+        };
+    }
+    let date = digest
+        .locale
+        .date(now.with_timezone(&digest.timezone).date_naive());
+    let readings: Vec<WeatherData> = digest
+        .cities
+        .iter()
+        .map(|city| state.weather_or_default(city, now))
+        .collect();
+
+    let payload = serde_json::json!({
+        "text": digest_text(&date, &readings, &digest.locale),
+        "digest": {
+            "owner": owner,
+            "date": date,
+            "timezone": digest.timezone.name(),
+            "cities": readings
+        }
+    });
+
+    let result =
+        webhook::post_signed(&state.signing, client, &digest.webhook_url, &payload, now).await;
+    match &result {
+        Ok(status) if status.is_success() => println!(
+            "📬 Delivered digest of {} ({} cities)",
+            owner,
+            readings.len()
+        ),
+        Ok(status) => println!("⚠️  Digest webhook of {} answered {}", owner, status),
+        Err(e) => println!("⚠️  Cannot deliver digest of {}: {}", owner, e),
+    }
+    Delivery::new(now, &result)
+}
+
+/// Deliver digests as they become due
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        // Digest URLs come from API-key holders, so only public hosts are reached
+        let client =
+            webhook::public_only(webhook::client_builder(), &state.config.webhook_allow_hosts)
+                .build()
+                .unwrap_or_default();
+        let mut ticker = tokio::time::interval(SCHEDULER_TICK);
+        loop {
+            ticker.tick().await;
+            for (owner, digest) in state.digests.take_due(Utc::now()) {
+                let state = state.clone();
+                let client = client.clone();
+                tokio::spawn(async move {
+                    // Readings come from the owner's own cities, as for their requests
+                    let tenant = state.api_keys.as_ref().and_then(|keys| keys.tenant(&owner));
+                    let scoped = match tenant {
+                        Some(tenant) => state.for_tenant(tenant),
+                        None => state.clone(),
+                    };
+                    let delivery = deliver(&scoped, &client, &owner, &digest).await;
+                    state.digests.record_delivery(&owner, delivery);
+                });
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DigestRequest {
+    cities: Vec<String>,
+    /// Local delivery time, `HH:MM`
+    time: String,
+    /// IANA time zone, e.g. `Europe/Stockholm` (default UTC)
+    timezone: Option<String>,
+    webhook_url: String,
+}
+
+/// The caller's digest
+pub async fn get_digest(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Digest>, DigestError> {
+    state.digests.get(&caller.name).map(Json).ok_or_else(|| {
+        error_response(
+            StatusCode::NOT_FOUND,
+            format!("No digest configured for '{}'", caller.name),
+        )
+    })
+}
+
+/// Create or replace the caller's digest, in the request's locale
+pub async fn put_digest(
+    Scoped(state): Scoped,
+    Extension(caller): Extension<Caller>,
+    RequestContext { locale, .. }: RequestContext,
+    Json(payload): Json<DigestRequest>,
+) -> Result<Json<Digest>, DigestError> {
+    let bad_request = |message: String| error_response(StatusCode::BAD_REQUEST, message);
+
+    if payload.cities.is_empty() || payload.cities.len() > MAX_FAVORITES {
+        return Err(bad_request(format!(
+            "A digest needs 1 to {} cities, you sent {}",
+            MAX_FAVORITES,
+            payload.cities.len()
+        )));
+    }
+    let database = state.cities.current();
+    let mut cities: Vec<String> = Vec::with_capacity(payload.cities.len());
+    for city in &payload.cities {
+        let city = city.trim().to_lowercase();
+        if database.get(&city).is_none() {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                format!("Unknown city '{}'", city),
+            ));
+        }
+        if !cities.contains(&city) {
+            cities.push(city);
+        }
+    }
+
+    let time = NaiveTime::parse_from_str(&payload.time, "%H:%M")
+        .map_err(|_| bad_request(format!("Invalid time '{}' (expected HH:MM)", payload.time)))?;
+    let timezone: Tz = payload
+        .timezone
+        .as_deref()
+        .unwrap_or("UTC")
+        .parse()
+        .map_err(|_| {
+            bad_request(format!(
+                "Unknown timezone '{}' (expected an IANA name such as Europe/Stockholm)",
+                payload.timezone.as_deref().unwrap_or_default()
+            ))
+        })?;
+    webhook::check_url(&payload.webhook_url, &state.config.webhook_allow_hosts)
+        .map_err(bad_request)?;
+
+    let digest = Digest {
+        cities,
+        time,
+        timezone,
+        webhook_url: payload.webhook_url,
+        locale,
+        next_delivery: next_delivery(time, timezone, Utc::now()),
+        last_delivery: None,
+    };
+    println!(
+        "📬 {} scheduled a digest of {} cities at {} {}",
+        caller.name,
+        digest.cities.len(),
+        payload.time,
+        timezone
+    );
+    state.digests.set(&caller.name, digest.clone());
+    state.persistence.save(&state).await;
+    Ok(Json(digest))
+}
+
+/// Stop the caller's digest
+pub async fn delete_digest(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<StatusCode, DigestError> {
+    if !state.digests.remove(&caller.name) {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("No digest configured for '{}'", caller.name),
+        ));
+    }
+    println!("📬 {} removed their digest", caller.name);
+    state.persistence.save(&state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::Condition;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_next_delivery_follows_the_local_clock() {
+        let seven = NaiveTime::from_hms_opt(7, 0, 0).unwrap();
+        let stockholm: Tz = "Europe/Stockholm".parse().unwrap();
+
+        // 07:00 in Stockholm is 05:00 UTC in summer
+        assert_eq!(
+            next_delivery(seven, stockholm, at("2026-06-10T04:00:00Z")),
+            at("2026-06-10T05:00:00Z")
+        );
+        // Already past today: tomorrow
+        assert_eq!(
+            next_delivery(seven, stockholm, at("2026-06-10T05:00:00Z")),
+            at("2026-06-11T05:00:00Z")
+        );
+        // 02:30 does not exist on the spring-forward day; delivered at 03:30 local
+        let half_two = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        assert_eq!(
+            next_delivery(half_two, stockholm, at("2026-03-29T00:00:00Z")),
+            at("2026-03-29T01:30:00Z")
+        );
+    }
+
+    #[test]
+    fn test_due_digests_are_rescheduled() {
+        let digests = Digests::new();
+        let now = at("2026-06-10T05:00:00Z");
+        digests.set(
+            "app",
+            Digest {
+                cities: vec!["oslo".to_string()],
+                time: NaiveTime::from_hms_opt(5, 0, 0).unwrap(),
+                timezone: Tz::UTC,
+                webhook_url: "http://localhost/hook".to_string(),
+                locale: Locale::default(),
+                next_delivery: now,
+                last_delivery: None,
+            },
+        );
+
+        assert_eq!(digests.take_due(now).len(), 1);
+        assert!(digests.take_due(now).is_empty());
+        assert_eq!(
+            digests.get("app").unwrap().next_delivery,
+            at("2026-06-11T05:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_digest_text() {
+        let readings = [WeatherData {
+            temperature: 7,
+            condition: Condition::Rain,
+            description: Some("Rainy".into()),
+            humidity: 85,
+            wind_speed: 23,
+            ..WeatherData::unknown("oslo")
+        }];
+        assert_eq!(
+            digest_text("Friday 16 October 2026", &readings, &Locale::default()),
+            "Weather digest for Friday 16 October 2026\n• oslo: 7°C, Rainy, humidity 85%, wind 23 km/h"
+        );
+        let us = Locale::from_tag("en-US").unwrap();
+        assert!(digest_text("", &readings, &us)
+            .ends_with("oslo: 45°F, Rainy, humidity 85%, wind 14 mph"));
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+/// Parse a duration like `60s`, `2m`, `1h`, `7d`, `250ms` or a bare number of seconds
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", input))?;
+
+    let seconds = |per_unit: u64| {
+        value
+            .checked_mul(per_unit)
+            .map(Duration::from_secs)
+            .ok_or_else(|| "duration too large".to_string())
+    };
+    let duration = match unit {
+        "ms" => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m" => seconds(60)?,
+        "h" => seconds(3600)?,
+        "d" => seconds(86_400)?,
+        _ => {
+            return Err(format!(
+                "unknown duration unit '{}' (use ms, s, m, h or d)",
+                unit
+            ))
+        }
+    };
+
+    if duration.is_zero() {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("60s"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("15"), Ok(Duration::from_secs(15)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86_400)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("10 days").is_err());
+        assert!(parse_duration("s").is_err());
+        assert_eq!(
+            parse_duration("300000000000000d"),
+            Err("duration too large".to_string())
+        );
+        assert!(parse_duration("18446744073709551615h").is_err());
+    }
+}
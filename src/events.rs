@@ -0,0 +1,346 @@
+use axum::response::sse::{Event, KeepAlive, Sse};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::alerts::Alert;
+use crate::anomalies::Anomaly;
+use crate::tenants::Scoped;
+
+/// What happened to a city
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CityChange {
+    Created,
+    Updated,
+    Deleted,
+    Restored,
+}
+
+/// A change to the data the API serves, published on the [`EventHub`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UpdateEvent {
+    /// One city was created, edited, soft-deleted or restored by an admin
+    CityChanged {
+        city: String,
+        change: CityChange,
+        at: DateTime<Utc>,
+    },
+    /// The whole database was swapped: a `--cities-file` reload or a
+    /// snapshot restore
+    CitiesReplaced { cities: usize, at: DateTime<Utc> },
+    /// The history sampler recorded every city's weather
+    HistoryRecorded { cities: usize, at: DateTime<Utc> },
+    /// A city's latest sample is far outside its recent range
+    AnomalyDetected(Anomaly),
+    /// A rate-of-change rule started firing for a city
+    AlertFired(Alert),
+}
+
+impl UpdateEvent {
+    /// Name used as the SSE event type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            UpdateEvent::CityChanged { .. } => "city_changed",
+            UpdateEvent::CitiesReplaced { .. } => "cities_replaced",
+            UpdateEvent::HistoryRecorded { .. } => "history_recorded",
+            UpdateEvent::AnomalyDetected(_) => "anomaly_detected",
+            UpdateEvent::AlertFired(_) => "alert_fired",
+        }
+    }
+
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            UpdateEvent::CityChanged { at, .. }
+            | UpdateEvent::CitiesReplaced { at, .. }
+            | UpdateEvent::HistoryRecorded { at, .. } => *at,
+            UpdateEvent::AnomalyDetected(anomaly) => anomaly.at,
+            UpdateEvent::AlertFired(alert) => alert.at,
+        }
+    }
+
+    /// The city the event is about, if it is about one
+    pub fn city(&self) -> Option<&str> {
+        match self {
+            UpdateEvent::CityChanged { city, .. } => Some(city),
+            UpdateEvent::AnomalyDetected(Anomaly { city, .. })
+            | UpdateEvent::AlertFired(Alert { city, .. }) => Some(city),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to a subscriber whose send queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SlowSubscriberPolicy {
+    /// Drop the events it has no room for, then send a `lagged` event
+    /// with how many it missed
+    #[default]
+    Drop,
+    /// Close the stream; the client reconnects and catches up (e.g. with
+    /// `GET /sync`)
+    Disconnect,
+}
+
+/// An entry in one subscriber's send queue
+#[derive(Debug, Clone, PartialEq)]
+pub enum Queued {
+    Event(UpdateEvent),
+    /// Events dropped since the last entry
+    Lagged(u64),
+}
+
+/// Queued subscribers, for `GET /metrics`
+#[derive(Debug, Default)]
+pub struct SubscriberStats {
+    active: AtomicUsize,
+    dropped: AtomicU64,
+    disconnected: AtomicU64,
+}
+
+impl SubscriberStats {
+    /// Subscribers currently connected
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Events dropped for slow subscribers since startup
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Subscribers disconnected for being too slow since startup
+    pub fn disconnected(&self) -> u64 {
+        self.disconnected.load(Ordering::Relaxed)
+    }
+}
+
+/// Decrements the active count when a subscriber goes away, however it goes
+struct ActiveSubscriber(Arc<SubscriberStats>);
+
+impl Drop for ActiveSubscriber {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Shared backbone for update events
+///
+/// Every data mutation publishes here, and every push transport (`GET /events`
+/// and the city webhooks) subscribes instead of polling the city store.
+/// Internal subscribers more than `--event-buffer` events behind skip ahead
+/// and are told how many they missed. Clients subscribe through
+/// [`EventHub::subscribe_queued`] instead, so a stalled connection only
+/// fills its own bounded queue.
+#[derive(Debug)]
+pub struct EventHub {
+    sender: broadcast::Sender<UpdateEvent>,
+    /// Capacity of each queued subscriber's send queue
+    queue: usize,
+    policy: SlowSubscriberPolicy,
+    stats: Arc<SubscriberStats>,
+}
+
+impl EventHub {
+    pub fn new(capacity: usize, queue: usize, policy: SlowSubscriberPolicy) -> Self {
+        EventHub {
+            sender: broadcast::channel(capacity).0,
+            queue,
+            policy,
+            stats: Arc::new(SubscriberStats::default()),
+        }
+    }
+
+    /// Send an event to every current subscriber; without any it is dropped
+    pub fn publish(&self, event: UpdateEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<UpdateEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn stats(&self) -> &SubscriberStats {
+        &self.stats
+    }
+
+    /// Subscribe with a send queue of its own, for a client connection
+    ///
+    /// A task moves the events passing `filter` from the hub to the queue
+    /// without waiting. When the queue is full the subscriber is too slow,
+    /// and `--slow-subscriber-policy` decides between dropping events and
+    /// closing the queue. The task ends when the receiver is dropped.
+    pub fn subscribe_queued(
+        &self,
+        filter: impl Fn(&UpdateEvent) -> bool + Send + 'static,
+    ) -> mpsc::Receiver<Queued> {
+        let mut receiver = self.sender.subscribe();
+        let (queue, output) = mpsc::channel(self.queue);
+        let policy = self.policy;
+        self.stats.active.fetch_add(1, Ordering::Relaxed);
+        let active = ActiveSubscriber(self.stats.clone());
+
+        tokio::spawn(async move {
+            let stats = active.0.clone();
+            let mut missed = 0;
+            loop {
+                let event = tokio::select! {
+                    _ = queue.closed() => return,
+                    event = receiver.recv() => event,
+                };
+                let event = match event {
+                    Ok(event) if filter(&event) => event,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        missed += skipped;
+                        stats.dropped.fetch_add(skipped, Ordering::Relaxed);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+
+                // Report earlier drops first, so the client sees them in order
+                let sent = match missed {
+                    0 => queue.try_send(Queued::Event(event)),
+                    _ => queue.try_send(Queued::Lagged(missed)).and_then(|()| {
+                        missed = 0;
+                        queue.try_send(Queued::Event(event))
+                    }),
+                };
+                match sent {
+                    Ok(()) => continue,
+                    Err(TrySendError::Full(_)) => {}
+                    Err(TrySendError::Closed(_)) => return,
+                }
+
+                match policy {
+                    SlowSubscriberPolicy::Drop => {
+                        if missed == 0 {
+                            println!("🐌 Event subscriber is too slow: dropping events");
+                        }
+                        missed += 1;
+                        stats.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    SlowSubscriberPolicy::Disconnect => {
+                        println!("🐌 Event subscriber is too slow: disconnecting");
+                        stats.disconnected.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            }
+        });
+        output
+    }
+}
+
+/// Server-sent stream of update events
+///
+/// Events about a single city are only sent to callers whose city set
+/// includes it. Each connection has a queue of `--event-queue` events; a
+/// subscriber that cannot keep up gets a `lagged` event with the number it
+/// missed, or is disconnected (`--slow-subscriber-policy`).
+pub async fn stream_events(
+    Scoped(state): Scoped,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    println!("📡 Event subscriber connected");
+
+    let filter_state = state.clone();
+    let queue = state
+        .events
+        .subscribe_queued(move |event| event.city().is_none_or(|city| filter_state.shares(city)));
+    let events = stream::unfold(queue, |mut queue| async move {
+        let event = match queue.recv().await? {
+            Queued::Event(event) => Event::default()
+                .event(event.kind())
+                .json_data(&event)
+                .unwrap_or_default(),
+            Queued::Lagged(skipped) => Event::default()
+                .event("lagged")
+                .data(format!("{{\"skipped\":{}}}", skipped)),
+        };
+        Some((Ok(event), queue))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_subscribers_skip_ahead() {
+        let hub = EventHub::new(2, 2, SlowSubscriberPolicy::Drop);
+        let mut receiver = hub.subscribe();
+        let at = Utc::now();
+        for cities in 1..=3 {
+            hub.publish(UpdateEvent::HistoryRecorded { cities, at });
+        }
+
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(1))));
+        let next = receiver.recv().await.unwrap();
+        assert_eq!(next, UpdateEvent::HistoryRecorded { cities: 2, at });
+        assert_eq!(next.kind(), "history_recorded");
+        assert_eq!(
+            serde_json::to_value(&next).unwrap()["type"],
+            "history_recorded"
+        );
+
+        let alert = UpdateEvent::AlertFired(Alert {
+            city: "oslo".to_string(),
+            rule: "temperature-5/3h".to_string(),
+            metric: "temperature",
+            from: 16,
+            to: 10,
+            since: at,
+            at,
+        });
+        let json = serde_json::to_value(&alert).unwrap();
+        assert_eq!(
+            (json["type"].as_str(), json["rule"].as_str()),
+            (Some("alert_fired"), Some("temperature-5/3h"))
+        );
+        assert_eq!(alert.city(), Some("oslo"));
+    }
+
+    #[tokio::test]
+    async fn test_queued_subscribers_drop_or_disconnect() {
+        let at = Utc::now();
+        let event = |cities| UpdateEvent::HistoryRecorded { cities, at };
+        let settle = || tokio::time::sleep(std::time::Duration::from_millis(20));
+
+        let hub = EventHub::new(16, 2, SlowSubscriberPolicy::Drop);
+        let mut queue = hub.subscribe_queued(|_| true);
+        for cities in 1..=5 {
+            hub.publish(event(cities));
+        }
+        settle().await;
+        assert_eq!(queue.recv().await, Some(Queued::Event(event(1))));
+        assert_eq!(queue.recv().await, Some(Queued::Event(event(2))));
+        hub.publish(event(6));
+        settle().await;
+        assert_eq!(queue.recv().await, Some(Queued::Lagged(3)));
+        assert_eq!(queue.recv().await, Some(Queued::Event(event(6))));
+        assert_eq!(hub.stats().dropped(), 3);
+        assert_eq!(hub.stats().active(), 1);
+        drop(queue);
+        hub.publish(event(7));
+        settle().await;
+        assert_eq!(hub.stats().active(), 0);
+
+        let hub = EventHub::new(16, 1, SlowSubscriberPolicy::Disconnect);
+        let mut queue = hub.subscribe_queued(|_| true);
+        for cities in 1..=3 {
+            hub.publish(event(cities));
+        }
+        settle().await;
+        assert_eq!(queue.recv().await, Some(Queued::Event(event(1))));
+        assert_eq!(queue.recv().await, None);
+        assert_eq!(hub.stats().disconnected(), 1);
+    }
+}
@@ -0,0 +1,258 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::api_keys::Caller;
+use crate::tenants::Scoped;
+use crate::{error_response, AppState, ErrorResponse};
+
+/// Most favorites one key can save (the same as the cities per `POST /weather`)
+pub const MAX_FAVORITES: usize = 20;
+
+type FavoritesError = (StatusCode, Json<ErrorResponse>);
+
+/// Favorite cities per API key name, in the order the client chose
+///
+/// Saved through [`crate::store`], so they survive restarts with `--data-file`.
+#[derive(Debug, Default)]
+pub struct Favorites {
+    lists: RwLock<HashMap<String, Vec<String>>>,
+}
+
+impl Favorites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self, owner: &str) -> Vec<String> {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        lists.get(owner).cloned().unwrap_or_default()
+    }
+
+    /// Every owner's favorites, for saving
+    pub fn all(&self) -> HashMap<String, Vec<String>> {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        lists.clone()
+    }
+
+    /// Put back favorites saved before a restart
+    pub fn restore(&self, saved: HashMap<String, Vec<String>>) {
+        let mut lists = self.lists.write().unwrap_or_else(|e| e.into_inner());
+        *lists = saved;
+    }
+
+    /// Append a city (lowercase name) to the owner's favorites
+    fn add(&self, owner: &str, city: String) -> Result<Vec<String>, FavoritesError> {
+        let mut lists = self.lists.write().unwrap_or_else(|e| e.into_inner());
+        let list = lists.entry(owner.to_string()).or_default();
+        if list.contains(&city) {
+            return Err(error_response(
+                StatusCode::CONFLICT,
+                format!("'{}' is already a favorite", city),
+            ));
+        }
+        if list.len() == MAX_FAVORITES {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("Too many favorites. Maximum is {}", MAX_FAVORITES),
+            ));
+        }
+        list.push(city);
+        Ok(list.clone())
+    }
+
+    /// Replace the owner's favorites, e.g. to reorder them
+    fn replace(&self, owner: &str, cities: Vec<String>) -> Vec<String> {
+        let mut lists = self.lists.write().unwrap_or_else(|e| e.into_inner());
+        lists.insert(owner.to_string(), cities.clone());
+        cities
+    }
+
+    /// Remove a city; false if it was not a favorite
+    fn remove(&self, owner: &str, city: &str) -> bool {
+        let mut lists = self.lists.write().unwrap_or_else(|e| e.into_inner());
+        let Some(list) = lists.get_mut(owner) else {
+            return false;
+        };
+        let before = list.len();
+        list.retain(|favorite| favorite != city);
+        list.len() != before
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddFavorite {
+    city: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplaceFavorites {
+    cities: Vec<String>,
+}
+
+/// Lowercase name of a known city, or 404
+fn known_city(state: &AppState, city: &str) -> Result<String, FavoritesError> {
+    let city = city.trim().to_lowercase();
+    if state.cities.current().get(&city).is_none() {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("Unknown city '{}'", city),
+        ));
+    }
+    Ok(city)
+}
+
+fn favorites_response(owner: &str, favorites: Vec<String>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "owner": owner,
+        "count": favorites.len(),
+        "favorites": favorites
+    }))
+}
+
+/// List the caller's favorite cities
+pub async fn list_favorites(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+) -> Json<serde_json::Value> {
+    println!("⭐ Listing favorites of {}", caller.name);
+    favorites_response(&caller.name, state.favorites.list(&caller.name))
+}
+
+/// Add a city to the end of the caller's favorites
+pub async fn add_favorite(
+    Scoped(state): Scoped,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<AddFavorite>,
+) -> Result<(StatusCode, Json<serde_json::Value>), FavoritesError> {
+    let city = known_city(&state, &payload.city)?;
+    let favorites = state.favorites.add(&caller.name, city.clone())?;
+    println!("⭐ {} added {} to favorites", caller.name, city);
+    state.persistence.save(&state).await;
+    Ok((
+        StatusCode::CREATED,
+        favorites_response(&caller.name, favorites),
+    ))
+}
+
+/// Replace the caller's favorites with the given cities, in that order
+pub async fn replace_favorites(
+    Scoped(state): Scoped,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<ReplaceFavorites>,
+) -> Result<Json<serde_json::Value>, FavoritesError> {
+    if payload.cities.len() > MAX_FAVORITES {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Too many favorites. Maximum is {}, you sent {}",
+                MAX_FAVORITES,
+                payload.cities.len()
+            ),
+        ));
+    }
+
+    let mut cities: Vec<String> = Vec::with_capacity(payload.cities.len());
+    for city in &payload.cities {
+        let city = known_city(&state, city)?;
+        if cities.contains(&city) {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("'{}' is listed more than once", city),
+            ));
+        }
+        cities.push(city);
+    }
+
+    println!("⭐ {} saved {} favorites", caller.name, cities.len());
+    let favorites = state.favorites.replace(&caller.name, cities);
+    state.persistence.save(&state).await;
+    Ok(favorites_response(&caller.name, favorites))
+}
+
+/// Remove a city from the caller's favorites
+pub async fn remove_favorite(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(city): Path<String>,
+) -> Result<StatusCode, FavoritesError> {
+    let city = city.to_lowercase();
+    if !state.favorites.remove(&caller.name, &city) {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("'{}' is not a favorite", city),
+        ));
+    }
+    println!("⭐ {} removed {} from favorites", caller.name, city);
+    state.persistence.save(&state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Current weather for each of the caller's favorites, in their order
+pub async fn weather_for_favorites(
+    Scoped(state): Scoped,
+    Extension(caller): Extension<Caller>,
+) -> Json<serde_json::Value> {
+    let favorites = state.favorites.list(&caller.name);
+    println!(
+        "📥 Received weather request for {} favorites of {}",
+        favorites.len(),
+        caller.name
+    );
+
+    let now = Utc::now();
+    let data: Vec<_> = favorites
+        .iter()
+        .map(|city| {
+            state.count_request(city);
+            state.weather_or_default(city, now)
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "owner": caller.name,
+        "count": data.len(),
+        "data": data
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_favorites_are_kept_per_owner_in_order() {
+        let favorites = Favorites::new();
+        favorites.add("app", "oslo".to_string()).unwrap();
+        favorites.add("app", "rome".to_string()).unwrap();
+        favorites.add("other", "paris".to_string()).unwrap();
+
+        let duplicate = favorites.add("app", "oslo".to_string()).unwrap_err();
+        assert_eq!(duplicate.0, StatusCode::CONFLICT);
+
+        favorites.replace("app", vec!["rome".to_string(), "oslo".to_string()]);
+        assert_eq!(favorites.list("app"), vec!["rome", "oslo"]);
+
+        assert!(favorites.remove("app", "rome"));
+        assert!(!favorites.remove("app", "rome"));
+        assert_eq!(favorites.list("app"), vec!["oslo"]);
+        assert_eq!(favorites.list("other"), vec!["paris"]);
+        assert!(favorites.list("nobody").is_empty());
+    }
+
+    #[test]
+    fn test_favorites_are_bounded() {
+        let favorites = Favorites::new();
+        for i in 0..MAX_FAVORITES {
+            favorites.add("app", format!("city{}", i)).unwrap();
+        }
+        let full = favorites.add("app", "one-more".to_string()).unwrap_err();
+        assert_eq!(full.0, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,118 @@
+use axum::{http::StatusCode, Json};
+use serde_json::Value;
+
+use crate::ErrorResponse;
+
+/// Attributes of a weather reading, selectable on `/weather` and `/stats`
+pub const WEATHER_FIELDS: &[&str] = &[
+    "city",
+    "temperature",
+    "condition",
+    "description",
+    "icon",
+    "humidity",
+    "wind_speed",
+    "source",
+    "observed_at",
+];
+
+/// Attributes of a city, selectable on `/cities`
+pub const CITY_FIELDS: &[&str] = &[
+    "city",
+    "name",
+    "country",
+    "continent",
+    "timezone",
+    "latitude",
+    "longitude",
+    "temperature",
+    "condition",
+    "description",
+    "icon",
+    "humidity",
+    "wind_speed",
+];
+
+/// Attributes requested with `?fields=a,b,c`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSelection {
+    fields: Vec<String>,
+}
+
+impl FieldSelection {
+    /// Parse the `fields` query parameter, rejecting names not in `allowed`
+    ///
+    /// Returns `None` when the parameter is absent, meaning every field.
+    pub fn from_query(
+        input: Option<&str>,
+        allowed: &[&str],
+    ) -> Result<Option<Self>, (StatusCode, Json<ErrorResponse>)> {
+        input
+            .map(|input| Self::parse(input, allowed))
+            .transpose()
+            .map_err(|error| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse { error, code: 400 }),
+                )
+            })
+    }
+
+    fn parse(input: &str, allowed: &[&str]) -> Result<Self, String> {
+        let mut fields = Vec::new();
+        let mut unknown = Vec::new();
+        for field in input.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            if !allowed.contains(&field) {
+                unknown.push(field);
+            } else if !fields.iter().any(|f| f == field) {
+                fields.push(field.to_string());
+            }
+        }
+
+        if !unknown.is_empty() {
+            return Err(format!(
+                "Unknown field(s) in 'fields': {}. Valid fields: {}",
+                unknown.join(", "),
+                allowed.join(", ")
+            ));
+        }
+        if fields.is_empty() {
+            return Err(format!(
+                "The 'fields' parameter is empty. Valid fields: {}",
+                allowed.join(", ")
+            ));
+        }
+
+        Ok(FieldSelection { fields })
+    }
+
+    /// Drop every attribute of a JSON object that was not selected
+    pub fn retain(&self, value: &mut Value) {
+        if let Value::Object(object) = value {
+            object.retain(|key, _| self.fields.iter().any(|f| f == key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fields() {
+        let selection = FieldSelection::parse(" city,temperature,city, ", WEATHER_FIELDS).unwrap();
+        assert_eq!(selection.fields, vec!["city", "temperature"]);
+
+        let err = FieldSelection::parse("city,temp,pressure", WEATHER_FIELDS).unwrap_err();
+        assert!(err.contains("temp, pressure"));
+        assert!(FieldSelection::parse(",", WEATHER_FIELDS).is_err());
+    }
+
+    #[test]
+    fn test_retain_keeps_selected_fields() {
+        let selection = FieldSelection::parse("city,humidity", WEATHER_FIELDS).unwrap();
+        let mut value = serde_json::json!({"city": "oslo", "temperature": 5, "humidity": 80});
+        selection.retain(&mut value);
+        assert_eq!(value, serde_json::json!({"city": "oslo", "humidity": 80}));
+    }
+}
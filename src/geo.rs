@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use crate::cities::CityDatabase;
+
+/// Rectangle of longitudes and latitudes, as given by `?bbox=`
+///
+/// A box whose west edge is east of its east edge crosses the antimeridian,
+/// e.g. `170,-50,-170,-30` covers the Pacific around longitude 180.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        let within_lon = if self.min_lon <= self.max_lon {
+            (self.min_lon..=self.max_lon).contains(&longitude)
+        } else {
+            longitude >= self.min_lon || longitude <= self.max_lon
+        };
+        within_lon && (self.min_lat..=self.max_lat).contains(&latitude)
+    }
+}
+
+/// Mean Earth radius used for distances
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance in kilometers between two points (haversine formula)
+pub fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// A known city and its distance from a point
+#[derive(Debug, Clone, Copy)]
+pub struct NearbyCity<'a> {
+    pub key: &'a str,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub distance_km: f64,
+}
+
+/// Cities with coordinates, nearest to the point first
+pub fn cities_by_distance(
+    database: &CityDatabase,
+    latitude: f64,
+    longitude: f64,
+) -> Vec<NearbyCity<'_>> {
+    let mut cities: Vec<NearbyCity> = database
+        .iter()
+        .filter_map(|(key, record)| {
+            let (city_lat, city_lon) = record.coordinates()?;
+            Some(NearbyCity {
+                key,
+                latitude: city_lat,
+                longitude: city_lon,
+                distance_km: distance_km(latitude, longitude, city_lat, city_lon),
+            })
+        })
+        .collect();
+    cities.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+    cities
+}
+
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    /// Parse `minLon,minLat,maxLon,maxLat`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let values = input
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Invalid bbox '{}': expected four numbers", input))?;
+        let [min_lon, min_lat, max_lon, max_lat] = values[..] else {
+            return Err(format!(
+                "Invalid bbox '{}': expected minLon,minLat,maxLon,maxLat",
+                input
+            ));
+        };
+
+        if ![min_lon, max_lon]
+            .iter()
+            .all(|lon| (-180.0..=180.0).contains(lon))
+        {
+            return Err(format!(
+                "Invalid bbox '{}': longitudes must be within -180..180",
+                input
+            ));
+        }
+        if ![min_lat, max_lat]
+            .iter()
+            .all(|lat| (-90.0..=90.0).contains(lat))
+        {
+            return Err(format!(
+                "Invalid bbox '{}': latitudes must be within -90..90",
+                input
+            ));
+        }
+        if min_lat > max_lat {
+            return Err(format!(
+                "Invalid bbox '{}': minLat is greater than maxLat",
+                input
+            ));
+        }
+
+        Ok(BoundingBox {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounding_box() {
+        let scandinavia: BoundingBox = "4.5,55,31,71".parse().unwrap();
+        assert!(scandinavia.contains(59.33, 18.07));
+        assert!(!scandinavia.contains(48.86, 2.35));
+
+        let pacific: BoundingBox = "170,-50,-170,-30".parse().unwrap();
+        assert!(pacific.contains(-41.29, 174.78));
+        assert!(pacific.contains(-40.0, -175.0));
+        assert!(!pacific.contains(-33.87, 151.21));
+
+        assert!("1,2,3".parse::<BoundingBox>().is_err());
+        assert!("0,10,10,0".parse::<BoundingBox>().is_err());
+        assert!("0,0,200,10".parse::<BoundingBox>().is_err());
+        assert!("a,b,c,d".parse::<BoundingBox>().is_err());
+    }
+
+    #[test]
+    fn test_distance_km() {
+        // Stockholm to Oslo is about 417 km
+        let d = distance_km(59.33, 18.07, 59.91, 10.75);
+        assert!((d - 417.0).abs() < 5.0, "got {}", d);
+        assert_eq!(distance_km(10.0, 20.0, 10.0, 20.0), 0.0);
+        // Across the antimeridian
+        assert!(distance_km(0.0, 179.5, 0.0, -179.5) < 112.0);
+    }
+
+    #[test]
+    fn test_cities_by_distance() {
+        let db = CityDatabase::builtin();
+        let nearest: Vec<&str> = cities_by_distance(&db, 59.4, 18.0)
+            .iter()
+            .take(2)
+            .map(|city| city.key)
+            .collect();
+        assert_eq!(nearest, vec!["stockholm", "uppsala"]);
+    }
+}
@@ -0,0 +1,116 @@
+use axum::{
+    extract::ConnectInfo,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use maxminddb::{geoip2, Reader};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+
+use crate::geo;
+use crate::tenants::Scoped;
+use crate::{error_response, ErrorResponse};
+
+/// Approximate location of an IP address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Radius around the point the address is likely to be in
+    pub accuracy_km: Option<u16>,
+}
+
+/// A MaxMind GeoIP2 or GeoLite2 City database
+///
+/// The database is not bundled (its license does not allow redistribution);
+/// point `--geoip-db` at a downloaded `.mmdb` file.
+pub struct GeoIp {
+    reader: Reader<Vec<u8>>,
+}
+
+impl GeoIp {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Reader::open_readfile(path)
+            .map(|reader| GeoIp { reader })
+            .map_err(|e| format!("cannot open GeoIP database {}: {}", path.display(), e))
+    }
+
+    pub fn locate(&self, ip: IpAddr) -> Option<IpLocation> {
+        let city: geoip2::City = self.reader.lookup(ip).ok()?;
+        let location = city.location?;
+        Some(IpLocation {
+            latitude: location.latitude?,
+            longitude: location.longitude?,
+            accuracy_km: location.accuracy_radius,
+        })
+    }
+}
+
+/// The client's address: the first `X-Forwarded-For` entry when the server
+/// runs behind a trusted proxy, otherwise the connection's peer
+fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_forwarded_for: bool) -> IpAddr {
+    let forwarded = trust_forwarded_for
+        .then(|| headers.get("x-forwarded-for"))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|ip| ip.trim().parse().ok());
+    forwarded.unwrap_or(peer.ip())
+}
+
+/// Weather at the known city nearest to the caller's approximate location
+pub async fn weather_for_client(
+    Scoped(state): Scoped,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let ip = client_ip(&headers, peer, state.config.trust_forwarded_for);
+    println!("📡 Received weather request for client {}", ip);
+
+    let not_found = |message: String| error_response(StatusCode::NOT_FOUND, message);
+
+    let location = state
+        .geoip
+        .as_ref()
+        .and_then(|geoip| geoip.locate(ip))
+        .ok_or_else(|| not_found(format!("No location known for {}", ip)))?;
+
+    let database = state.cities.current();
+    let nearest = geo::cities_by_distance(&database, location.latitude, location.longitude)
+        .into_iter()
+        .next()
+        .ok_or_else(|| not_found("No cities with coordinates are known".to_string()))?;
+    state.count_request(nearest.key);
+
+    Ok(Json(serde_json::json!({
+        "ip": ip.to_string(),
+        "location": {
+            "lat": location.latitude,
+            "lon": location.longitude,
+            "accuracy_km": location.accuracy_km
+        },
+        "city": nearest.key,
+        "distance_km": (nearest.distance_km * 10.0).round() / 10.0,
+        "weather": state.weather_or_default(nearest.key, Utc::now())
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip_trusts_forwarded_for_only_when_asked() {
+        let peer: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+
+        assert_eq!(
+            client_ip(&headers, peer, true),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(client_ip(&headers, peer, false), peer.ip());
+        assert_eq!(client_ip(&HeaderMap::new(), peer, true), peer.ip());
+    }
+}
@@ -0,0 +1,550 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+use crate::conditions::Condition;
+use crate::config::ServerConfig;
+use crate::events::UpdateEvent;
+use crate::rounding::TemperatureRounding;
+use crate::tenants::Scoped;
+use crate::{AppState, ErrorResponse, WeatherData};
+
+/// One recorded observation of a city
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Sample {
+    pub at: DateTime<Utc>,
+    pub temperature: i32,
+    pub humidity: i32,
+    pub wind_speed: i32,
+    pub condition: Condition,
+}
+
+/// Readings kept in the history, with how to get each from a sample
+pub const METRICS: &[Metric] = &[
+    ("temperature", |s| s.temperature),
+    ("humidity", |s| s.humidity),
+    ("wind_speed", |s| s.wind_speed),
+];
+
+pub type Metric = (&'static str, fn(&Sample) -> i32);
+
+/// Lowest, highest and mean value of one metric over a rollup's samples
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Aggregate {
+    pub min: i32,
+    pub max: i32,
+    pub average: f64,
+}
+
+impl Aggregate {
+    fn of(value: i32) -> Self {
+        Aggregate {
+            min: value,
+            max: value,
+            average: value as f64,
+        }
+    }
+
+    /// Combine with `other`, weighting the averages by sample counts
+    fn merge(&mut self, count: u32, other: Aggregate, other_count: u32) {
+        let total = (count + other_count) as f64;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.average = (self.average * count as f64 + other.average * other_count as f64) / total;
+    }
+}
+
+/// Samples of one hour or day folded together by the compaction
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Rollup {
+    /// Start of the hour or day (UTC)
+    pub start: DateTime<Utc>,
+    pub samples: u32,
+    pub temperature: Aggregate,
+    pub humidity: Aggregate,
+    pub wind_speed: Aggregate,
+    /// The condition of every sample, or `None` when it changed within
+    /// the period
+    pub condition: Option<Condition>,
+}
+
+impl Rollup {
+    fn of(sample: &Sample, start: DateTime<Utc>) -> Self {
+        Rollup {
+            start,
+            samples: 1,
+            temperature: Aggregate::of(sample.temperature),
+            humidity: Aggregate::of(sample.humidity),
+            wind_speed: Aggregate::of(sample.wind_speed),
+            condition: Some(sample.condition),
+        }
+    }
+
+    fn merge(&mut self, other: &Rollup) {
+        if self.condition != other.condition {
+            self.condition = None;
+        }
+        self.temperature
+            .merge(self.samples, other.temperature, other.samples);
+        self.humidity
+            .merge(self.samples, other.humidity, other.samples);
+        self.wind_speed
+            .merge(self.samples, other.wind_speed, other.samples);
+        self.samples += other.samples;
+    }
+
+    /// Averages rounded for responses; temperatures follow `rounding`
+    fn rounded(mut self, rounding: TemperatureRounding) -> Self {
+        self.temperature.average = rounding.round(self.temperature.average);
+        self.humidity.average = (self.humidity.average * 10.0).round() / 10.0;
+        self.wind_speed.average = (self.wind_speed.average * 10.0).round() / 10.0;
+        self
+    }
+}
+
+/// Fold `rollup` into the rollups of its period, kept oldest first
+///
+/// Compaction moves data in time order, so it either extends the newest
+/// rollup or starts a new one.
+fn fold(rollups: &mut VecDeque<Rollup>, rollup: Rollup, period: TimeDelta) {
+    let start = rollup.start.duration_trunc(period).unwrap_or(rollup.start);
+    match rollups.back_mut() {
+        Some(last) if last.start == start => last.merge(&rollup),
+        _ => rollups.push_back(Rollup { start, ..rollup }),
+    }
+}
+
+/// How long each tier of the history keeps its points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retention {
+    /// Raw samples, also bounded by `--history-len` per city
+    pub raw: Duration,
+    /// Hourly rollups of older samples
+    pub hourly: Duration,
+    /// Daily rollups of older hours; kept forever when `None`
+    pub daily: Option<Duration>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            raw: Duration::from_secs(7 * 86_400),
+            hourly: Duration::from_secs(90 * 86_400),
+            daily: None,
+        }
+    }
+}
+
+impl Retention {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Retention {
+            raw: config.history_raw_retention,
+            hourly: config.history_hourly_retention,
+            daily: config.history_daily_retention,
+        }
+    }
+}
+
+/// Resolution of the points served by `GET /history/:city`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    #[default]
+    Raw,
+    Hourly,
+    Daily,
+}
+
+#[derive(Debug, Default)]
+struct CityHistory {
+    raw: VecDeque<Sample>,
+    hourly: VecDeque<Rollup>,
+    daily: VecDeque<Rollup>,
+}
+
+/// Copy of one city's history, each tier oldest first
+///
+/// Tiers follow each other in time: days, then hours, then raw samples.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CityTiers {
+    pub daily: Vec<Rollup>,
+    pub hourly: Vec<Rollup>,
+    pub raw: Vec<Sample>,
+}
+
+/// Points kept per tier, over every city
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreSize {
+    pub raw: usize,
+    pub hourly: usize,
+    pub daily: usize,
+}
+
+impl StoreSize {
+    /// Approximate memory used by the points
+    pub fn bytes(&self) -> usize {
+        self.raw * std::mem::size_of::<Sample>()
+            + (self.hourly + self.daily) * std::mem::size_of::<Rollup>()
+    }
+}
+
+/// What one compaction run did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Compaction {
+    /// Raw samples folded into hourly rollups
+    pub raw_rolled_up: usize,
+    /// Hourly rollups folded into daily ones
+    pub hourly_rolled_up: usize,
+    /// Daily rollups dropped past their retention
+    pub daily_dropped: usize,
+}
+
+/// Observations per city, oldest first, in three tiers
+///
+/// Raw samples are kept for `retention.raw` (and at most `capacity` per
+/// city); the compaction then folds them into hourly rollups, and hourly
+/// rollups older than `retention.hourly` into daily ones. Everything is kept
+/// in memory and lost on restart.
+#[derive(Debug)]
+pub struct WeatherHistory {
+    capacity: usize,
+    retention: Retention,
+    cities: RwLock<HashMap<String, CityHistory>>,
+    compactions: AtomicU64,
+    last_compaction: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl WeatherHistory {
+    pub fn new(capacity: usize, retention: Retention) -> Self {
+        WeatherHistory {
+            capacity,
+            retention,
+            cities: RwLock::new(HashMap::new()),
+            compactions: AtomicU64::new(0),
+            last_compaction: Mutex::new(None),
+        }
+    }
+
+    /// Append one reading per city; cities missing from `readings` are forgotten
+    ///
+    /// A city's oldest sample beyond `capacity` is rolled up right away.
+    pub fn record(&self, readings: &[WeatherData], at: DateTime<Utc>) {
+        let mut cities = self.cities.write().unwrap_or_else(|e| e.into_inner());
+        cities.retain(|city, _| readings.iter().any(|r| &r.city == city));
+
+        for reading in readings {
+            let city = cities.entry(reading.city.to_string()).or_default();
+            if city.raw.len() == self.capacity {
+                if let Some(oldest) = city.raw.pop_front() {
+                    fold(
+                        &mut city.hourly,
+                        Rollup::of(&oldest, oldest.at),
+                        TimeDelta::hours(1),
+                    );
+                }
+            }
+            city.raw.push_back(Sample {
+                at,
+                temperature: reading.temperature,
+                humidity: reading.humidity,
+                wind_speed: reading.wind_speed,
+                condition: reading.condition,
+            });
+        }
+    }
+
+    /// Roll up and drop whatever has outlived its tier's retention
+    pub fn compact(&self, now: DateTime<Utc>) -> Compaction {
+        // Retentions too long to subtract keep everything
+        let age = |retention: Duration| {
+            TimeDelta::from_std(retention)
+                .ok()
+                .and_then(|retention| now.checked_sub_signed(retention))
+                .unwrap_or(DateTime::<Utc>::MIN_UTC)
+        };
+        let raw_cutoff = age(self.retention.raw);
+        let hourly_cutoff = age(self.retention.hourly);
+        let daily_cutoff = self.retention.daily.map(age);
+
+        let mut done = Compaction::default();
+        let mut cities = self.cities.write().unwrap_or_else(|e| e.into_inner());
+        for city in cities.values_mut() {
+            while let Some(sample) = city.raw.front().filter(|s| s.at < raw_cutoff).copied() {
+                city.raw.pop_front();
+                fold(
+                    &mut city.hourly,
+                    Rollup::of(&sample, sample.at),
+                    TimeDelta::hours(1),
+                );
+                done.raw_rolled_up += 1;
+            }
+            // Whole hours only, so a rollup is never split across tiers
+            while let Some(hour) = city
+                .hourly
+                .front()
+                .filter(|h| h.start + TimeDelta::hours(1) <= hourly_cutoff)
+                .copied()
+            {
+                city.hourly.pop_front();
+                fold(&mut city.daily, hour, TimeDelta::days(1));
+                done.hourly_rolled_up += 1;
+            }
+            if let Some(cutoff) = daily_cutoff {
+                while city
+                    .daily
+                    .front()
+                    .is_some_and(|d| d.start + TimeDelta::days(1) <= cutoff)
+                {
+                    city.daily.pop_front();
+                    done.daily_dropped += 1;
+                }
+            }
+        }
+
+        self.compactions.fetch_add(1, Ordering::Relaxed);
+        *self
+            .last_compaction
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(now);
+        done
+    }
+
+    /// Samples recorded for a city (lowercase name), oldest first
+    pub fn city(&self, city: &str) -> Vec<Sample> {
+        let cities = self.cities.read().unwrap_or_else(|e| e.into_inner());
+        cities
+            .get(city)
+            .map(|c| c.raw.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Rollups of a city (lowercase name) at an hourly or daily resolution,
+    /// oldest first; `None` for an unrecorded city
+    pub fn rollups(&self, city: &str, resolution: Resolution) -> Option<Vec<Rollup>> {
+        let cities = self.cities.read().unwrap_or_else(|e| e.into_inner());
+        let city = cities.get(city)?;
+        Some(match resolution {
+            Resolution::Raw => city
+                .raw
+                .iter()
+                .map(|sample| Rollup::of(sample, sample.at))
+                .collect(),
+            Resolution::Hourly => city.hourly.iter().copied().collect(),
+            Resolution::Daily => city.daily.iter().copied().collect(),
+        })
+    }
+
+    /// Every tier of a city (lowercase name); `None` for an unrecorded city
+    pub fn tiers(&self, city: &str) -> Option<CityTiers> {
+        let cities = self.cities.read().unwrap_or_else(|e| e.into_inner());
+        let city = cities.get(city)?;
+        Some(CityTiers {
+            daily: city.daily.iter().copied().collect(),
+            hourly: city.hourly.iter().copied().collect(),
+            raw: city.raw.iter().copied().collect(),
+        })
+    }
+
+    /// When the most recent sample was recorded
+    pub fn last_recorded(&self) -> Option<DateTime<Utc>> {
+        let cities = self.cities.read().unwrap_or_else(|e| e.into_inner());
+        cities
+            .values()
+            .filter_map(|c| c.raw.back())
+            .map(|s| s.at)
+            .max()
+    }
+
+    /// Samples for every city, oldest first
+    pub fn all(&self) -> HashMap<String, Vec<Sample>> {
+        let cities = self.cities.read().unwrap_or_else(|e| e.into_inner());
+        cities
+            .iter()
+            .map(|(city, c)| (city.clone(), c.raw.iter().copied().collect()))
+            .collect()
+    }
+
+    pub fn size(&self) -> StoreSize {
+        let cities = self.cities.read().unwrap_or_else(|e| e.into_inner());
+        cities
+            .values()
+            .fold(StoreSize::default(), |size, c| StoreSize {
+                raw: size.raw + c.raw.len(),
+                hourly: size.hourly + c.hourly.len(),
+                daily: size.daily + c.daily.len(),
+            })
+    }
+
+    /// Compaction runs since startup
+    pub fn compactions(&self) -> u64 {
+        self.compactions.load(Ordering::Relaxed)
+    }
+
+    pub fn last_compaction(&self) -> Option<DateTime<Utc>> {
+        *self
+            .last_compaction
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Record every city's current weather each `interval` and analyze the result
+pub fn spawn_sampler(state: AppState, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let readings = state.all_weather();
+            let at = Utc::now();
+            state.history.record(&readings, at);
+            state.events.publish(UpdateEvent::HistoryRecorded {
+                cities: readings.len(),
+                at,
+            });
+            for anomaly in state.anomalies.analyze(&state.history) {
+                state.events.publish(UpdateEvent::AnomalyDetected(anomaly));
+            }
+            for alert in state.alerts.evaluate(&state.history) {
+                state.events.publish(UpdateEvent::AlertFired(alert));
+            }
+        }
+    });
+}
+
+/// Run the compaction every `interval`
+pub fn spawn_compactor(state: AppState, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let done = state.history.compact(Utc::now());
+            if done != Compaction::default() {
+                println!(
+                    "🗜️  History compacted: {} samples into hours, {} hours into days, {} days dropped",
+                    done.raw_rolled_up, done.hourly_rolled_up, done.daily_dropped
+                );
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    resolution: Resolution,
+}
+
+/// Recorded history of one city at `raw`, `hourly` or `daily` resolution
+///
+/// Raw points are single samples (`samples` is 1). Each tier only holds what
+/// the others do not: hours start where the raw samples were compacted away.
+pub async fn get_history(
+    Scoped(state): Scoped,
+    Path(city): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let key = city.trim().to_lowercase();
+    println!(
+        "📈 Received history request for {} ({:?})",
+        key, query.resolution
+    );
+
+    // The history covers global cities only
+    let points = state
+        .shares(&key)
+        .then(|| state.history.rollups(&key, query.resolution))
+        .flatten()
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("No history recorded for '{}'", city),
+                    code: 404,
+                }),
+            )
+        })?;
+    let rounding = state.config.rounding();
+    let points: Vec<Rollup> = points.into_iter().map(|p| p.rounded(rounding)).collect();
+
+    Ok(Json(serde_json::json!({
+        "city": key,
+        "resolution": query.resolution,
+        "count": points.len(),
+        "points": points
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(city: &str, temperature: i32) -> WeatherData {
+        WeatherData {
+            temperature,
+            ..WeatherData::unknown(city)
+        }
+    }
+
+    #[test]
+    fn test_history_is_bounded_and_follows_the_city_list() {
+        let history = WeatherHistory::new(2, Retention::default());
+        let now = Utc::now();
+
+        history.record(&[reading("oslo", 1), reading("rome", 20)], now);
+        history.record(&[reading("oslo", 2), reading("rome", 21)], now);
+        history.record(&[reading("oslo", 3)], now);
+
+        let oslo: Vec<i32> = history.city("oslo").iter().map(|s| s.temperature).collect();
+        assert_eq!(oslo, vec![2, 3]);
+        assert!(history.city("rome").is_empty());
+        // The sample pushed out by the capacity was rolled up, not lost
+        assert_eq!(history.size().hourly, 1);
+    }
+
+    #[test]
+    fn test_compaction_rolls_up_expired_tiers() {
+        let retention = Retention {
+            raw: Duration::from_secs(2 * 3600),
+            hourly: Duration::from_secs(2 * 86_400),
+            daily: Some(Duration::from_secs(4 * 86_400)),
+        };
+        let history = WeatherHistory::new(1000, retention);
+        let start = DateTime::parse_from_rfc3339("2026-10-10T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // Every 30 minutes for five days
+        for step in 0..240 {
+            let at = start + TimeDelta::minutes(30 * step);
+            history.record(&[reading("oslo", (step % 4) as i32)], at);
+        }
+
+        let now = start + TimeDelta::days(5);
+        let done = history.compact(now);
+        let size = history.size();
+        // The last two hours stay raw
+        assert_eq!(size.raw, 4);
+        assert_eq!(done.raw_rolled_up, 236);
+        // Hours of the last two days stay hourly, earlier ones become days
+        assert_eq!(size.hourly, 46);
+        assert_eq!(size.daily, 2);
+        assert_eq!(done.daily_dropped, 1);
+
+        let days = history.rollups("oslo", Resolution::Daily).unwrap();
+        assert_eq!(days[0].start, start + TimeDelta::days(1));
+        assert_eq!(days[0].samples, 48);
+        assert_eq!((days[0].temperature.min, days[0].temperature.max), (0, 3));
+        assert_eq!(days[0].temperature.average, 1.5);
+
+        // Nothing left to do on a second run
+        assert_eq!(history.compact(now), Compaction::default());
+        assert_eq!(history.compactions(), 2);
+    }
+}
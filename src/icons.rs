@@ -0,0 +1,63 @@
+use serde::Serialize;
+
+/// Stable icon codes for weather conditions
+///
+/// Clients should pick their artwork by `icon` instead of matching on
+/// conditions. Codes are never renamed; new ones may be added. Each
+/// condition's icon is given by [`Condition::icon`](crate::conditions::Condition::icon).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Icon {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Rain,
+    Snow,
+    Fog,
+    Haze,
+    Wind,
+    Hot,
+    Humid,
+    Cold,
+    Variable,
+    Unknown,
+}
+
+impl Icon {
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Icon::Clear => "☀️",
+            Icon::PartlyCloudy => "⛅",
+            Icon::Cloudy => "☁️",
+            Icon::Rain => "🌧️",
+            Icon::Snow => "❄️",
+            Icon::Fog => "🌫️",
+            Icon::Haze => "🌁",
+            Icon::Wind => "💨",
+            Icon::Hot => "🔥",
+            Icon::Humid => "💦",
+            Icon::Cold => "🥶",
+            Icon::Variable => "🌦️",
+            Icon::Unknown => "❔",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::Condition;
+
+    #[test]
+    fn test_condition_icons() {
+        assert_eq!(Condition::Overcast.icon(), Icon::Cloudy);
+        assert_eq!(
+            Condition::classify("Hot & Humid".into()).0.icon(),
+            Icon::Humid
+        );
+        assert_eq!(
+            serde_json::to_value(Icon::PartlyCloudy).unwrap(),
+            "partly-cloudy"
+        );
+    }
+}
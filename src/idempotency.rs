@@ -0,0 +1,340 @@
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{error_response, AppState};
+
+/// Request header carrying the client-chosen key
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Response header set when a stored response is replayed
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Largest request or response body the cache will buffer
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Stored outcome of the first request made with a key
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+#[derive(Debug)]
+enum Entry {
+    /// The first request is still being handled
+    InFlight { fingerprint: u64 },
+    Done {
+        fingerprint: u64,
+        response: StoredResponse,
+        /// `None` when the window is too long to reach
+        expires: Option<Instant>,
+    },
+}
+
+/// First responses to mutating requests, keyed by `Idempotency-Key`
+///
+/// Retries with the same key and the same request replay the stored
+/// response instead of running the handler again. Entries expire after the
+/// configured window; server errors are not stored, so they can be retried.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Size and effectiveness of the cache, for `/admin/status`
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// Requests answered from a stored response
+    pub hits: u64,
+    /// Requests that ran the handler
+    pub misses: u64,
+}
+
+enum Lookup {
+    New,
+    Replay(StoredResponse),
+    InFlight,
+    Mismatch,
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration) -> Self {
+        IdempotencyCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats {
+            entries: entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Claim `key` for a new request, or report what is already stored
+    fn begin(&self, key: &str, fingerprint: u64) -> Lookup {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        entries.retain(
+            |_, entry| !matches!(entry, Entry::Done { expires: Some(expires), .. } if *expires <= now),
+        );
+
+        match entries.get(key) {
+            Some(Entry::InFlight { fingerprint: f } | Entry::Done { fingerprint: f, .. })
+                if *f != fingerprint =>
+            {
+                Lookup::Mismatch
+            }
+            Some(Entry::InFlight { .. }) => Lookup::InFlight,
+            Some(Entry::Done { response, .. }) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Lookup::Replay(response.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                entries.insert(key.to_string(), Entry::InFlight { fingerprint });
+                Lookup::New
+            }
+        }
+    }
+
+    /// Store the response for a claimed key, or release the key if there is
+    /// nothing worth replaying
+    fn finish(&self, key: &str, fingerprint: u64, response: Option<StoredResponse>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match response {
+            Some(response) => {
+                let expires = Instant::now().checked_add(self.ttl);
+                entries.insert(
+                    key.to_string(),
+                    Entry::Done {
+                        fingerprint,
+                        response,
+                        expires,
+                    },
+                );
+            }
+            None => {
+                entries.remove(key);
+            }
+        }
+    }
+}
+
+/// A key claimed by a request in progress
+///
+/// Released when dropped unfinished, as happens when a timeout or a client
+/// disconnect drops the request's future, so retries are not refused forever.
+struct Claim<'a> {
+    cache: &'a IdempotencyCache,
+    key: String,
+    fingerprint: u64,
+    finished: bool,
+}
+
+impl Claim<'_> {
+    fn finish(mut self, response: Option<StoredResponse>) {
+        self.cache.finish(&self.key, self.fingerprint, response);
+        self.finished = true;
+    }
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.cache.finish(&self.key, self.fingerprint, None);
+        }
+    }
+}
+
+/// Middleware honoring `Idempotency-Key` on POST requests
+pub async fn idempotency(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+    else {
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request body too large".to_string(),
+        )
+        .into_response();
+    };
+
+    // Keys are scoped to the route, and reusing one for a different request is an error
+    let scoped_key = format!("{} {}", parts.uri.path(), key);
+    let mut hasher = DefaultHasher::new();
+    parts.uri.path().hash(&mut hasher);
+    body.hash(&mut hasher);
+    let fingerprint = hasher.finish();
+
+    let cache = &state.idempotency;
+    match cache.begin(&scoped_key, fingerprint) {
+        Lookup::Replay(stored) => {
+            println!("🔁 Replaying stored response for Idempotency-Key {}", key);
+            return replay(stored);
+        }
+        Lookup::InFlight => {
+            return error_response(
+                StatusCode::CONFLICT,
+                format!(
+                    "A request with Idempotency-Key '{}' is still in progress",
+                    key
+                ),
+            )
+            .into_response();
+        }
+        Lookup::Mismatch => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!(
+                    "Idempotency-Key '{}' was already used for a different request",
+                    key
+                ),
+            )
+            .into_response();
+        }
+        Lookup::New => {}
+    }
+    let claim = Claim {
+        cache,
+        key: scoped_key,
+        fingerprint,
+        finished: false,
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            claim.finish(None);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Response body too large".to_string(),
+            )
+            .into_response();
+        }
+    };
+
+    let stored = (!parts.status.is_server_error()).then(|| StoredResponse {
+        status: parts.status,
+        content_type: parts.headers.get(header::CONTENT_TYPE).cloned(),
+        body: body.clone(),
+    });
+    claim.finish(stored);
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let mut response = (stored.status, stored.body).into_response();
+    let headers = response.headers_mut();
+    if let Some(content_type) = stored.content_type {
+        headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored() -> StoredResponse {
+        StoredResponse {
+            status: StatusCode::CREATED,
+            content_type: None,
+            body: Bytes::from_static(b"{}"),
+        }
+    }
+
+    #[test]
+    fn test_replays_matching_requests_only() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+
+        assert!(matches!(cache.begin("k", 1), Lookup::New));
+        assert!(matches!(cache.begin("k", 1), Lookup::InFlight));
+        cache.finish("k", 1, Some(stored()));
+
+        assert!(
+            matches!(cache.begin("k", 1), Lookup::Replay(r) if r.status == StatusCode::CREATED)
+        );
+        assert!(matches!(cache.begin("k", 2), Lookup::Mismatch));
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_failed_and_expired_requests_can_be_retried() {
+        let cache = IdempotencyCache::new(Duration::ZERO);
+
+        assert!(matches!(cache.begin("a", 1), Lookup::New));
+        cache.finish("a", 1, None);
+        assert!(matches!(cache.begin("a", 1), Lookup::New));
+
+        cache.finish("a", 1, Some(stored()));
+        assert!(matches!(cache.begin("a", 1), Lookup::New));
+
+        let forever = IdempotencyCache::new(Duration::MAX);
+        forever.finish("a", 1, Some(stored()));
+        assert!(matches!(forever.begin("a", 1), Lookup::Replay(_)));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_requests_release_their_key() {
+        use crate::cities::{CityDatabase, CityStore};
+        use axum::{middleware, routing::post, Router};
+        use clap::Parser;
+        use std::sync::Arc;
+        use tower::ServiceExt;
+
+        let state = AppState::new(
+            crate::config::ServerConfig::parse_from(["server"]),
+            Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let app = Router::new()
+            .route("/slow", post(std::future::pending::<StatusCode>))
+            .layer(middleware::from_fn_with_state(state.clone(), idempotency));
+        let request = Request::post("/slow")
+            .header(IDEMPOTENCY_KEY, "k")
+            .body(Body::empty())
+            .unwrap();
+
+        let timed_out = tokio::time::timeout(Duration::from_millis(20), app.oneshot(request)).await;
+        assert!(timed_out.is_err());
+        assert_eq!(state.idempotency.stats().entries, 0);
+    }
+}
@@ -0,0 +1,278 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::conditions::Condition;
+
+/// Rules used without `--condition-rules`, in the file's own format
+pub const DEFAULT_RULES: &str = r#"
+[[rule]]
+condition = "snow"
+precipitation = { min = 0.1 }
+temperature = { max = 0 }
+
+[[rule]]
+condition = "rain"
+precipitation = { min = 0.1 }
+
+[[rule]]
+condition = "overcast"
+cloud_cover = { min = 90 }
+
+[[rule]]
+condition = "cloudy"
+cloud_cover = { min = 60 }
+
+[[rule]]
+condition = "partly_cloudy"
+cloud_cover = { min = 20 }
+
+[[rule]]
+condition = "clear"
+cloud_cover = { max = 20 }
+"#;
+
+/// Raw numeric readings a condition is inferred from
+///
+/// Sources that do not measure something leave it `None`, and rules bounding
+/// it then do not apply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurements {
+    /// °C
+    pub temperature: f64,
+    /// Relative humidity in percent
+    pub humidity: f64,
+    /// km/h
+    pub wind_speed: f64,
+    /// mm per hour
+    pub precipitation: Option<f64>,
+    /// Percent of the sky covered
+    pub cloud_cover: Option<f64>,
+}
+
+/// Inclusive bounds on one measurement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bounds {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Bounds {
+    fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+
+    fn admits(&self, value: Option<f64>) -> bool {
+        if self.is_unbounded() {
+            return true;
+        }
+        value.is_some_and(|value| {
+            self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+        })
+    }
+}
+
+/// One row of the rules table: the condition to report when every bound holds
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionRule {
+    pub condition: Condition,
+    /// Free-text wording reported with the condition, e.g. "Light rain"
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub temperature: Bounds,
+    #[serde(default)]
+    pub humidity: Bounds,
+    #[serde(default)]
+    pub wind_speed: Bounds,
+    #[serde(default)]
+    pub precipitation: Bounds,
+    #[serde(default)]
+    pub cloud_cover: Bounds,
+}
+
+impl ConditionRule {
+    fn matches(&self, m: &Measurements) -> bool {
+        self.temperature.admits(Some(m.temperature))
+            && self.humidity.admits(Some(m.humidity))
+            && self.wind_speed.admits(Some(m.wind_speed))
+            && self.precipitation.admits(m.precipitation)
+            && self.cloud_cover.admits(m.cloud_cover)
+    }
+
+    fn bounds(&self) -> [(&'static str, Bounds); 5] {
+        [
+            ("temperature", self.temperature),
+            ("humidity", self.humidity),
+            ("wind_speed", self.wind_speed),
+            ("precipitation", self.precipitation),
+            ("cloud_cover", self.cloud_cover),
+        ]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<ConditionRule>,
+}
+
+#[derive(Debug)]
+pub enum RulesError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            RulesError::Parse(path, e) => {
+                write!(f, "invalid condition rules file {}: {}", path.display(), e)
+            }
+            RulesError::Invalid(path, reason) => {
+                write!(
+                    f,
+                    "invalid condition rules file {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl Error for RulesError {}
+
+/// Ordered table deriving a condition from raw measurements
+///
+/// The first rule whose bounds all hold wins. When none does, the caller
+/// keeps the condition it already had (for simulated readings, the city's
+/// static condition). The icon always follows the condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionRules {
+    rules: Vec<ConditionRule>,
+}
+
+impl Default for ConditionRules {
+    fn default() -> Self {
+        Self::parse(DEFAULT_RULES).expect("the default condition rules are valid")
+    }
+}
+
+impl ConditionRules {
+    /// Load a TOML file of `[[rule]]` entries replacing the default table
+    pub fn load(path: &Path) -> Result<Self, RulesError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| RulesError::Read(path.into(), e))?;
+        let file: RulesFile =
+            toml::from_str(&contents).map_err(|e| RulesError::Parse(path.into(), e))?;
+        Self::new(file.rule).map_err(|reason| RulesError::Invalid(path.into(), reason))
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let file: RulesFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        Self::new(file.rule)
+    }
+
+    fn new(rules: Vec<ConditionRule>) -> Result<Self, String> {
+        if rules.is_empty() {
+            return Err("no rules defined".to_string());
+        }
+        for (index, rule) in rules.iter().enumerate() {
+            for (name, bounds) in rule.bounds() {
+                if let (Some(min), Some(max)) = (bounds.min, bounds.max) {
+                    if min > max {
+                        return Err(format!(
+                            "rule {} ({}): {} min {} is above max {}",
+                            index + 1,
+                            rule.condition.code(),
+                            name,
+                            min,
+                            max
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(ConditionRules { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// The first rule matching `measurements`, if any
+    pub fn infer(&self, measurements: &Measurements) -> Option<&ConditionRule> {
+        self.rules.iter().find(|rule| rule.matches(measurements))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measured(temperature: f64, precipitation: f64, cloud_cover: Option<f64>) -> Measurements {
+        Measurements {
+            temperature,
+            humidity: 80.0,
+            wind_speed: 10.0,
+            precipitation: Some(precipitation),
+            cloud_cover,
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = ConditionRules::default();
+        let condition = |m| rules.infer(&m).map(|rule| rule.condition);
+
+        assert_eq!(condition(measured(-2.0, 1.5, None)), Some(Condition::Snow));
+        assert_eq!(condition(measured(4.0, 1.5, None)), Some(Condition::Rain));
+        assert_eq!(
+            condition(measured(4.0, 0.0, Some(95.0))),
+            Some(Condition::Overcast)
+        );
+        assert_eq!(
+            condition(measured(4.0, 0.0, Some(20.0))),
+            Some(Condition::PartlyCloudy)
+        );
+        assert_eq!(
+            condition(measured(4.0, 0.0, Some(5.0))),
+            Some(Condition::Clear)
+        );
+        // Nothing is known about the sky, so no rule applies
+        assert_eq!(condition(measured(4.0, 0.0, None)), None);
+
+        let custom = ConditionRules::parse(
+            r#"
+            [[rule]]
+            condition = "fog"
+            description = "Freezing fog"
+            humidity = { min = 95 }
+            temperature = { max = 0 }
+            "#,
+        )
+        .unwrap();
+        let rule = custom.infer(&Measurements {
+            humidity: 98.0,
+            ..measured(-1.0, 0.0, None)
+        });
+        assert_eq!(
+            rule.and_then(|r| r.description.as_deref()),
+            Some("Freezing fog")
+        );
+
+        assert!(ConditionRules::parse("rule = []").is_err());
+        let inverted = "[[rule]]\ncondition = \"hot\"\ntemperature = { min = 30, max = 20 }";
+        assert!(ConditionRules::parse(inverted).is_err());
+        assert!(
+            ConditionRules::parse("[[rule]]\ncondition = \"hot\"\nheat = { min = 30 }").is_err()
+        );
+    }
+}
@@ -0,0 +1,583 @@
+use axum::body::{to_bytes, Body};
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderName, HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lambda_runtime::{service_fn, Diagnostic, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use tower::ServiceExt;
+
+use crate::error_response;
+
+/// Set by AWS Lambda to the address of its runtime API
+pub const RUNTIME_API: &str = "AWS_LAMBDA_RUNTIME_API";
+
+/// Largest response body passed back (Lambda's own limit is 6 MB)
+const MAX_RESPONSE_BYTES: usize = 6 * 1024 * 1024;
+/// Header classifying an error reported to the runtime API
+const ERROR_TYPE_HEADER: &str = "lambda-runtime-function-error-type";
+
+/// Trace header passed on to the router, as API Gateway and ALB send it
+const AMZN_TRACE_ID: &str = "x-amzn-trace-id";
+
+/// Which integration sent an event, and so how it expects the answer
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Source {
+    /// API Gateway HTTP APIs and function URLs (payload 2.0)
+    Http,
+    /// API Gateway REST APIs (payload 1.0)
+    Rest,
+    /// Application Load Balancers; `multi_value` when the target group has
+    /// multi-value headers turned on
+    Alb { multi_value: bool },
+}
+
+/// An HTTP request from API Gateway HTTP APIs or function URLs (payload 2.0)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpEvent {
+    raw_path: String,
+    #[serde(default)]
+    raw_query_string: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    cookies: Vec<String>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    is_base64_encoded: bool,
+    request_context: EventContext,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventContext {
+    http: EventHttp,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventHttp {
+    method: String,
+    #[serde(default)]
+    source_ip: Option<String>,
+}
+
+/// An HTTP request from API Gateway REST APIs or an Application Load
+/// Balancer (payload 1.0); absent maps come as `null`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestEvent {
+    http_method: String,
+    path: String,
+    #[serde(default)]
+    headers: Option<HashMap<String, String>>,
+    #[serde(default)]
+    multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    #[serde(default)]
+    query_string_parameters: Option<HashMap<String, String>>,
+    #[serde(default)]
+    multi_value_query_string_parameters: Option<HashMap<String, Vec<String>>>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    is_base64_encoded: bool,
+    #[serde(default)]
+    request_context: RestContext,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RestContext {
+    /// Set by REST APIs
+    #[serde(default)]
+    identity: Option<RestIdentity>,
+    /// Set by load balancers
+    #[serde(default)]
+    elb: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestIdentity {
+    #[serde(default)]
+    source_ip: Option<String>,
+}
+
+/// An event's request, whichever payload format it came in
+struct Incoming {
+    method: String,
+    /// Path and query string
+    uri: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    is_base64_encoded: bool,
+    source_ip: Option<IpAddr>,
+}
+
+impl HttpEvent {
+    fn into_incoming(self) -> Incoming {
+        let uri = if self.raw_query_string.is_empty() {
+            self.raw_path
+        } else {
+            format!("{}?{}", self.raw_path, self.raw_query_string)
+        };
+        let mut headers: Vec<(String, String)> = self.headers.into_iter().collect();
+        // Payload 2.0 moves cookies out of the headers
+        if !self.cookies.is_empty() {
+            headers.push((header::COOKIE.to_string(), self.cookies.join("; ")));
+        }
+        Incoming {
+            method: self.request_context.http.method,
+            uri,
+            headers,
+            body: self.body,
+            is_base64_encoded: self.is_base64_encoded,
+            source_ip: self
+                .request_context
+                .http
+                .source_ip
+                .and_then(|ip| ip.parse().ok()),
+        }
+    }
+}
+
+impl RestEvent {
+    fn source(&self) -> Source {
+        match self.request_context.elb {
+            Some(_) => Source::Alb {
+                multi_value: self.multi_value_headers.is_some(),
+            },
+            None => Source::Rest,
+        }
+    }
+
+    fn into_incoming(self) -> Incoming {
+        let alb = self.request_context.elb.is_some();
+        let headers: Vec<(String, String)> = match self.multi_value_headers {
+            Some(multi) => multi
+                .into_iter()
+                .flat_map(|(name, values)| values.into_iter().map(move |v| (name.clone(), v)))
+                .collect(),
+            None => self.headers.unwrap_or_default().into_iter().collect(),
+        };
+        let parameters: Vec<(String, String)> = match self.multi_value_query_string_parameters {
+            Some(multi) => multi
+                .into_iter()
+                .flat_map(|(name, values)| values.into_iter().map(move |v| (name.clone(), v)))
+                .collect(),
+            None => self
+                .query_string_parameters
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        };
+        // Load balancers pass the query string on as sent; REST APIs decode it
+        let query = if alb {
+            parameters
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("&")
+        } else {
+            let mut url = reqwest::Url::parse("http://lambda/").expect("valid URL");
+            url.query_pairs_mut().extend_pairs(&parameters);
+            url.query().unwrap_or_default().to_string()
+        };
+        let uri = if query.is_empty() {
+            self.path
+        } else {
+            format!("{}?{}", self.path, query)
+        };
+        // Load balancers only report the client in X-Forwarded-For
+        let source_ip = match self.request_context.identity {
+            Some(identity) => identity.source_ip,
+            None => headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("x-forwarded-for"))
+                .and_then(|(_, value)| value.split(',').next())
+                .map(|ip| ip.trim().to_string()),
+        };
+        Incoming {
+            method: self.http_method,
+            uri,
+            headers,
+            body: self.body,
+            is_base64_encoded: self.is_base64_encoded,
+            source_ip: source_ip.and_then(|ip| ip.parse().ok()),
+        }
+    }
+}
+
+impl Incoming {
+    fn into_request(self) -> Result<Request<Body>, String> {
+        let body = match self.body {
+            Some(body) if self.is_base64_encoded => STANDARD
+                .decode(body)
+                .map_err(|e| format!("invalid base64 body: {}", e))?,
+            Some(body) => body.into_bytes(),
+            None => Vec::new(),
+        };
+
+        let mut request = Request::builder()
+            .method(self.method.as_str())
+            .uri(self.uri)
+            .body(Body::from(body))
+            .map_err(|e| format!("invalid request: {}", e))?;
+        let headers = request.headers_mut();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        // The client's address, as a listener would report it (GET /weather/me)
+        if let Some(ip) = self.source_ip {
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::new(ip, 0)));
+        }
+        Ok(request)
+    }
+}
+
+/// The response to an [`HttpEvent`]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpResponse {
+    status_code: u16,
+    headers: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cookies: Vec<String>,
+    body: String,
+    is_base64_encoded: bool,
+}
+
+/// The response to a [`RestEvent`]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RestResponse {
+    status_code: u16,
+    /// Required by load balancers, e.g. `200 OK`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_description: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    headers: HashMap<String, String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    multi_value_headers: HashMap<String, Vec<String>>,
+    body: String,
+    is_base64_encoded: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum EventResponse {
+    Http(HttpResponse),
+    Rest(RestResponse),
+}
+
+impl EventResponse {
+    async fn from_response(source: Source, response: Response) -> Result<Self, String> {
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, MAX_RESPONSE_BYTES)
+            .await
+            .map_err(|e| format!("cannot read response body: {}", e))?;
+        let (body, is_base64_encoded) = match String::from_utf8(body.to_vec()) {
+            Ok(text) => (text, false),
+            Err(_) => (STANDARD.encode(&body), true),
+        };
+        let status_code = parts.status.as_u16();
+        let headers = parts
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())));
+
+        // One value per name: repeated headers are joined, except cookies,
+        // which payload 2.0 lists apart and single-value ALBs keep the last of
+        let mut joined: HashMap<String, String> = HashMap::new();
+        let mut cookies = Vec::new();
+        let mut multi_value: HashMap<String, Vec<String>> = HashMap::new();
+        let single_value = matches!(source, Source::Http | Source::Alb { multi_value: false });
+        for (name, value) in headers {
+            if single_value {
+                if name == header::SET_COOKIE.as_str() {
+                    cookies.push(value);
+                    continue;
+                }
+                joined
+                    .entry(name)
+                    .and_modify(|joined| {
+                        joined.push_str(", ");
+                        joined.push_str(&value);
+                    })
+                    .or_insert(value);
+            } else {
+                multi_value.entry(name).or_default().push(value);
+            }
+        }
+
+        Ok(match source {
+            Source::Http => EventResponse::Http(HttpResponse {
+                status_code,
+                headers: joined,
+                cookies,
+                body,
+                is_base64_encoded,
+            }),
+            Source::Rest | Source::Alb { .. } => {
+                if let Some(cookie) = cookies.pop() {
+                    joined.insert(header::SET_COOKIE.to_string(), cookie);
+                }
+                let status_description = matches!(source, Source::Alb { .. }).then(|| {
+                    format!(
+                        "{} {}",
+                        status_code,
+                        parts.status.canonical_reason().unwrap_or_default()
+                    )
+                });
+                EventResponse::Rest(RestResponse {
+                    status_code,
+                    status_description,
+                    headers: joined,
+                    multi_value_headers: multi_value,
+                    body,
+                    is_base64_encoded,
+                })
+            }
+        })
+    }
+}
+
+/// Answer one invocation with the router, as the listener would answer a request
+///
+/// `trace_id` is the invocation's X-Ray trace header, passed on as
+/// `X-Amzn-Trace-Id` unless the request already carries one. Each invocation
+/// gets one complete body back, so event streams (`GET /events`) are answered
+/// with `501` instead of being read until they end, which they never do.
+async fn invoke(
+    app: &Router,
+    event: serde_json::Value,
+    trace_id: Option<&str>,
+) -> Result<EventResponse, String> {
+    let unsupported = |e: serde_json::Error| format!("unsupported event: {}", e);
+    let (source, incoming) = if event.get("rawPath").is_some() {
+        let event: HttpEvent = serde_json::from_value(event).map_err(unsupported)?;
+        (Source::Http, event.into_incoming())
+    } else if event.get("httpMethod").is_some() {
+        let event: RestEvent = serde_json::from_value(event).map_err(unsupported)?;
+        (event.source(), event.into_incoming())
+    } else {
+        return Err(
+            "unsupported event: expected an API Gateway, function URL or load balancer request"
+                .to_string(),
+        );
+    };
+
+    let mut request = incoming.into_request()?;
+    if let Some(trace_id) = trace_id.and_then(|id| HeaderValue::from_str(id).ok()) {
+        request
+            .headers_mut()
+            .entry(AMZN_TRACE_ID)
+            .or_insert(trace_id);
+    }
+    let response = app
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap_or_else(|never| match never {});
+    let streaming = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"));
+    let response = if streaming {
+        error_response(
+            StatusCode::NOT_IMPLEMENTED,
+            "Event streams are not available when running on AWS Lambda".to_string(),
+        )
+        .into_response()
+    } else {
+        response
+    };
+    EventResponse::from_response(source, response).await
+}
+
+/// Tell the runtime API the function cannot start, so the failure is
+/// reported as an init error rather than a timeout of the first invocation
+///
+/// Startup checks fail before [`run`] hands over to `lambda_runtime`, which
+/// only reports errors of its own initialization.
+pub async fn report_init_error(api: &str, message: &str) -> Result<(), reqwest::Error> {
+    reqwest::Client::new()
+        .post(format!("http://{}/2018-06-01/runtime/init/error", api))
+        .header(ERROR_TYPE_HEADER, "Runtime.InitError")
+        .json(&serde_json::json!({
+            "errorMessage": message,
+            "errorType": "Runtime.InitError"
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Serve invocations with `lambda_runtime` until the runtime API goes away
+///
+/// Used instead of the TCP listener when `AWS_LAMBDA_RUNTIME_API` is set,
+/// with API Gateway (HTTP or REST APIs), a function URL or an Application
+/// Load Balancer in front. Each invocation is one request, so streams
+/// (`GET /events`) do not fit, and background jobs such as the history
+/// sampler only run while an invocation is in progress.
+pub async fn run(app: Router) -> Result<(), lambda_runtime::Error> {
+    lambda_runtime::run(service_fn(move |event: LambdaEvent<serde_json::Value>| {
+        let app = app.clone();
+        async move {
+            let trace_id = event.context.xray_trace_id.as_deref();
+            invoke(&app, event.payload, trace_id)
+                .await
+                .map_err(|error| {
+                    println!(
+                        "⚠️  Lambda invocation {} failed: {}",
+                        event.context.request_id, error
+                    );
+                    Diagnostic {
+                        error_type: "InvalidEvent".to_string(),
+                        error_message: error,
+                    }
+                })
+        }
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, response::AppendHeaders, routing::post};
+
+    fn echo() -> Router {
+        Router::new().route(
+            "/echo",
+            post(
+                |headers: HeaderMap, uri: axum::http::Uri, body: String| async move {
+                    let header = |name: &str| {
+                        headers
+                            .get(name)
+                            .map_or("-", |value| value.to_str().unwrap())
+                            .to_string()
+                    };
+                    (
+                        AppendHeaders([
+                            (header::SET_COOKIE, "seen=1"),
+                            (header::SET_COOKIE, "again=1"),
+                        ]),
+                        format!(
+                            "{} {} {} {}",
+                            header("cookie"),
+                            uri.query().unwrap_or("-"),
+                            header(AMZN_TRACE_ID),
+                            body
+                        ),
+                    )
+                },
+            ),
+        )
+    }
+
+    fn json(response: EventResponse) -> serde_json::Value {
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_invocation_round_trip() {
+        let event = serde_json::json!({
+            "version": "2.0",
+            "rawPath": "/echo",
+            "rawQueryString": "units=metric",
+            "headers": {"content-type": "text/plain"},
+            "cookies": ["a=1", "b=2"],
+            "body": STANDARD.encode("hello"),
+            "isBase64Encoded": true,
+            "requestContext": {"http": {"method": "POST", "sourceIp": "203.0.113.7"}}
+        });
+
+        let response = json(invoke(&echo(), event, Some("Root=1-abc")).await.unwrap());
+        assert_eq!(response["statusCode"], 200);
+        assert_eq!(response["body"], "a=1; b=2 units=metric Root=1-abc hello");
+        assert_eq!(response["isBase64Encoded"], false);
+        assert_eq!(
+            response["cookies"],
+            serde_json::json!(["seen=1", "again=1"])
+        );
+
+        assert!(
+            invoke(&echo(), serde_json::json!({"source": "aws.events"}), None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_api_and_load_balancer_events() {
+        let rest = serde_json::json!({
+            "httpMethod": "POST",
+            "path": "/echo",
+            "headers": {"Cookie": "a=1"},
+            "multiValueHeaders": {"Cookie": ["a=1"], "X-Amzn-Trace-Id": ["Root=1-rest"]},
+            "queryStringParameters": {"city": "São Paulo"},
+            "multiValueQueryStringParameters": {"city": ["São Paulo"]},
+            "body": "hi",
+            "isBase64Encoded": false,
+            "requestContext": {"identity": {"sourceIp": "203.0.113.7"}}
+        });
+        let response = json(invoke(&echo(), rest, Some("Root=1-lambda")).await.unwrap());
+        assert_eq!(response["body"], "a=1 city=S%C3%A3o+Paulo Root=1-rest hi");
+        assert_eq!(
+            response["multiValueHeaders"]["set-cookie"],
+            serde_json::json!(["seen=1", "again=1"])
+        );
+        assert!(response.get("statusDescription").is_none());
+
+        let alb = serde_json::json!({
+            "httpMethod": "POST",
+            "path": "/echo",
+            "headers": {"x-forwarded-for": "203.0.113.7"},
+            "queryStringParameters": {"city": "S%C3%A3o%20Paulo"},
+            "body": "hi",
+            "isBase64Encoded": false,
+            "requestContext": {"elb": {"targetGroupArn": "arn:aws:elasticloadbalancing:..."}}
+        });
+        let response = json(invoke(&echo(), alb, None).await.unwrap());
+        assert_eq!(response["body"], "- city=S%C3%A3o%20Paulo - hi");
+        assert_eq!(response["statusDescription"], "200 OK");
+        assert_eq!(response["headers"]["set-cookie"], "again=1");
+    }
+
+    #[tokio::test]
+    async fn test_event_streams_are_refused() {
+        use axum::response::sse::{Event, Sse};
+        use axum::routing::get;
+
+        let app = Router::new().route(
+            "/events",
+            get(|| async {
+                Sse::new(futures_util::stream::pending::<
+                    Result<Event, std::convert::Infallible>,
+                >())
+            }),
+        );
+        let event = serde_json::json!({
+            "rawPath": "/events",
+            "requestContext": {"http": {"method": "GET"}}
+        });
+        let response =
+            tokio::time::timeout(std::time::Duration::from_secs(5), invoke(&app, event, None))
+                .await
+                .expect("the stream is not read")
+                .unwrap();
+        assert_eq!(json(response)["statusCode"], 501);
+    }
+}
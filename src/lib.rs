@@ -0,0 +1,1446 @@
+//! The weather API server as a library
+//!
+//! [`router`] builds the whole API from an [`AppState`] without starting
+//! anything, so other hosts can mount or drive it, and [`spawn_jobs`] starts
+//! the background jobs its routes rely on; [`run`] is the `server` binary.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use weather_api::{router, spawn_jobs, AppState, CityDatabase, CityStore, ServerConfig};
+//!
+//! # async fn host() {
+//! let config = ServerConfig::load();
+//! let cities = Arc::new(CityStore::new(CityDatabase::builtin()));
+//! let state = AppState::new(config, cities, None);
+//! spawn_jobs(&state);
+//! let (app, _manifest) = router(state);
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await.unwrap();
+//! axum::serve(listener, app).await.unwrap();
+//! # }
+//! ```
+
+// Admin API (soft delete and restore of cities)
+mod admin;
+// Rate-of-change alerts over the recorded history
+mod alerts;
+// Router composition, shared by the listener and embedding hosts
+mod app;
+// Per-city usage analytics
+mod analytics;
+// API keys with daily/monthly quotas
+mod api_keys;
+// Paginated bulk read of every city (GET /weather/all)
+mod bulk;
+// Anomaly detection over the recorded history
+mod anomalies;
+// Fault injection for --chaos
+mod chaos;
+// City database (built-in or loaded from --cities-file)
+mod cities;
+// Condition taxonomy (codes, labels and legacy free text)
+mod conditions;
+// Server configuration
+mod config;
+// Per-request locale, strictness, debug and API key (RequestContext)
+mod context;
+// Startup self-check of files, settings and the listening port
+mod preflight;
+// HTML dashboard module
+#[cfg(feature = "dashboard")]
+mod dashboard;
+// MCP API module
+mod mcp_api;
+// MCP protocol version negotiation, sessions and result envelopes
+mod mcp_session;
+// Monthly climate normals
+mod normals;
+// Duration parsing shared with the client
+mod duration;
+// Changes between two points of a city's history (GET /diff/:city)
+mod diff;
+// Broadcast hub for update events, and the SSE stream at GET /events
+mod events;
+// Scheduled weather digests posted to webhooks
+mod digests;
+// Sparse field selection (?fields=)
+mod fields;
+// Favorite cities per API key
+mod favorites;
+// Geographic queries (bounding boxes and distances)
+mod geo;
+// IP geolocation for /weather/me
+#[cfg(feature = "geoip")]
+mod geoip;
+// Icon codes for weather conditions
+mod icons;
+// Idempotency-Key replay for mutating requests
+mod idempotency;
+// Condition inference from raw measurements (--condition-rules)
+mod inference;
+// AWS Lambda runtime loop driving the router without a listener
+#[cfg(feature = "lambda")]
+mod lambda;
+// Accept-Language negotiation for rendered outputs
+mod locale;
+// Endpoint manifest built with the router (GET /.well-known/api-manifest)
+mod manifest;
+// In-memory history of recent readings
+mod history;
+// City ranking by closeness to ideal weather (GET /rank)
+mod ranking;
+// Continent and country rollups (GET /stats/regions)
+mod regions;
+// Hot reload of --cities-file
+mod reload;
+// Precision and tie-breaking of computed temperatures
+mod rounding;
+// Optional in-memory log of request summaries
+mod request_log;
+// Deterministic weather simulation
+mod simulation;
+// Several weather stations per city and their aggregation (GET /stations/:city)
+mod stations;
+// How long conditions last (GET /conditions/:city/streak)
+mod streaks;
+// Shared application state
+mod state;
+// Connection-limited accept loop (replaces axum::serve)
+mod serve;
+// HMAC signatures of webhook deliveries and MCP results
+mod signing;
+// Precomputed /stats responses for static data
+mod stats_cache;
+// Persistence of favorites, digests and webhooks (--data-file)
+mod store;
+// Delta sync of changed and removed cities (GET /sync)
+mod sync;
+// Per-API-key city sets layered over the global database
+mod tenants;
+// Per-route request timeouts
+mod timeouts;
+// ?debug=true traces of lookups and cache use (admin only), and joining
+// the caller's distributed trace
+mod trace;
+// W3C traceparent headers, shared with the client
+mod traceparent;
+// Startup cache warm-up and the readiness probe (GET /ready)
+mod warmup;
+// Outgoing webhook requests: signed delivery, and which URLs callers may
+// have the server post to
+mod webhook;
+// Per-city change webhooks for cache invalidation
+mod webhooks;
+
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use conditions::Condition;
+use context::RequestContext;
+use fields::FieldSelection;
+use icons::Icon;
+use rounding::TemperatureRounding;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tenants::Scoped;
+use tower::BoxError;
+
+pub use app::{router, spawn_jobs};
+pub use cities::{CityDatabase, CityStore};
+pub use config::ServerConfig;
+pub use manifest::Manifest;
+pub use state::AppState;
+
+#[derive(Debug, Deserialize)]
+struct WeatherRequest {
+    cities: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeatherQuery {
+    /// Comma-separated cities, for `GET /weather` (POST sends them in the body)
+    cities: Option<String>,
+    /// RFC 3339 instant to evaluate the simulation at, e.g. `2025-12-24T18:00:00Z`
+    at: Option<String>,
+    /// Comma-separated attributes to return, e.g. `city,temperature`
+    fields: Option<String>,
+    /// Add a summary of the requested cities when there are several
+    #[serde(default)]
+    summary: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct WeatherResponse {
+    data: HashMap<String, WeatherData>,
+    /// Echoes the requested instant for time-travel queries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<WeatherSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<trace::Trace>,
+}
+
+/// Aggregates over the known cities of a multi-city request
+#[derive(Debug, Serialize, PartialEq)]
+struct WeatherSummary {
+    /// Known cities the summary covers (unknown ones are left out)
+    cities: usize,
+    average_temp: f64,
+    warmest_city: String,
+    coldest_city: String,
+    /// Most frequent condition (first by code on ties)
+    common_condition: Condition,
+}
+
+impl WeatherSummary {
+    fn of(readings: &[&WeatherData], rounding: TemperatureRounding) -> Option<Self> {
+        let warmest = readings.iter().max_by_key(|w| w.temperature)?;
+        let coldest = readings.iter().min_by_key(|w| w.temperature)?;
+        let total: i32 = readings.iter().map(|w| w.temperature).sum();
+        let common_condition = Condition::most_common(readings.iter().map(|w| w.condition))?;
+
+        Some(WeatherSummary {
+            cities: readings.len(),
+            average_temp: rounding.round(total as f64 / readings.len() as f64),
+            warmest_city: warmest.city.to_string(),
+            coldest_city: coldest.city.to_string(),
+            common_condition,
+        })
+    }
+}
+
+/// Text fields borrow static strings (built-in city names and descriptions)
+/// where possible instead of allocating per city per request
+#[derive(Debug, Serialize, Clone)]
+pub struct WeatherData {
+    pub city: Cow<'static, str>,
+    pub temperature: i32,
+    pub condition: Condition,
+    /// Free-text wording finer than `condition`, e.g. "Hot & Sunny"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Cow<'static, str>>,
+    pub humidity: i32,
+    pub wind_speed: i32,
+    /// Stable icon code for `condition`, e.g. `partly-cloudy`
+    pub icon: Icon,
+    /// Where the values came from
+    pub source: Source,
+    /// The instant the values describe
+    pub observed_at: DateTime<Utc>,
+}
+
+/// Origin of a weather reading, so consumers can tell mock data from real
+/// observations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// The city database (built-in or `--cities-file`), unchanged over time
+    Static,
+    /// The `--simulate` model, varying with `observed_at`
+    Simulated,
+    /// Default values for a city that is not in the database
+    Fallback,
+}
+
+impl WeatherData {
+    /// Default data for unknown cities
+    pub fn unknown(city: &str) -> Self {
+        WeatherData {
+            city: Cow::Owned(city.to_string()),
+            temperature: 20,
+            condition: Condition::Unknown,
+            description: None,
+            icon: Icon::Unknown,
+            humidity: 50,
+            wind_speed: 10,
+            source: Source::Fallback,
+            observed_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    #[serde(default)]
+    sort: String,
+    /// Comma-separated attributes to return for each city
+    fields: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NearbyQuery {
+    lat: Option<f64>,
+    lon: Option<f64>,
+    /// Search radius in kilometers (default 100)
+    radius_km: Option<f64>,
+    /// Most cities to return (default 10)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CitiesQuery {
+    /// Return city objects with these attributes instead of plain names
+    fields: Option<String>,
+    /// Only cities inside `minLon,minLat,maxLon,maxLat`
+    bbox: Option<String>,
+    /// Return city objects with their current weather
+    #[serde(default, alias = "details")]
+    weather: bool,
+    /// Only cities currently in one of these comma-separated conditions
+    condition: Option<String>,
+    /// Only cities currently at least this warm (°C)
+    min_temp: Option<i32>,
+    /// Only cities currently at most this warm (°C)
+    max_temp: Option<i32>,
+    /// Same values as `/stats`; cities are sorted by name otherwise
+    #[serde(default)]
+    sort: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    total_cities: usize,
+    /// `None` (like the hottest and coldest city) when there are no cities,
+    /// e.g. for an API key whose city set is empty
+    average_temp: Option<f64>,
+    hottest_city: Option<Cow<'static, str>>,
+    coldest_city: Option<Cow<'static, str>>,
+    /// Relative humidity in percent, with one decimal
+    average_humidity: Option<f64>,
+    most_humid_city: Option<Cow<'static, str>>,
+    driest_city: Option<Cow<'static, str>>,
+    /// km/h, with one decimal
+    average_wind_speed: Option<f64>,
+    windiest_city: Option<Cow<'static, str>>,
+    calmest_city: Option<Cow<'static, str>>,
+    cities: Vec<WeatherData>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+    code: u16,
+}
+
+/// An error reply: `status` with `{"error": message, "code": status}`
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message,
+            code: status.as_u16(),
+        }),
+    )
+}
+
+/// Seconds clients are asked to wait (via `Retry-After`) when requests are shed
+const OVERLOAD_RETRY_AFTER_SECS: u64 = 1;
+
+/// Largest `radius_km` accepted by `/weather/nearby` (half the Earth's circumference)
+const MAX_NEARBY_RADIUS_KM: f64 = 20_038.0;
+/// Largest `limit` accepted by `/weather/nearby`
+const MAX_NEARBY_LIMIT: usize = 100;
+
+/// Run the server as configured on the command line and in the environment
+///
+/// This is the `server` binary: checks, background jobs, then the listener
+/// (or the Lambda runtime). Hosts that only want the routes use [`router`].
+pub async fn run() {
+    let config = config::ServerConfig::load();
+    let limits = serve::ConnectionLimits::from_config(&config);
+    let startup = match preflight::check(
+        &config,
+        SocketAddr::from(([0, 0, 0, 0], 3000)),
+        limits.backlog,
+    ) {
+        Ok(startup) => startup,
+        Err(report) => {
+            eprint!("{}", report);
+            #[cfg(feature = "lambda")]
+            if let Ok(api) = std::env::var(lambda::RUNTIME_API) {
+                if let Err(e) = lambda::report_init_error(&api, &report.to_string()).await {
+                    eprintln!(
+                        "❌ Cannot report the init error to the Lambda runtime API: {}",
+                        e
+                    );
+                }
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!("🦀 Rust Weather API Server v0.3.0 - MCP Edition");
+    println!("================================================");
+    println!();
+    println!("🌤️  Starting server on http://localhost:3000");
+    println!("🤖 Claude Code Integration: ENABLED");
+    println!();
+
+    if config.simulate {
+        println!("🎲 Simulation mode: ENABLED (seasonal, diurnal and random-walk variation)");
+        println!("   Seed: {}", config.seed);
+        println!();
+    }
+
+    if config.chaos {
+        println!(
+            "🐒 Chaos mode: ENABLED ({}% errors, {}% malformed bodies, latency up to {:?}) - do not use in production",
+            config.chaos_error_rate,
+            config.chaos_malformed_rate,
+            config.chaos_latency.unwrap_or_default()
+        );
+        println!();
+    }
+
+    if config.request_log {
+        println!(
+            "📝 Request log: ENABLED (last {} requests, up to {:?})",
+            config.request_log_max_entries, config.request_log_retention
+        );
+        println!();
+    }
+
+    for warning in &startup.warnings {
+        println!("⚠️  {}", warning);
+    }
+    if !startup.warnings.is_empty() {
+        println!();
+    }
+
+    if let Some(path) = &config.cities_file {
+        println!(
+            "🗺️  Loaded {} cities from {}",
+            startup.cities.len(),
+            path.display()
+        );
+        println!();
+    }
+    let cities = Arc::new(cities::CityStore::new(startup.cities));
+
+    if let (Some(keys), Some(path)) = (&startup.api_keys, &config.api_keys_file) {
+        println!(
+            "🔑 Loaded {} API keys from {} (X-API-Key required)",
+            keys.len(),
+            path.display()
+        );
+        let database = cities.current();
+        for (name, tenant) in keys.tenants() {
+            println!(
+                "🏢 {} has its own city set ({} cities)",
+                name,
+                tenant.store(&database).current().len()
+            );
+        }
+        println!();
+    }
+    let api_keys = startup.api_keys;
+
+    let mut state = AppState::new(config, cities, api_keys);
+    if let (Some(rules), Some(path)) = (startup.condition_rules, &state.config.condition_rules) {
+        println!(
+            "🌦️  Loaded {} condition rules from {}",
+            rules.len(),
+            path.display()
+        );
+        println!();
+        state.condition_rules = Arc::new(rules);
+    }
+    #[cfg(feature = "geoip")]
+    let state = AppState {
+        geoip: startup.geoip.map(|geoip| {
+            if let Some(path) = &state.config.geoip_db {
+                println!(
+                    "📡 GeoIP database loaded from {}: GET /weather/me enabled",
+                    path.display()
+                );
+                println!();
+            }
+            Arc::new(geoip)
+        }),
+        ..state
+    };
+    let config = state.config.clone();
+    let connections = state.connections.clone();
+
+    if config.data_file.is_some() {
+        let saved = startup.saved;
+        println!(
+            "💾 Restored {} favorite lists, {} digests and {} webhooks from {}",
+            saved.favorites.len(),
+            saved.digests.len(),
+            saved.webhooks.len(),
+            state.persistence.location()
+        );
+        println!();
+        store::rehydrate(&state, saved);
+    }
+
+    // Keep the watcher alive for the lifetime of the server
+    let _cities_watcher =
+        config.cities_file.clone().and_then(|path| {
+            match reload::watch_cities_file(
+                path.clone(),
+                state.cities.clone(),
+                state.events.clone(),
+            ) {
+                Ok(watcher) => {
+                    println!("👀 Watching {} for changes", path.display());
+                    println!();
+                    Some(watcher)
+                }
+                Err(e) => {
+                    println!(
+                        "⚠️  Cannot watch {}: {} (hot reload disabled)",
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            }
+        });
+
+    if config.warm_up {
+        println!("🔥 Warming up: GET /ready answers 503 until the caches are filled");
+        println!();
+        warmup::spawn(state.clone());
+    }
+
+    history::spawn_sampler(state.clone(), config.history_interval);
+    println!(
+        "📈 Recording history every {:?} ({} samples per city), anomaly threshold {}σ",
+        config.history_interval, config.history_len, config.anomaly_threshold
+    );
+    history::spawn_compactor(state.clone(), config.history_compaction_interval);
+    println!(
+        "🗜️  Compacting history every {:?}: raw for {:?}, hourly for {:?}, daily {}",
+        config.history_compaction_interval,
+        config.history_raw_retention,
+        config.history_hourly_retention,
+        config
+            .history_daily_retention
+            .map_or("forever".to_string(), |d| format!("for {:?}", d))
+    );
+    let rules: Vec<&str> = config.alert_rules.iter().map(|r| r.spec.as_str()).collect();
+    println!("🚨 Alert rules: {}", rules.join(", "));
+    println!();
+
+    app::spawn_jobs(&state);
+    let (app, manifest) = app::router(state);
+
+    println!("📡 Endpoints (GET /.well-known/api-manifest):");
+    for endpoint in &manifest.endpoints {
+        let auth = match endpoint.auth {
+            manifest::Auth::None => "",
+            manifest::Auth::ApiKey => " [API key]",
+            manifest::Auth::AdminToken => " [admin]",
+        };
+        println!(
+            "   {:<6} {} - {}{}",
+            endpoint.method, endpoint.path, endpoint.summary, auth
+        );
+    }
+    println!();
+
+    #[cfg(feature = "lambda")]
+    if let Ok(api) = std::env::var(lambda::RUNTIME_API) {
+        println!("☁️  Serving AWS Lambda invocations from {}", api);
+        if let Err(e) = lambda::run(app).await {
+            eprintln!("❌ Lambda runtime API failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Run the server
+    let listener = startup.listener;
+
+    println!(
+        "🔌 Up to {} connections, keep-alive {:?}, backlog {}",
+        limits.max_connections, limits.keep_alive_timeout, limits.backlog
+    );
+    println!("✅ Server running! Press Ctrl+C to stop\n");
+
+    serve::serve(listener, app, limits, connections).await;
+}
+
+/// Health check endpoint
+async fn health_check(
+    State(state): State<AppState>,
+    Extension(manifest): Extension<Arc<Manifest>>,
+) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "ok",
+        "service": "Rust Weather API",
+        "version": "0.3.0",
+        "mcp_enabled": true,
+        "cities": {
+            "count": state.cities.current().len(),
+            "source": state
+                .config
+                .cities_file
+                .as_ref()
+                .map_or("builtin".to_string(), |path| path.display().to_string()),
+            "last_reload": state.cities.last_reload().map(|at| at.to_rfc3339())
+        },
+        "temperature_rounding": state.config.rounding(),
+        "endpoints": manifest.labels()
+    }))
+}
+
+/// Connection metrics in the Prometheus text format
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let connections = &state.connections;
+    let history = state.history.size();
+    let events = state.events.stats();
+    let body = format!(
+        "# HELP weather_connections_active Client connections currently open.\n\
+         # TYPE weather_connections_active gauge\n\
+         weather_connections_active {}\n\
+         # HELP weather_connections_max Configured limit on open client connections.\n\
+         # TYPE weather_connections_max gauge\n\
+         weather_connections_max {}\n\
+         # HELP weather_connections_total Client connections accepted since startup.\n\
+         # TYPE weather_connections_total counter\n\
+         weather_connections_total {}\n\
+         # HELP weather_history_points Points kept in the history, by tier.\n\
+         # TYPE weather_history_points gauge\n\
+         weather_history_points{{tier=\"raw\"}} {}\n\
+         weather_history_points{{tier=\"hourly\"}} {}\n\
+         weather_history_points{{tier=\"daily\"}} {}\n\
+         # HELP weather_history_bytes Approximate memory used by the history points.\n\
+         # TYPE weather_history_bytes gauge\n\
+         weather_history_bytes {}\n\
+         # HELP weather_history_compactions_total History compaction runs since startup.\n\
+         # TYPE weather_history_compactions_total counter\n\
+         weather_history_compactions_total {}\n\
+         # HELP weather_event_subscribers Clients connected to the update event stream.\n\
+         # TYPE weather_event_subscribers gauge\n\
+         weather_event_subscribers {}\n\
+         # HELP weather_events_dropped_total Update events dropped for slow subscribers.\n\
+         # TYPE weather_events_dropped_total counter\n\
+         weather_events_dropped_total {}\n\
+         # HELP weather_event_subscribers_disconnected_total Subscribers disconnected for being too slow.\n\
+         # TYPE weather_event_subscribers_disconnected_total counter\n\
+         weather_event_subscribers_disconnected_total {}\n",
+        connections.active(),
+        connections.max(),
+        connections.total(),
+        history.raw,
+        history.hourly,
+        history.daily,
+        history.bytes(),
+        state.history.compactions(),
+        events.active(),
+        events.dropped(),
+        events.disconnected()
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Fallback for unknown routes: JSON error listing the available endpoints
+async fn not_found(
+    Extension(manifest): Extension<Arc<Manifest>>,
+    method: Method,
+    uri: Uri,
+) -> impl IntoResponse {
+    println!("❓ No route for {} {}", method, uri.path());
+
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "error": format!("No route for {} {}", method, uri.path()),
+            "code": 404,
+            "available_endpoints": manifest.labels()
+        })),
+    )
+}
+
+/// Response for requests shed by the concurrency limit
+async fn overloaded(err: BoxError) -> Response {
+    if !err.is::<tower::load_shed::error::Overloaded>() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Unhandled internal error: {}", err),
+                code: 500,
+            }),
+        )
+            .into_response();
+    }
+
+    println!("🚦 Shedding request: concurrency limit reached");
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, OVERLOAD_RETRY_AFTER_SECS.to_string())],
+        Json(ErrorResponse {
+            error: "Server is overloaded, please retry later".to_string(),
+            code: 503,
+        }),
+    )
+        .into_response()
+}
+
+/// Fallback for known routes called with the wrong method
+///
+/// axum adds the `Allow` header listing the supported methods.
+async fn method_not_allowed(
+    Extension(manifest): Extension<Arc<Manifest>>,
+    method: Method,
+    uri: Uri,
+) -> impl IntoResponse {
+    println!("🚫 Method {} not allowed on {}", method, uri.path());
+
+    let allowed = manifest.methods_for(uri.path());
+
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        Json(serde_json::json!({
+            "error": format!(
+                "Method {} not allowed on {}. Use: {}",
+                method,
+                uri.path(),
+                allowed.join(", ")
+            ),
+            "code": 405
+        })),
+    )
+}
+
+/// Get weather information for multiple cities
+async fn get_weather(
+    Scoped(state): Scoped,
+    context: RequestContext,
+    Query(query): Query<WeatherQuery>,
+    Json(payload): Json<WeatherRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    weather_for_cities(&state, &context, &query, payload.cities)
+}
+
+/// `GET /weather?cities=stockholm,paris`, for clients that cannot send a body
+async fn get_weather_by_query(
+    Scoped(state): Scoped,
+    context: RequestContext,
+    Query(query): Query<WeatherQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let Some(cities) = &query.cities else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Missing 'cities' query parameter, e.g. ?cities=stockholm,paris".to_string(),
+                code: 400,
+            }),
+        ));
+    };
+    let cities = cities
+        .split(',')
+        .map(str::trim)
+        .filter(|city| !city.is_empty())
+        .map(String::from)
+        .collect();
+    weather_for_cities(&state, &context, &query, cities)
+}
+
+/// Shared by the POST and GET forms of `/weather`
+///
+/// Unknown cities get default values, or a 404 in strict mode.
+fn weather_for_cities(
+    state: &AppState,
+    context: &RequestContext,
+    query: &WeatherQuery,
+    cities: Vec<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    println!(
+        "📥 Received weather request for {} cities{}",
+        cities.len(),
+        context.caller_suffix()
+    );
+    let started = Instant::now();
+    let mut trace = context.debug.then(trace::Trace::default);
+
+    let at = match &query.at {
+        Some(at) => Some(parse_at(state, at)?),
+        None => None,
+    };
+    let fields = FieldSelection::from_query(query.fields.as_deref(), fields::WEATHER_FIELDS)?;
+
+    // Validation: check if cities list is empty
+    if cities.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Cities list cannot be empty".to_string(),
+                code: 400,
+            }),
+        ));
+    }
+
+    // Validation: check if too many cities requested
+    if cities.len() > 20 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!(
+                    "Too many cities requested. Maximum is 20, you requested {}",
+                    cities.len()
+                ),
+                code: 400,
+            }),
+        ));
+    }
+
+    let mut response_data = HashMap::new();
+    let mut known: Vec<String> = Vec::new();
+    let database = state.cities.current();
+
+    if context.strict {
+        let unknown: Vec<&str> = cities
+            .iter()
+            .filter(|city| database.get(&city.to_lowercase()).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("Unknown cities: {}", unknown.join(", ")),
+                    code: 404,
+                }),
+            ));
+        }
+    }
+
+    for city in cities {
+        let lookup_started = Instant::now();
+        let weather_data = state.weather_or_default(&city, at.unwrap_or_else(Utc::now));
+        state.count_request(&city);
+
+        println!(
+            "  ✓ {} - {}°C, {}",
+            city, weather_data.temperature, weather_data.condition
+        );
+        let found = database.get(&city.to_lowercase()).is_some();
+        // `oslo` and `Oslo` are two entries in the response but one city
+        if found && !known.iter().any(|k| k.eq_ignore_ascii_case(&city)) {
+            known.push(city.clone());
+        }
+        if let Some(trace) = &mut trace {
+            trace.cities.push(trace::CityTrace {
+                city: city.clone(),
+                found,
+                source: weather_data.source,
+                elapsed_us: trace::micros_since(lookup_started),
+            });
+        }
+        response_data.insert(city.clone(), weather_data);
+    }
+
+    let summary = if query.summary && known.len() > 1 {
+        let readings: Vec<&WeatherData> =
+            known.iter().filter_map(|c| response_data.get(c)).collect();
+        WeatherSummary::of(&readings, state.config.rounding())
+    } else {
+        None
+    };
+
+    println!("📤 Sending response\n");
+
+    let response = WeatherResponse {
+        data: response_data,
+        at: at.map(|at| at.to_rfc3339()),
+        summary,
+        trace: trace.map(|trace| trace.finish(started)),
+    };
+    let Some(fields) = fields else {
+        return Ok(Json(response).into_response());
+    };
+
+    let mut response = serde_json::to_value(response).unwrap_or_default();
+    if let Some(data) = response["data"].as_object_mut() {
+        data.values_mut().for_each(|entry| fields.retain(entry));
+    }
+    Ok(Json(response).into_response())
+}
+
+/// Get weather for the cities closest to a point, nearest first
+async fn get_nearby(
+    Scoped(state): Scoped,
+    Query(query): Query<NearbyQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |message: String| error_response(StatusCode::BAD_REQUEST, message);
+
+    let (Some(lat), Some(lon)) = (query.lat, query.lon) else {
+        return Err(bad_request("Both 'lat' and 'lon' are required".to_string()));
+    };
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(bad_request(format!(
+            "Invalid point ({}, {}): lat must be within -90..90 and lon within -180..180",
+            lat, lon
+        )));
+    }
+    let radius_km = query.radius_km.unwrap_or(100.0);
+    if !(radius_km > 0.0 && radius_km <= MAX_NEARBY_RADIUS_KM) {
+        return Err(bad_request(format!(
+            "Invalid radius_km {}: expected more than 0 and at most {}",
+            radius_km, MAX_NEARBY_RADIUS_KM
+        )));
+    }
+    let limit = query.limit.unwrap_or(10);
+    if !(1..=MAX_NEARBY_LIMIT).contains(&limit) {
+        return Err(bad_request(format!(
+            "Invalid limit {}: expected 1 to {}",
+            limit, MAX_NEARBY_LIMIT
+        )));
+    }
+
+    println!(
+        "📍 Received nearby request for ({}, {}) within {} km",
+        lat, lon, radius_km
+    );
+
+    let database = state.cities.current();
+    let now = Utc::now();
+    let cities: Vec<_> = geo::cities_by_distance(&database, lat, lon)
+        .into_iter()
+        .take_while(|city| city.distance_km <= radius_km)
+        .take(limit)
+        .map(|city| {
+            serde_json::json!({
+                "city": city.key,
+                "latitude": city.latitude,
+                "longitude": city.longitude,
+                "distance_km": (city.distance_km * 10.0).round() / 10.0,
+                "weather": state.weather_or_default(city.key, now)
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "center": { "lat": lat, "lon": lon },
+        "radius_km": radius_km,
+        "count": cities.len(),
+        "cities": cities
+    })))
+}
+
+/// Parse the `at` query parameter used for time-travel queries
+///
+/// Only the simulation can be evaluated at arbitrary instants; the static
+/// data has no notion of time, so `at` is rejected rather than ignored.
+fn parse_at(
+    state: &AppState,
+    at: &str,
+) -> Result<DateTime<Utc>, (StatusCode, Json<ErrorResponse>)> {
+    if state.simulator.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "The 'at' parameter requires simulation mode (--simulate)".to_string(),
+                code: 400,
+            }),
+        ));
+    }
+
+    DateTime::parse_from_rfc3339(at)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Invalid 'at' timestamp '{}': {} (expected RFC 3339, e.g. 2025-12-24T18:00:00Z)",
+                        at, e
+                    ),
+                    code: 400,
+                }),
+            )
+        })
+}
+
+/// Get statistics about all weather data
+async fn get_stats(
+    Scoped(state): Scoped,
+    context: RequestContext,
+    Query(params): Query<StatsQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    println!("📊 Received stats request{}", context.caller_suffix());
+    let started = Instant::now();
+
+    let debug = context.debug;
+    let fields = FieldSelection::from_query(params.fields.as_deref(), fields::WEATHER_FIELDS)?;
+    let (stats, cache_use) = compute_stats_traced(&state, &params.sort);
+    if fields.is_none() && !debug {
+        return Ok(Json(&*stats).into_response());
+    }
+
+    let mut stats = serde_json::to_value(&*stats).unwrap_or_default();
+    if let (Some(fields), Some(cities)) = (fields, stats["cities"].as_array_mut()) {
+        cities.iter_mut().for_each(|entry| fields.retain(entry));
+    }
+    if debug {
+        let trace = trace::Trace {
+            stats_cache: Some(cache_use),
+            ..Default::default()
+        };
+        stats["trace"] = serde_json::to_value(trace.finish(started)).unwrap_or_default();
+    }
+    Ok(Json(stats).into_response())
+}
+
+/// Compute statistics over the whole database, with cities sorted by `sort`
+/// Shared by the JSON stats endpoint and the HTML dashboard
+///
+/// Static data only changes with the database, so its statistics are
+/// computed once per database and sort order; simulated readings are
+/// computed on every call.
+pub fn compute_stats(state: &AppState, sort: &str) -> Arc<StatsResponse> {
+    compute_stats_traced(state, sort).0
+}
+
+/// [`compute_stats`], also reporting what the cache did
+fn compute_stats_traced(state: &AppState, sort: &str) -> (Arc<StatsResponse>, trace::CacheUse) {
+    let database = state.cities.current();
+    let sort = sort_order(sort);
+    let computed = std::cell::Cell::new(false);
+    let compute = || {
+        computed.set(true);
+        stats_for(
+            state.all_weather_in(&database, Utc::now()),
+            sort,
+            state.config.rounding(),
+        )
+    };
+
+    if state.simulator.is_some() {
+        return (Arc::new(compute()), trace::CacheUse::Bypassed);
+    }
+    let stats = state.stats_cache.get_or_compute(&database, sort, compute);
+    let cache_use = if computed.get() {
+        trace::CacheUse::Miss
+    } else {
+        trace::CacheUse::Hit
+    };
+    (stats, cache_use)
+}
+
+/// Canonical name of a `sort` parameter; unknown values mean no sorting
+fn sort_order(sort: &str) -> &'static str {
+    match sort {
+        "temp" | "temperature" => "temperature",
+        "name" | "city" => "name",
+        "humidity" => "humidity",
+        "wind" => "wind",
+        _ => "none",
+    }
+}
+
+/// The reading a canonical sort order (see [`sort_order`]) compares, if any
+fn weather_sort_key(sort: &str) -> Option<fn(&WeatherData) -> i32> {
+    match sort {
+        "temperature" => Some(|w| w.temperature),
+        "humidity" => Some(|w| w.humidity),
+        "wind" => Some(|w| w.wind_speed),
+        _ => None,
+    }
+}
+
+/// Comma-separated condition codes or names from a query parameter
+fn parse_conditions(input: &str) -> Result<Vec<Condition>, (StatusCode, Json<ErrorResponse>)> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            Condition::from_name(name).ok_or_else(|| {
+                let codes: Vec<&str> = Condition::ALL.iter().map(|c| c.code()).collect();
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!(
+                            "Unknown condition '{}'. Valid conditions: {}",
+                            name,
+                            codes.join(", ")
+                        ),
+                        code: 400,
+                    }),
+                )
+            })
+        })
+        .collect()
+}
+
+fn stats_for(
+    mut cities_data: Vec<WeatherData>,
+    sort: &str,
+    rounding: TemperatureRounding,
+) -> StatsResponse {
+    // Sort based on query parameter
+    match sort {
+        "temperature" => cities_data.sort_by_key(|c| c.temperature),
+        "name" => cities_data.sort_by(|a, b| a.city.cmp(&b.city)),
+        "humidity" => cities_data.sort_by_key(|c| c.humidity),
+        "wind" => cities_data.sort_by_key(|c| c.wind_speed),
+        _ => {} // default: no sorting
+    }
+
+    let temperature = Spread::of(&cities_data, |c| c.temperature);
+    let humidity = Spread::of(&cities_data, |c| c.humidity);
+    let wind = Spread::of(&cities_data, |c| c.wind_speed);
+    let one_decimal = TemperatureRounding::default();
+
+    StatsResponse {
+        total_cities: cities_data.len(),
+        average_temp: temperature.as_ref().map(|s| rounding.round(s.average)),
+        hottest_city: temperature.as_ref().map(|s| s.highest.clone()),
+        coldest_city: temperature.map(|s| s.lowest),
+        average_humidity: humidity.as_ref().map(|s| one_decimal.round(s.average)),
+        most_humid_city: humidity.as_ref().map(|s| s.highest.clone()),
+        driest_city: humidity.map(|s| s.lowest),
+        average_wind_speed: wind.as_ref().map(|s| one_decimal.round(s.average)),
+        windiest_city: wind.as_ref().map(|s| s.highest.clone()),
+        calmest_city: wind.map(|s| s.lowest),
+        cities: cities_data,
+    }
+}
+
+/// Average of one reading across cities, and the cities with its highest
+/// and lowest value
+struct Spread {
+    average: f64,
+    highest: Cow<'static, str>,
+    lowest: Cow<'static, str>,
+}
+
+impl Spread {
+    fn of(cities: &[WeatherData], value: fn(&WeatherData) -> i32) -> Option<Self> {
+        let total: i32 = cities.iter().map(value).sum();
+        let highest = cities.iter().max_by_key(|c| value(c))?;
+        let lowest = cities.iter().min_by_key(|c| value(c))?;
+        Some(Spread {
+            average: total as f64 / cities.len() as f64,
+            highest: highest.city.clone(),
+            lowest: lowest.city.clone(),
+        })
+    }
+}
+
+/// Get list of all available cities
+///
+/// With `?fields=` or `?weather=true` (alias `details`), each city is an
+/// object (`city` is the lowercase name used by the other endpoints);
+/// `?weather=true` fills in the current weather. `?bbox=` keeps only cities
+/// with coordinates inside it, and `condition`, `min_temp` and `max_temp`
+/// filter by the current weather.
+async fn get_cities(
+    Scoped(state): Scoped,
+    Query(query): Query<CitiesQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    println!("🌍 Received cities list request");
+
+    let fields = FieldSelection::from_query(query.fields.as_deref(), fields::CITY_FIELDS)?;
+    let bbox = match &query.bbox {
+        Some(bbox) => Some(bbox.parse::<geo::BoundingBox>().map_err(|error| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse { error, code: 400 }),
+            )
+        })?),
+        None => None,
+    };
+    let conditions = match &query.condition {
+        Some(conditions) => Some(parse_conditions(conditions)?),
+        None => None,
+    };
+    let sort = sort_order(&query.sort);
+    let needs_weather = query.weather
+        || conditions.is_some()
+        || query.min_temp.is_some()
+        || query.max_temp.is_some()
+        || !matches!(sort, "name" | "none");
+    let database = state.cities.current();
+    let now = Utc::now();
+
+    let mut cities: Vec<_> = database
+        .iter()
+        .filter(|(_, record)| match (bbox, record.coordinates()) {
+            (None, _) => true,
+            (Some(bbox), Some((latitude, longitude))) => bbox.contains(latitude, longitude),
+            (Some(_), None) => false,
+        })
+        .map(|(key, record)| {
+            (
+                key,
+                record,
+                needs_weather.then(|| state.weather_or_default(key, now)),
+            )
+        })
+        .filter(|(_, _, weather)| {
+            let Some(weather) = weather else {
+                return true;
+            };
+            conditions
+                .as_ref()
+                .is_none_or(|c| c.contains(&weather.condition))
+                && query.min_temp.is_none_or(|min| weather.temperature >= min)
+                && query.max_temp.is_none_or(|max| weather.temperature <= max)
+        })
+        .collect();
+    cities.sort_by(|a, b| a.0.cmp(b.0));
+    if let Some(key) = weather_sort_key(sort) {
+        // Stable, so ties stay in name order
+        cities.sort_by_key(|(_, _, weather)| weather.as_ref().map(key));
+    }
+
+    if fields.is_none() && !query.weather {
+        let cities: Vec<&str> = cities.into_iter().map(|(key, _, _)| key).collect();
+
+        return Ok(Json(serde_json::json!({
+            "count": cities.len(),
+            "cities": cities
+        })));
+    }
+
+    let cities: Vec<_> = cities
+        .into_iter()
+        .map(|(key, record, weather)| {
+            let mut entry = serde_json::to_value(record).unwrap_or_default();
+            entry["city"] = key.into();
+            if let (true, Some(weather)) = (query.weather, weather) {
+                entry["temperature"] = weather.temperature.into();
+                entry["condition"] = weather.condition.code().into();
+                entry["icon"] = serde_json::to_value(weather.icon).unwrap_or_default();
+                match weather.description {
+                    Some(description) => entry["description"] = description.into(),
+                    None => {
+                        if let Some(entry) = entry.as_object_mut() {
+                            entry.remove("description");
+                        }
+                    }
+                }
+                entry["humidity"] = weather.humidity.into();
+                entry["wind_speed"] = weather.wind_speed.into();
+            }
+            if let Some(fields) = &fields {
+                fields.retain(&mut entry);
+            }
+            entry
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "count": cities.len(),
+        "cities": cities
+    })))
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weather_summary() {
+        let reading = |city: &str, temperature: i32, condition: Condition| WeatherData {
+            temperature,
+            condition,
+            ..WeatherData::unknown(city)
+        };
+        let oslo = reading("Oslo", 4, Condition::Rain);
+        let rome = reading("Rome", 21, Condition::Clear);
+        let bergen = reading("Bergen", 6, Condition::Rain);
+
+        let rounding = TemperatureRounding::default();
+        let summary = WeatherSummary::of(&[&oslo, &rome, &bergen], rounding).unwrap();
+        assert_eq!(summary.cities, 3);
+        assert_eq!(summary.average_temp, 10.3);
+        assert_eq!(
+            (summary.warmest_city.as_str(), summary.coldest_city.as_str()),
+            ("Rome", "Oslo")
+        );
+        assert_eq!(summary.common_condition, Condition::Rain);
+
+        let pair = WeatherSummary::of(&[&oslo, &rome], rounding).unwrap();
+        assert_eq!(pair.common_condition, Condition::Clear);
+        assert_eq!(pair.average_temp, 12.5);
+        let bankers = TemperatureRounding {
+            decimals: 0,
+            mode: rounding::RoundingMode::HalfEven,
+        };
+        assert_eq!(
+            WeatherSummary::of(&[&oslo, &rome], bankers)
+                .unwrap()
+                .average_temp,
+            12.0
+        );
+        assert!(WeatherSummary::of(&[], rounding).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_summary_counts_each_city_once() {
+        use cities::{CityDatabase, CityStore};
+        use clap::Parser;
+
+        let state = AppState::new(
+            config::ServerConfig::parse_from(["server"]),
+            Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let context = RequestContext {
+            locale: locale::Locale::default(),
+            strict: false,
+            debug: false,
+            caller: None,
+        };
+        let summary_of = |cities: &[&str]| {
+            let query = WeatherQuery {
+                cities: None,
+                at: None,
+                fields: None,
+                summary: true,
+            };
+            let cities = cities.iter().map(|city| city.to_string()).collect();
+            weather_for_cities(&state, &context, &query, cities).unwrap()
+        };
+        let summary = |response: Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["summary"].clone()
+        };
+
+        assert_eq!(
+            summary(summary_of(&["oslo", "Oslo"])).await,
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            summary(summary_of(&["oslo", "OSLO", "rome"])).await["cities"],
+            2
+        );
+    }
+
+    #[test]
+    fn test_weather_database_has_cities() {
+        let db = cities::CityDatabase::builtin();
+        assert!(!db.is_empty(), "Weather database should not be empty");
+        assert!(
+            db.get("stockholm").is_some(),
+            "Database should contain Stockholm"
+        );
+        assert!(db.get("gaza").is_some(), "Database should contain Gaza");
+        assert!(db.get("paris").is_some(), "Database should contain Paris");
+    }
+
+    #[test]
+    fn test_weather_database_format() {
+        let db = cities::CityDatabase::builtin();
+
+        // Check Stockholm has correct format
+        if let Some(city) = db.get("stockholm") {
+            assert!(
+                city.temperature > -50 && city.temperature < 60,
+                "Temperature should be in reasonable range"
+            );
+            assert_ne!(
+                city.condition,
+                Condition::Unknown,
+                "Condition should be classified"
+            );
+            assert!(
+                city.humidity >= 0 && city.humidity <= 100,
+                "Humidity should be 0-100%"
+            );
+            assert!(city.wind_speed >= 0, "Wind speed should be positive");
+        }
+    }
+
+    #[test]
+    fn test_all_cities_have_valid_data() {
+        let db = cities::CityDatabase::builtin();
+
+        for name in db.names() {
+            let city = db.get(name).unwrap();
+            assert!(!name.is_empty(), "City name should not be empty");
+            assert!(
+                city.temperature > -60 && city.temperature < 60,
+                "Temperature for {} should be reasonable",
+                name
+            );
+            assert_ne!(
+                city.condition,
+                Condition::Unknown,
+                "Condition for {} should be classified",
+                name
+            );
+            assert!(
+                city.humidity >= 0 && city.humidity <= 100,
+                "Humidity for {} should be 0-100%",
+                name
+            );
+            assert!(
+                city.wind_speed >= 0 && city.wind_speed < 100,
+                "Wind speed for {} should be reasonable",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_weather_database_count() {
+        let db = cities::CityDatabase::builtin();
+        assert!(db.len() >= 40, "Database should have at least 40 cities");
+    }
+
+    #[test]
+    fn test_stats_for_no_cities() {
+        let stats = stats_for(Vec::new(), "temperature", TemperatureRounding::default());
+        assert_eq!(stats.total_cities, 0);
+        assert_eq!(stats.average_temp, None);
+        assert_eq!((stats.hottest_city, stats.coldest_city), (None, None));
+        assert_eq!((stats.average_humidity, stats.windiest_city), (None, None));
+    }
+
+    #[test]
+    fn test_stats_for_humidity_and_wind() {
+        let reading = |city: &str, humidity, wind_speed| WeatherData {
+            humidity,
+            wind_speed,
+            ..WeatherData::unknown(city)
+        };
+        let cities = vec![
+            reading("Oslo", 85, 23),
+            reading("Riyadh", 15, 12),
+            reading("Rome", 60, 4),
+        ];
+        let stats = stats_for(cities, "", TemperatureRounding::default());
+        assert_eq!(stats.average_humidity, Some(53.3));
+        assert_eq!(stats.most_humid_city.as_deref(), Some("Oslo"));
+        assert_eq!(stats.driest_city.as_deref(), Some("Riyadh"));
+        assert_eq!(stats.average_wind_speed, Some(13.0));
+        assert_eq!(stats.windiest_city.as_deref(), Some("Oslo"));
+        assert_eq!(stats.calmest_city.as_deref(), Some("Rome"));
+    }
+
+    #[test]
+    fn test_parse_conditions() {
+        assert_eq!(
+            parse_conditions("rain, Snow,").unwrap(),
+            vec![Condition::Rain, Condition::Snow]
+        );
+        assert_eq!(
+            parse_conditions("partly cloudy").unwrap(),
+            vec![Condition::PartlyCloudy]
+        );
+        assert_eq!(
+            parse_conditions("drizzle").unwrap_err().0,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
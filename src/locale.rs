@@ -0,0 +1,314 @@
+use axum::{
+    extract::{Query, Request},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::conditions::Condition;
+use crate::error_response;
+
+/// Regions that use Fahrenheit and miles
+const IMPERIAL_REGIONS: &[&str] = &["us", "lr", "mm"];
+
+/// Languages rendered outputs can be localized to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "sv")]
+    Swedish,
+    #[serde(rename = "de")]
+    German,
+}
+
+impl Language {
+    const SUPPORTED: &'static str = "en, sv, de";
+
+    fn from_subtag(subtag: &str) -> Option<Self> {
+        match subtag {
+            "en" => Some(Language::English),
+            "sv" => Some(Language::Swedish),
+            "de" => Some(Language::German),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Swedish => "sv",
+            Language::German => "de",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    Metric,
+    Imperial,
+}
+
+/// Language, units and date style for rendered outputs (dashboard, digests)
+///
+/// JSON responses are not affected: they always carry °C and km/h.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Locale {
+    pub language: Language,
+    pub units: Units,
+    /// US-style dates, month before day
+    #[serde(skip)]
+    pub month_first: bool,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            language: Language::English,
+            units: Units::Metric,
+            month_first: false,
+        }
+    }
+}
+
+impl Locale {
+    /// Locale for a language tag such as `sv` or `en-US`, if the language is supported
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_lowercase();
+        let mut subtags = tag.split(['-', '_']);
+        let language = Language::from_subtag(subtags.next()?)?;
+        let region = subtags.find(|subtag| subtag.len() == 2);
+        let us = region == Some("us");
+        Some(Locale {
+            language,
+            units: if region.is_some_and(|r| IMPERIAL_REGIONS.contains(&r)) {
+                Units::Imperial
+            } else {
+                Units::Metric
+            },
+            month_first: us,
+        })
+    }
+
+    /// The preferred supported locale of an `Accept-Language` header, or English
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut ranges: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so equally preferred languages keep the client's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges
+            .iter()
+            .find_map(|(tag, _)| Locale::from_tag(tag))
+            .unwrap_or_default()
+    }
+
+    pub fn temperature(&self, celsius: i32) -> String {
+        match self.units {
+            Units::Metric => format!("{}°C", celsius),
+            Units::Imperial => format!("{:.0}°F", celsius as f64 * 9.0 / 5.0 + 32.0),
+        }
+    }
+
+    /// Average temperature, with `decimals` decimals (`--temperature-precision`)
+    pub fn average_temperature(&self, celsius: f64, decimals: u8) -> String {
+        let decimals = decimals as usize;
+        match self.units {
+            Units::Metric => format!("{:.*}°C", decimals, celsius),
+            Units::Imperial => format!("{:.*}°F", decimals, celsius * 9.0 / 5.0 + 32.0),
+        }
+    }
+
+    pub fn wind(&self, kmh: i32) -> String {
+        match self.units {
+            Units::Metric => format!("{} km/h", kmh),
+            Units::Imperial => format!("{:.0} mph", kmh as f64 * 0.621_371),
+        }
+    }
+
+    /// How a condition is shown: the city data's free-text description in
+    /// English, otherwise the condition's name in the locale's language
+    pub fn condition<'a>(&self, condition: Condition, description: Option<&'a str>) -> &'a str {
+        let names = CONDITION_NAMES
+            .iter()
+            .find(|(c, _, _)| *c == condition)
+            .map_or(("", ""), |(_, sv, de)| (*sv, *de));
+        match self.language {
+            Language::English => description.unwrap_or(condition.label()),
+            Language::Swedish => names.0,
+            Language::German => names.1,
+        }
+    }
+
+    /// Long date, e.g. `Friday 16 October 2026` or `fredag 16 oktober 2026`
+    pub fn date(&self, date: NaiveDate) -> String {
+        let weekday = WEEKDAYS[date.weekday().num_days_from_monday() as usize];
+        let month = MONTHS[date.month0() as usize];
+        let (day, year) = (date.day(), date.year());
+        match self.language {
+            Language::English if self.month_first => {
+                format!("{}, {} {}, {}", weekday.0, month.0, day, year)
+            }
+            Language::English => format!("{} {} {} {}", weekday.0, day, month.0, year),
+            Language::Swedish => format!("{} {} {} {}", weekday.1, day, month.1, year),
+            Language::German => format!("{}, {}. {} {}", weekday.2, day, month.2, year),
+        }
+    }
+}
+
+/// Weekday names in English, Swedish and German, Monday first
+const WEEKDAYS: [(&str, &str, &str); 7] = [
+    ("Monday", "måndag", "Montag"),
+    ("Tuesday", "tisdag", "Dienstag"),
+    ("Wednesday", "onsdag", "Mittwoch"),
+    ("Thursday", "torsdag", "Donnerstag"),
+    ("Friday", "fredag", "Freitag"),
+    ("Saturday", "lördag", "Samstag"),
+    ("Sunday", "söndag", "Sonntag"),
+];
+
+const MONTHS: [(&str, &str, &str); 12] = [
+    ("January", "januari", "Januar"),
+    ("February", "februari", "Februar"),
+    ("March", "mars", "März"),
+    ("April", "april", "April"),
+    ("May", "maj", "Mai"),
+    ("June", "juni", "Juni"),
+    ("July", "juli", "Juli"),
+    ("August", "augusti", "August"),
+    ("September", "september", "September"),
+    ("October", "oktober", "Oktober"),
+    ("November", "november", "November"),
+    ("December", "december", "Dezember"),
+];
+
+/// Condition names in Swedish and German (English uses [`Condition::label`])
+const CONDITION_NAMES: [(Condition, &str, &str); 14] = [
+    (Condition::Clear, "Klart", "Klar"),
+    (Condition::PartlyCloudy, "Halvklart", "Teilweise bewölkt"),
+    (Condition::Cloudy, "Molnigt", "Bewölkt"),
+    (Condition::Overcast, "Mulet", "Bedeckt"),
+    (Condition::Rain, "Regn", "Regen"),
+    (Condition::Snow, "Snö", "Schnee"),
+    (Condition::Fog, "Dimma", "Nebel"),
+    (Condition::Haze, "Dis", "Dunst"),
+    (Condition::Wind, "Blåsigt", "Windig"),
+    (Condition::Hot, "Varmt", "Heiß"),
+    (Condition::Humid, "Fuktigt", "Schwül"),
+    (Condition::Cold, "Kallt", "Kalt"),
+    (Condition::Variable, "Växlande", "Wechselhaft"),
+    (Condition::Unknown, "Okänt", "Unbekannt"),
+];
+
+/// Per-request overrides of the negotiated locale
+#[derive(Debug, Deserialize)]
+pub struct LocaleQuery {
+    /// Language tag, e.g. `sv` or `en-US`
+    lang: Option<String>,
+    /// `metric` or `imperial`
+    units: Option<String>,
+}
+
+/// Middleware choosing the request's [`Locale`] from `Accept-Language`
+///
+/// `?lang=` and `?units=` take precedence over the header. Handlers read the
+/// result from the request extensions.
+pub async fn negotiate(
+    Query(query): Query<LocaleQuery>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let header = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok());
+
+    let mut locale = match (&query.lang, header) {
+        (Some(lang), _) => match Locale::from_tag(lang) {
+            Some(locale) => locale,
+            None => {
+                let message = format!(
+                    "Unsupported language '{}' (supported: {})",
+                    lang,
+                    Language::SUPPORTED
+                );
+                return error_response(StatusCode::BAD_REQUEST, message).into_response();
+            }
+        },
+        (None, Some(header)) => Locale::from_accept_language(header),
+        (None, None) => Locale::default(),
+    };
+    match query.units.as_deref() {
+        None => {}
+        Some("metric") => locale.units = Units::Metric,
+        Some("imperial") => locale.units = Units::Imperial,
+        Some(units) => {
+            let message = format!("Invalid units '{}' (expected metric or imperial)", units);
+            return error_response(StatusCode::BAD_REQUEST, message).into_response();
+        }
+    }
+
+    request.extensions_mut().insert(locale);
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-language"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language_negotiation() {
+        let us = Locale::from_accept_language("en-US,en;q=0.9");
+        assert_eq!(us.language, Language::English);
+        assert_eq!(us.units, Units::Imperial);
+
+        let swedish = Locale::from_accept_language("fr-FR, sv;q=0.8, en;q=0.5");
+        assert_eq!(swedish.language, Language::Swedish);
+        assert_eq!(swedish.units, Units::Metric);
+
+        assert_eq!(
+            Locale::from_accept_language("en;q=0.1, de-AT"),
+            Locale::from_tag("de").unwrap()
+        );
+        assert_eq!(Locale::from_accept_language("*"), Locale::default());
+        assert!(Locale::from_tag("xx").is_none());
+    }
+
+    #[test]
+    fn test_localized_formatting() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let us = Locale::from_tag("en-US").unwrap();
+        assert_eq!(us.date(date), "Friday, October 16, 2026");
+        assert_eq!(us.temperature(20), "68°F");
+        assert_eq!(us.wind(23), "14 mph");
+
+        let gb = Locale::from_tag("en-GB").unwrap();
+        assert_eq!(gb.date(date), "Friday 16 October 2026");
+        assert_eq!(gb.temperature(20), "20°C");
+
+        let sv = Locale::from_tag("sv-SE").unwrap();
+        assert_eq!(sv.date(date), "fredag 16 oktober 2026");
+        assert_eq!(sv.condition(Condition::PartlyCloudy, None), "Halvklart");
+        assert_eq!(sv.condition(Condition::Hot, Some("Very Hot")), "Varmt");
+        assert_eq!(gb.condition(Condition::Hot, Some("Very Hot")), "Very Hot");
+        assert_eq!(gb.condition(Condition::Rain, None), "Rain");
+
+        let de = Locale::from_tag("de").unwrap();
+        assert_eq!(de.date(date), "Freitag, 16. Oktober 2026");
+    }
+}
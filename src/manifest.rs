@@ -0,0 +1,340 @@
+use axum::{
+    extract::Request,
+    handler::Handler,
+    response::IntoResponse,
+    routing::{self, MethodRouter},
+    Extension, Json, Router,
+};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tower::Service;
+
+use crate::state::AppState;
+
+/// What a caller needs to use an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Auth {
+    None,
+    /// `X-API-Key`, when `--api-keys-file` is set
+    ApiKey,
+    /// `Authorization: Bearer <admin token>`
+    AdminToken,
+}
+
+/// One method on one path, as served by the router
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Endpoint {
+    pub method: &'static str,
+    /// With `{name}` placeholders, e.g. `/history/{city}`
+    pub path: String,
+    pub auth: Auth,
+    pub summary: &'static str,
+}
+
+impl Endpoint {
+    /// `GET /history/{city}`
+    pub fn label(&self) -> String {
+        format!("{} {}", self.method, self.path)
+    }
+
+    /// Whether a request path is served by this endpoint's path
+    fn matches(&self, path: &str) -> bool {
+        let mut template = self.path.split('/');
+        let mut segments = path.split('/');
+        loop {
+            match (template.next(), segments.next()) {
+                (None, None) => return true,
+                (Some(t), Some(_)) if t.starts_with('{') => continue,
+                (Some(t), Some(s)) if t == s => continue,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Every endpoint the router serves with this configuration
+///
+/// Built together with the router by [`Routes`], so it cannot list routes
+/// that do not exist or miss ones that do. Served at
+/// `GET /.well-known/api-manifest`, and behind the health check, the 404 and
+/// 405 responses, `GET /openapi.json` and the startup banner.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub service: &'static str,
+    pub version: &'static str,
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl Manifest {
+    /// `METHOD /path` for every endpoint
+    pub fn labels(&self) -> Vec<String> {
+        self.endpoints.iter().map(Endpoint::label).collect()
+    }
+
+    /// Methods served on a request path
+    pub fn methods_for(&self, path: &str) -> Vec<&'static str> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.matches(path))
+            .map(|endpoint| endpoint.method)
+            .collect()
+    }
+
+    /// Minimal OpenAPI 3.1 description: paths, methods, summaries and auth
+    pub fn openapi(&self) -> serde_json::Value {
+        let mut paths = serde_json::Map::new();
+        for endpoint in &self.endpoints {
+            let parameters: Vec<serde_json::Value> = endpoint
+                .path
+                .split('/')
+                .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+                .map(|name| {
+                    serde_json::json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": {"type": "string"}
+                    })
+                })
+                .collect();
+            let security = match endpoint.auth {
+                Auth::None => serde_json::json!([]),
+                Auth::ApiKey => serde_json::json!([{"apiKey": []}]),
+                Auth::AdminToken => serde_json::json!([{"adminToken": []}]),
+            };
+            let operation = serde_json::json!({
+                "summary": endpoint.summary,
+                "parameters": parameters,
+                "security": security
+            });
+            let path = paths
+                .entry(endpoint.path.clone())
+                .or_insert_with(|| serde_json::json!({}));
+            path[endpoint.method.to_lowercase()] = operation;
+        }
+
+        serde_json::json!({
+            "openapi": "3.1.0",
+            "info": {"title": self.service, "version": self.version},
+            "paths": paths,
+            "components": {
+                "securitySchemes": {
+                    "apiKey": {"type": "apiKey", "in": "header", "name": "X-API-Key"},
+                    "adminToken": {"type": "http", "scheme": "bearer"}
+                }
+            }
+        })
+    }
+}
+
+/// A handler for one method, with the summary listed in the manifest
+pub struct Route {
+    method: &'static str,
+    summary: &'static str,
+    handler: MethodRouter<AppState>,
+}
+
+pub fn get<H, T>(handler: H, summary: &'static str) -> Route
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    Route {
+        method: "GET",
+        summary,
+        handler: routing::get(handler),
+    }
+}
+
+pub fn post<H, T>(handler: H, summary: &'static str) -> Route
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    Route {
+        method: "POST",
+        summary,
+        handler: routing::post(handler),
+    }
+}
+
+pub fn put<H, T>(handler: H, summary: &'static str) -> Route
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    Route {
+        method: "PUT",
+        summary,
+        handler: routing::put(handler),
+    }
+}
+
+pub fn delete<H, T>(handler: H, summary: &'static str) -> Route
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    Route {
+        method: "DELETE",
+        summary,
+        handler: routing::delete(handler),
+    }
+}
+
+/// A `Router` that lists every route it is given in a [`Manifest`]
+pub struct Routes {
+    router: Router<AppState>,
+    endpoints: Vec<Endpoint>,
+    auth: Auth,
+}
+
+impl Routes {
+    /// Routes that need `auth`, unless added with [`Routes::open_route`]
+    pub fn new(auth: Auth) -> Self {
+        Routes {
+            router: Router::new(),
+            endpoints: Vec::new(),
+            auth,
+        }
+    }
+
+    pub fn route(self, path: &str, routes: impl IntoIterator<Item = Route>) -> Self {
+        let auth = self.auth;
+        self.route_with(path, auth, routes)
+    }
+
+    /// A route anyone may call, whatever the group needs
+    pub fn open_route(self, path: &str, routes: impl IntoIterator<Item = Route>) -> Self {
+        self.route_with(path, Auth::None, routes)
+    }
+
+    fn route_with(
+        mut self,
+        path: &str,
+        auth: Auth,
+        routes: impl IntoIterator<Item = Route>,
+    ) -> Self {
+        let mut handlers: Option<MethodRouter<AppState>> = None;
+        for route in routes {
+            self.endpoints.push(Endpoint {
+                method: route.method,
+                path: placeholders(path),
+                auth,
+                summary: route.summary,
+            });
+            handlers = Some(match handlers {
+                Some(handlers) => handlers.merge(route.handler),
+                None => route.handler,
+            });
+        }
+        if let Some(handlers) = handlers {
+            self.router = self.router.route(path, handlers);
+        }
+        self
+    }
+
+    /// Serve everything under `path` with `service`, listed as `GET path/{path}`
+    pub fn nest_service<S>(mut self, path: &str, summary: &'static str, service: S) -> Self
+    where
+        S: Service<Request, Error = Infallible> + Clone + Send + 'static,
+        S::Response: IntoResponse,
+        S::Future: Send + 'static,
+    {
+        self.endpoints.push(Endpoint {
+            method: "GET",
+            path: format!("{}/{{path}}", path),
+            auth: self.auth,
+            summary,
+        });
+        self.router = self.router.nest_service(path, service);
+        self
+    }
+
+    pub fn merge(mut self, other: Routes) -> Self {
+        self.router = self.router.merge(other.router);
+        self.endpoints.extend(other.endpoints);
+        self
+    }
+
+    /// Change the router without adding routes, e.g. to add a route layer
+    pub fn map(mut self, f: impl FnOnce(Router<AppState>) -> Router<AppState>) -> Self {
+        self.router = f(self.router);
+        self
+    }
+
+    pub fn finish(self) -> (Router<AppState>, Manifest) {
+        let manifest = Manifest {
+            service: "Rust Weather API",
+            version: env!("CARGO_PKG_VERSION"),
+            endpoints: self.endpoints,
+        };
+        (self.router, manifest)
+    }
+}
+
+/// `/history/:city` as `/history/{city}`
+fn placeholders(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix([':', '*']) {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The endpoint manifest (GET /.well-known/api-manifest)
+pub async fn get_manifest(Extension(manifest): Extension<Arc<Manifest>>) -> Json<Manifest> {
+    Json(manifest.as_ref().clone())
+}
+
+/// OpenAPI description generated from the manifest (GET /openapi.json)
+pub async fn get_openapi(Extension(manifest): Extension<Arc<Manifest>>) -> Json<serde_json::Value> {
+    Json(manifest.openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_lists_what_is_routed() {
+        let admin = Routes::new(Auth::AdminToken).route(
+            "/admin/cities/:name",
+            [
+                put(|| async {}, "Edit a city"),
+                delete(|| async {}, "Delete a city"),
+            ],
+        );
+        let (_, manifest) = Routes::new(Auth::ApiKey)
+            .open_route("/", [get(|| async {}, "Health check")])
+            .route("/history/:city", [get(|| async {}, "Recorded history")])
+            .merge(admin)
+            .finish();
+
+        assert_eq!(
+            manifest.labels(),
+            [
+                "GET /",
+                "GET /history/{city}",
+                "PUT /admin/cities/{name}",
+                "DELETE /admin/cities/{name}"
+            ]
+        );
+        assert_eq!(manifest.endpoints[0].auth, Auth::None);
+        assert_eq!(manifest.endpoints[1].auth, Auth::ApiKey);
+        assert_eq!(
+            manifest.methods_for("/admin/cities/oslo"),
+            ["PUT", "DELETE"]
+        );
+        assert!(manifest.methods_for("/history").is_empty());
+
+        let openapi = manifest.openapi();
+        let history = &openapi["paths"]["/history/{city}"]["get"];
+        assert_eq!(history["parameters"][0]["name"], "city");
+        assert_eq!(history["security"][0]["apiKey"], serde_json::json!([]));
+    }
+}
@@ -1,14 +1,17 @@
 use axum::{
     extract::Json,
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use chrono::Utc;
 
-// Import weather database from parent module (server.rs)
-use crate::{get_weather_database, WeatherData};
+// Import shared state and data types from parent module (server.rs)
+use crate::context::RequestContext;
+use crate::mcp_session::{Envelope, PROTOCOL_VERSIONS};
+use crate::tenants::Scoped;
+use crate::WeatherData;
 
 /// MCP Request structure
 #[derive(Debug, Deserialize)]
@@ -39,6 +42,8 @@ pub struct McpErrorResponse {
 ///
 /// This endpoint provides weather information in MCP (Model Context Protocol) format
 /// for integration with Claude Code and other MCP-compatible clients.
+/// Unknown cities get default values, or a 404 in strict mode
+/// (`?strict=true` or `Prefer: handling=strict`).
 ///
 /// ## Request Format
 /// ```json
@@ -57,91 +62,359 @@ pub struct McpErrorResponse {
 ///     "Gaza": {
 ///       "city": "Gaza",
 ///       "temperature": 27,
-///       "condition": "Sunny",
+///       "condition": "clear",
+///       "description": "Sunny",
 ///       "humidity": 60,
-///       "wind_speed": 12
+///       "wind_speed": 12,
+///       "icon": "clear",
+///       "source": "static",
+///       "observed_at": "2025-10-08T14:30:00Z"
 ///     }
 ///   }
 /// }
 /// ```
+///
+/// Clients on MCP revision 2025-06-18 or later (their session's, or the
+/// `MCP-Protocol-Version` header) get the same object as `structuredContent`
+/// next to a text content block with one line per city.
 pub async fn weather_info_mcp(
+    Scoped(state): Scoped,
+    context: RequestContext,
+    envelope: Envelope,
     Json(payload): Json<McpWeatherRequest>,
-) -> Result<Json<McpWeatherResponse>, (StatusCode, Json<McpErrorResponse>)> {
-
+) -> Response {
     let timestamp = Utc::now().to_rfc3339();
+    let error = |status: StatusCode, error: String| {
+        envelope.error(
+            status,
+            McpErrorResponse {
+                tool: "weather_info".to_string(),
+                status: "error".to_string(),
+                timestamp: timestamp.clone(),
+                error,
+                code: status.as_u16(),
+            },
+        )
+    };
 
     // Validation: check if cities list is empty
     if payload.cities.is_empty() {
-        return Err((
+        return error(
             StatusCode::BAD_REQUEST,
-            Json(McpErrorResponse {
-                tool: "weather_info".to_string(),
-                status: "error".to_string(),
-                timestamp,
-                error: "Cities list cannot be empty".to_string(),
-                code: 400,
-            }),
-        ));
+            "Cities list cannot be empty".to_string(),
+        );
     }
 
     // Validation: check if too many cities requested
     if payload.cities.len() > 20 {
-        return Err((
+        return error(
             StatusCode::BAD_REQUEST,
-            Json(McpErrorResponse {
-                tool: "weather_info".to_string(),
-                status: "error".to_string(),
-                timestamp,
-                error: format!(
-                    "Too many cities requested. Maximum is 20, you requested {}",
-                    payload.cities.len()
-                ),
-                code: 400,
-            }),
-        ));
+            format!(
+                "Too many cities requested. Maximum is 20, you requested {}",
+                payload.cities.len()
+            ),
+        );
     }
 
-    println!("🔧 [MCP] Received weather_info request for {} cities", payload.cities.len());
+    if context.strict {
+        let database = state.cities.current();
+        let unknown: Vec<&str> = payload
+            .cities
+            .iter()
+            .filter(|city| database.get(&city.to_lowercase()).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return error(
+                StatusCode::NOT_FOUND,
+                format!("Unknown cities: {}", unknown.join(", ")),
+            );
+        }
+    }
 
-    let weather_db = get_weather_database();
-    let mut results = HashMap::new();
+    println!(
+        "🔧 [MCP] Received weather_info request for {} cities{}",
+        payload.cities.len(),
+        context.caller_suffix()
+    );
+
+    let mut results: HashMap<String, WeatherData> = HashMap::new();
+    // One line per city, in the order asked for, for the text content block
+    let mut lines = Vec::new();
 
     for city in payload.cities {
-        let city_lower = city.to_lowercase();
-
-        let weather_data = if let Some((temp, condition, humidity, wind)) =
-            weather_db.get(city_lower.as_str())
-        {
-            WeatherData {
-                city: city.clone(),
-                temperature: *temp,
-                condition: condition.to_string(),
-                humidity: *humidity,
-                wind_speed: *wind,
-            }
-        } else {
-            // Default data for unknown cities
-            WeatherData {
-                city: city.clone(),
-                temperature: 20,
-                condition: "Unknown".to_string(),
-                humidity: 50,
-                wind_speed: 10,
-            }
-        };
+        let weather_data = state.weather_or_default(&city, Utc::now());
+        state.count_request(&city);
 
-        println!("  ✓ [MCP] {} - {}°C, {}", city, weather_data.temperature, weather_data.condition);
+        println!(
+            "  ✓ [MCP] {} - {}°C, {}",
+            city, weather_data.temperature, weather_data.condition
+        );
+        lines.push(format!(
+            "{}: {}, {}, humidity {}%, wind {}",
+            city,
+            context.locale.temperature(weather_data.temperature),
+            context
+                .locale
+                .condition(weather_data.condition, weather_data.description.as_deref()),
+            weather_data.humidity,
+            context.locale.wind(weather_data.wind_speed)
+        ));
         results.insert(city.clone(), weather_data);
     }
 
-    println!("📤 [MCP] Sending response with {} results\n", results.len());
+    println!(
+        "📤 [MCP] Sending response with {} results ({:?})\n",
+        results.len(),
+        envelope
+    );
+
+    envelope.result(
+        McpWeatherResponse {
+            tool: "weather_info".to_string(),
+            status: "success".to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            results,
+        },
+        lines.join("\n"),
+    )
+}
+
+/// Most cities one `compare_weather` call accepts, as for `weather_info`
+const MAX_COMPARE_CITIES: usize = 20;
 
-    Ok(Json(McpWeatherResponse {
-        tool: "weather_info".to_string(),
+/// MCP compare_weather request: two or more cities, the first one being the
+/// baseline the others are compared with
+#[derive(Debug, Deserialize)]
+pub struct McpCompareRequest {
+    pub cities: Vec<String>,
+}
+
+/// How one city's weather differs from the baseline city's
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WeatherDelta {
+    pub city: String,
+    /// Degrees warmer (positive) or colder (negative) than the baseline
+    pub temperature: i32,
+    /// Percentage points of relative humidity
+    pub humidity: i32,
+    /// km/h
+    pub wind_speed: i32,
+    pub same_condition: bool,
+}
+
+/// MCP compare_weather response
+#[derive(Debug, Serialize)]
+pub struct McpCompareResponse {
+    pub tool: String,
+    pub status: String,
+    pub timestamp: String,
+    pub baseline: String,
+    /// Current weather, in request order
+    pub results: Vec<WeatherData>,
+    /// One entry per city after the baseline
+    pub deltas: Vec<WeatherDelta>,
+    pub warmest: String,
+    pub coldest: String,
+    /// One English sentence describing the comparison
+    pub summary: String,
+}
+
+fn compare_error(status: StatusCode, error: String) -> (StatusCode, Json<McpErrorResponse>) {
+    (
+        status,
+        Json(McpErrorResponse {
+            tool: "compare_weather".to_string(),
+            status: "error".to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            error,
+            code: status.as_u16(),
+        }),
+    )
+}
+
+/// MCP-compatible compare_weather endpoint
+///
+/// Compares the current weather of two or more cities: per-city deltas
+/// against the first city plus a one-sentence `summary` an assistant can
+/// quote directly. Unlike `weather_info`, unknown cities are an error, since
+/// default values would make the comparison meaningless.
+///
+/// ## Request Format
+/// ```json
+/// {
+///   "cities": ["Oslo", "Rome"]
+/// }
+/// ```
+pub async fn compare_weather_mcp(
+    Scoped(state): Scoped,
+    Json(payload): Json<McpCompareRequest>,
+) -> Result<Json<McpCompareResponse>, (StatusCode, Json<McpErrorResponse>)> {
+    // The same city twice (in any case) adds nothing to compare
+    let mut cities: Vec<String> = Vec::new();
+    for city in payload.cities {
+        let city = city.trim().to_string();
+        if !city.is_empty() && !cities.iter().any(|c| c.eq_ignore_ascii_case(&city)) {
+            cities.push(city);
+        }
+    }
+    if !(2..=MAX_COMPARE_CITIES).contains(&cities.len()) {
+        return Err(compare_error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Between 2 and {} different cities are needed, got {}",
+                MAX_COMPARE_CITIES,
+                cities.len()
+            ),
+        ));
+    }
+
+    let database = state.cities.current();
+    let unknown: Vec<&str> = cities
+        .iter()
+        .filter(|city| database.get(&city.to_lowercase()).is_none())
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        return Err(compare_error(
+            StatusCode::NOT_FOUND,
+            format!("Unknown cities: {}", unknown.join(", ")),
+        ));
+    }
+
+    println!(
+        "🔧 [MCP] Received compare_weather request for {}",
+        cities.join(", ")
+    );
+
+    let now = Utc::now();
+    let readings: Vec<WeatherData> = cities
+        .iter()
+        .map(|city| {
+            state.count_request(city);
+            state.weather_or_default(city, now)
+        })
+        .collect();
+    let comparison = compare(readings);
+    println!("📤 [MCP] {}\n", comparison.summary);
+
+    Ok(Json(comparison))
+}
+
+/// Deltas and summary for `readings`, of which there are at least two
+fn compare(readings: Vec<WeatherData>) -> McpCompareResponse {
+    let baseline = &readings[0];
+    let deltas = readings[1..]
+        .iter()
+        .map(|reading| WeatherDelta {
+            city: reading.city.to_string(),
+            temperature: reading.temperature - baseline.temperature,
+            humidity: reading.humidity - baseline.humidity,
+            wind_speed: reading.wind_speed - baseline.wind_speed,
+            same_condition: reading.condition == baseline.condition,
+        })
+        .collect();
+    // The first city wins ties, following the request order
+    let warmest = readings
+        .iter()
+        .rev()
+        .max_by_key(|r| r.temperature)
+        .expect("at least two readings");
+    let coldest = readings
+        .iter()
+        .min_by_key(|r| r.temperature)
+        .expect("at least two readings");
+
+    McpCompareResponse {
+        tool: "compare_weather".to_string(),
         status: "success".to_string(),
         timestamp: Utc::now().to_rfc3339(),
-        results,
-    }))
+        baseline: baseline.city.to_string(),
+        deltas,
+        warmest: warmest.city.to_string(),
+        coldest: coldest.city.to_string(),
+        summary: summarize(&readings, warmest, coldest),
+        results: readings,
+    }
+}
+
+fn summarize(readings: &[WeatherData], warmest: &WeatherData, coldest: &WeatherData) -> String {
+    let spread = warmest.temperature - coldest.temperature;
+
+    if let [first, second] = readings {
+        // Described from the warmer city's side, or the first city's on a tie
+        let (warm, cold) = if spread == 0 {
+            (first, second)
+        } else {
+            (warmest, coldest)
+        };
+        let condition = |r: &WeatherData| r.condition.label().to_lowercase();
+        let humidity = match warm.humidity - cold.humidity {
+            0 => "the same humidity".to_string(),
+            h if h > 0 => format!("{}% more humidity", h),
+            h => format!("{}% less humidity", -h),
+        };
+        let wind = match warm.wind_speed - cold.wind_speed {
+            0 => "the same wind".to_string(),
+            w if w > 0 => format!("{} km/h more wind", w),
+            w => format!("{} km/h less wind", -w),
+        };
+        return if spread == 0 {
+            format!(
+                "{} and {} are both {}°C ({} vs {}), with {} and {} in {}.",
+                warm.city,
+                cold.city,
+                warm.temperature,
+                condition(warm),
+                condition(cold),
+                humidity,
+                wind,
+                warm.city
+            )
+        } else {
+            format!(
+                "{} is {}°C warmer than {} ({}°C and {} vs {}°C and {}), with {} and {}.",
+                warm.city,
+                spread,
+                cold.city,
+                warm.temperature,
+                condition(warm),
+                cold.temperature,
+                condition(cold),
+                humidity,
+                wind
+            )
+        };
+    }
+
+    let windiest = readings
+        .iter()
+        .rev()
+        .max_by_key(|r| r.wind_speed)
+        .expect("at least two readings");
+    let temperatures = if spread == 0 {
+        format!(
+            "all {} cities are {}°C",
+            readings.len(),
+            warmest.temperature
+        )
+    } else {
+        format!(
+            "of {} cities, {} is the warmest at {}°C and {} the coldest at {}°C ({}°C apart)",
+            readings.len(),
+            warmest.city,
+            warmest.temperature,
+            coldest.city,
+            coldest.temperature,
+            spread
+        )
+    };
+    let mut sentence = format!(
+        "{}, and {} is the windiest at {} km/h.",
+        temperatures, windiest.city, windiest.wind_speed
+    );
+    sentence[..1].make_ascii_uppercase();
+    sentence
 }
 
 /// Health check endpoint for MCP service
@@ -151,7 +424,76 @@ pub async fn mcp_health_check() -> impl IntoResponse {
         "status": "ok",
         "version": "0.3.0",
         "mcp_compatible": true,
-        "tools": ["weather_info"],
-        "endpoint": "/mcp/tool/weather_info"
+        "tools": ["weather_info", "compare_weather"],
+        "protocol_versions": PROTOCOL_VERSIONS,
+        "endpoint": "/mcp/tool/weather_info",
+        "endpoints": {
+            "initialize": "/mcp/initialize",
+            "weather_info": "/mcp/tool/weather_info",
+            "compare_weather": "/mcp/tool/compare_weather"
+        }
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::Condition;
+
+    fn reading(
+        city: &'static str,
+        temperature: i32,
+        humidity: i32,
+        wind_speed: i32,
+    ) -> WeatherData {
+        WeatherData {
+            temperature,
+            humidity,
+            wind_speed,
+            condition: Condition::Clear,
+            ..WeatherData::unknown(city)
+        }
+    }
+
+    #[test]
+    fn test_compare_weather_deltas_and_summary() {
+        let oslo = reading("Oslo", 7, 80, 15);
+        let rome = WeatherData {
+            condition: Condition::Rain,
+            ..reading("Rome", 21, 60, 10)
+        };
+        let comparison = compare(vec![oslo.clone(), rome]);
+        assert_eq!(comparison.baseline, "Oslo");
+        assert_eq!(
+            comparison.deltas,
+            vec![WeatherDelta {
+                city: "Rome".to_string(),
+                temperature: 14,
+                humidity: -20,
+                wind_speed: -5,
+                same_condition: false,
+            }]
+        );
+        assert_eq!(
+            comparison.summary,
+            "Rome is 14°C warmer than Oslo (21°C and rain vs 7°C and clear), with 20% less humidity and 5 km/h less wind."
+        );
+
+        let bergen = reading("Bergen", 7, 90, 30);
+        let tie = compare(vec![oslo.clone(), bergen.clone()]);
+        assert_eq!(
+            (tie.warmest.as_str(), tie.coldest.as_str()),
+            ("Oslo", "Oslo")
+        );
+        assert_eq!(
+            tie.summary,
+            "Oslo and Bergen are both 7°C (clear vs clear), with 10% less humidity and 15 km/h less wind in Oslo."
+        );
+
+        let three = compare(vec![oslo, bergen, reading("Riyadh", 35, 20, 12)]);
+        assert_eq!(
+            three.summary,
+            "Of 3 cities, Riyadh is the warmest at 35°C and Oslo the coldest at 7°C (28°C apart), and Bergen is the windiest at 30 km/h."
+        );
+    }
+}
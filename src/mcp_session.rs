@@ -0,0 +1,307 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::mcp_api::McpErrorResponse;
+use crate::{error_response, AppState, ErrorResponse};
+
+/// Session returned by `POST /mcp/initialize`, sent back on each tool call
+pub const SESSION_HEADER: &str = "mcp-session-id";
+/// Spec revision a client speaks, for tool calls outside a session
+pub const VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// MCP spec revisions the tools understand, oldest first
+pub const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+/// First revision whose tool results are content blocks with structured content
+const CONTENT_BLOCKS_SINCE: &str = "2025-06-18";
+/// Sessions kept at once; the least recently used one is forgotten first
+const MAX_SESSIONS: usize = 1000;
+
+/// Shape of MCP tool results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Envelope {
+    /// `{"tool", "status", "timestamp", "results"}`, as before MCP revision 2025-06-18
+    #[default]
+    Flat,
+    /// `{"content": [{"type": "text", ...}], "structuredContent": <flat result>, "isError"}`
+    Content,
+}
+
+impl Envelope {
+    fn for_version(version: &str) -> Self {
+        if version >= CONTENT_BLOCKS_SINCE {
+            Envelope::Content
+        } else {
+            Envelope::Flat
+        }
+    }
+
+    /// A tool result: `flat` as is, or as the structured content next to
+    /// `text` for the model to read
+    pub fn result<T: Serialize>(self, flat: T, text: String) -> Response {
+        match self {
+            Envelope::Flat => Json(flat).into_response(),
+            Envelope::Content => Json(serde_json::json!({
+                "content": [{"type": "text", "text": text}],
+                "structuredContent": flat,
+                "isError": false
+            }))
+            .into_response(),
+        }
+    }
+
+    /// A tool error, with the same status in either envelope
+    pub fn error(self, status: StatusCode, flat: McpErrorResponse) -> Response {
+        match self {
+            Envelope::Flat => (status, Json(flat)).into_response(),
+            Envelope::Content => (
+                status,
+                Json(serde_json::json!({
+                    "content": [{"type": "text", "text": flat.error}],
+                    "structuredContent": flat,
+                    "isError": true
+                })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// The revision to speak with a client asking for `requested`
+///
+/// A known revision is used as is; a newer one than any known gets the
+/// latest known, as the MCP spec has servers answer.
+pub fn negotiate(requested: &str) -> Option<&'static str> {
+    let requested = requested.trim();
+    if let Some(known) = PROTOCOL_VERSIONS.iter().find(|v| **v == requested) {
+        return Some(known);
+    }
+    let latest = PROTOCOL_VERSIONS.last()?;
+    let is_date = chrono::NaiveDate::parse_from_str(requested, "%Y-%m-%d").is_ok();
+    (is_date && requested > *latest).then_some(*latest)
+}
+
+fn unsupported(requested: &str) -> String {
+    format!(
+        "Unsupported MCP protocol version '{}' (supported: {})",
+        requested,
+        PROTOCOL_VERSIONS.join(", ")
+    )
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    protocol_version: &'static str,
+    envelope: Envelope,
+    last_used: DateTime<Utc>,
+}
+
+/// MCP sessions by id, each with the revision negotiated when it started
+#[derive(Debug, Default)]
+pub struct McpSessions {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl McpSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn start(&self, id: String, protocol_version: &'static str, now: DateTime<Utc>) -> Session {
+        let session = Session {
+            protocol_version,
+            envelope: Envelope::for_version(protocol_version),
+            last_used: now,
+        };
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+        sessions.insert(id, session.clone());
+        session
+    }
+
+    fn touch(&self, id: &str, now: DateTime<Utc>) -> Option<Session> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let session = sessions.get_mut(id)?;
+        session.last_used = now;
+        Some(session.clone())
+    }
+
+    fn end(&self, id: &str) -> bool {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.remove(id).is_some()
+    }
+
+    /// Sessions currently kept
+    pub fn count(&self) -> usize {
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.len()
+    }
+}
+
+type SessionError = (StatusCode, Json<ErrorResponse>);
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// The envelope for a tool call: the session's (`Mcp-Session-Id`), else the
+/// one of `MCP-Protocol-Version`, else `--mcp-envelope`
+#[async_trait]
+impl FromRequestParts<AppState> for Envelope {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(id) = header(&parts.headers, SESSION_HEADER) {
+            return state
+                .mcp_sessions
+                .touch(id, Utc::now())
+                .map(|session| session.envelope)
+                .ok_or_else(|| {
+                    error_response(
+                        StatusCode::NOT_FOUND,
+                        format!(
+                            "Unknown MCP session '{}'; start one with POST /mcp/initialize",
+                            id
+                        ),
+                    )
+                    .into_response()
+                });
+        }
+        match header(&parts.headers, VERSION_HEADER) {
+            Some(requested) => negotiate(requested)
+                .map(Envelope::for_version)
+                .ok_or_else(|| {
+                    error_response(StatusCode::BAD_REQUEST, unsupported(requested)).into_response()
+                }),
+            None => Ok(state.config.mcp_envelope),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeRequest {
+    protocol_version: String,
+}
+
+/// Start an MCP session speaking the revision negotiated from the client's
+pub async fn initialize(
+    State(state): State<AppState>,
+    Json(payload): Json<InitializeRequest>,
+) -> Result<impl IntoResponse, SessionError> {
+    let version = negotiate(&payload.protocol_version).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            unsupported(&payload.protocol_version),
+        )
+    })?;
+
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Cannot generate a session id: {}", e),
+        )
+    })?;
+    let id: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let session = state.mcp_sessions.start(id.clone(), version, Utc::now());
+    println!(
+        "🔧 [MCP] Started session speaking {} ({:?} results)",
+        session.protocol_version, session.envelope
+    );
+    Ok((
+        [(SESSION_HEADER, id.clone())],
+        Json(serde_json::json!({
+            "sessionId": id,
+            "protocolVersion": version,
+            "envelope": session.envelope,
+            "supportedVersions": PROTOCOL_VERSIONS
+        })),
+    ))
+}
+
+/// End the MCP session named by `Mcp-Session-Id`
+pub async fn end_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, SessionError> {
+    let id = header(&headers, SESSION_HEADER).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            "Missing Mcp-Session-Id header".to_string(),
+        )
+    })?;
+    if !state.mcp_sessions.end(id) {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("Unknown MCP session '{}'", id),
+        ));
+    }
+    println!("🔧 [MCP] Ended session");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiation_and_sessions() {
+        assert_eq!(negotiate("2024-11-05"), Some("2024-11-05"));
+        assert_eq!(negotiate("2030-01-01"), Some("2025-06-18"));
+        assert_eq!(negotiate("2023-01-01"), None);
+        assert_eq!(negotiate("latest"), None);
+        assert_eq!(Envelope::for_version("2025-03-26"), Envelope::Flat);
+        assert_eq!(Envelope::for_version("2025-06-18"), Envelope::Content);
+
+        let sessions = McpSessions::new();
+        let start: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        for n in 0..MAX_SESSIONS as i64 {
+            sessions.start(
+                format!("s{}", n),
+                "2024-11-05",
+                start + chrono::TimeDelta::seconds(n),
+            );
+        }
+        // s0 was used most recently, so s1 makes room for a new session
+        sessions.touch("s0", start + chrono::TimeDelta::hours(1));
+        sessions.start(
+            "new".to_string(),
+            "2025-06-18",
+            start + chrono::TimeDelta::hours(2),
+        );
+        assert_eq!(sessions.count(), MAX_SESSIONS);
+        assert!(sessions.touch("s1", start).is_none());
+        assert_eq!(
+            sessions.touch("s0", start).unwrap().envelope,
+            Envelope::Flat
+        );
+        assert_eq!(
+            sessions.touch("new", start).unwrap().envelope,
+            Envelope::Content
+        );
+        assert!(sessions.end("new"));
+        assert!(!sessions.end("new"));
+    }
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::cities::CityRecord;
+use crate::rounding::TemperatureRounding;
+use crate::simulation::ClimateProfile;
+use crate::tenants::Scoped;
+use crate::{error_response, ErrorResponse};
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct NormalsQuery {
+    /// Month (1-12); all twelve months when absent
+    month: Option<u32>,
+}
+
+/// Long-term typical values for one month
+#[derive(Debug, Serialize)]
+pub struct MonthlyNormal {
+    month: u32,
+    name: &'static str,
+    temperature: f64,
+    /// Typical afternoon high
+    temperature_high: f64,
+    /// Typical pre-dawn low
+    temperature_low: f64,
+    humidity: i32,
+    wind_speed: i32,
+}
+
+/// Monthly normals for a city, seeded from the bundled dataset
+///
+/// The dataset's values are treated as annual means; the monthly temperature
+/// follows the same seasonal and day/night model as the simulation. Cities
+/// without coordinates have no seasonal cycle, so every month gets the
+/// annual values.
+pub async fn get_normals(
+    Scoped(state): Scoped,
+    Path(city): Path<String>,
+    Query(query): Query<NormalsQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    println!("📅 Received normals request for {}", city);
+
+    let months = match query.month {
+        Some(month @ 1..=12) => month..=month,
+        Some(month) => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid month {}: expected 1-12", month),
+            ))
+        }
+        None => 1..=12,
+    };
+
+    let key = city.to_lowercase();
+    let Some(record) = state.cities.current().get(&key).cloned() else {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("Unknown city '{}'", city),
+        ));
+    };
+
+    let rounding = state.config.rounding();
+    let normals: Vec<MonthlyNormal> = months
+        .map(|month| monthly_normal(&record, month, rounding))
+        .collect();
+
+    // How today's weather compares with this month's normal
+    let now = Utc::now();
+    let current = state.weather_or_default(&key, now);
+    state.count_request(&key);
+    let usual = monthly_normal(&record, now.month(), rounding).temperature;
+
+    Ok(Json(serde_json::json!({
+        "city": key,
+        "seasonal": record.coordinates().is_some(),
+        "months": normals,
+        "current": {
+            "month": now.month(),
+            "temperature": current.temperature,
+            "normal": usual,
+            "difference": rounding.round(current.temperature as f64 - usual)
+        }
+    })))
+}
+
+fn monthly_normal(record: &CityRecord, month: u32, rounding: TemperatureRounding) -> MonthlyNormal {
+    let (temperature, diurnal) = match record.coordinates() {
+        Some((latitude, longitude)) => {
+            let profile = ClimateProfile {
+                mean_temp: record.temperature as f64,
+                mean_humidity: record.humidity as f64,
+                mean_wind: record.wind_speed as f64,
+                latitude,
+                longitude,
+            };
+            (
+                profile.monthly_mean_temp(month),
+                profile.diurnal_amplitude(),
+            )
+        }
+        None => (record.temperature as f64, 0.0),
+    };
+
+    MonthlyNormal {
+        month,
+        name: MONTH_NAMES[(month - 1) as usize],
+        temperature: rounding.round(temperature),
+        temperature_high: rounding.round(temperature + diurnal),
+        temperature_low: rounding.round(temperature - diurnal),
+        humidity: record.humidity,
+        wind_speed: record.wind_speed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cities::CityDatabase;
+
+    #[test]
+    fn test_monthly_normals_vary_only_with_coordinates() {
+        let db = CityDatabase::builtin();
+        let stockholm = db.get("stockholm").unwrap();
+        let rounding = TemperatureRounding::default();
+        assert!(
+            monthly_normal(stockholm, 7, rounding).temperature
+                > monthly_normal(stockholm, 1, rounding).temperature
+        );
+
+        let mut no_coordinates = stockholm.clone();
+        no_coordinates.latitude = None;
+        no_coordinates.longitude = None;
+        let july = monthly_normal(&no_coordinates, 7, rounding);
+        assert_eq!(july.temperature, stockholm.temperature as f64);
+        assert_eq!(july.name, "July");
+    }
+}
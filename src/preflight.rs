@@ -0,0 +1,232 @@
+use std::fmt;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+use crate::api_keys::ApiKeys;
+use crate::cities::CityDatabase;
+use crate::config::ServerConfig;
+use crate::inference::ConditionRules;
+use crate::serve;
+use crate::store::{self, Saved};
+
+/// Shortest `--signing-secret` that does not get a warning
+const MIN_SIGNING_SECRET_LEN: usize = 16;
+
+/// Everything the server needs from outside its configuration, loaded
+/// and checked before anything starts
+pub struct Startup {
+    pub cities: CityDatabase,
+    pub api_keys: Option<ApiKeys>,
+    /// From `--condition-rules`, replacing the built-in table
+    pub condition_rules: Option<ConditionRules>,
+    /// Favorites, digests and webhooks from `--data-file`
+    pub saved: Saved,
+    #[cfg(feature = "geoip")]
+    pub geoip: Option<crate::geoip::GeoIp>,
+    pub listener: TcpListener,
+    /// Problems that do not stop the server
+    pub warnings: Vec<String>,
+}
+
+/// Every problem found by [`check`], printed as one report
+#[derive(Debug, Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.errors.len();
+        writeln!(
+            f,
+            "❌ Startup check failed ({} problem{}):",
+            count,
+            if count == 1 { "" } else { "s" }
+        )?;
+        for error in &self.errors {
+            writeln!(f, "   • {}", error)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "   ⚠️  {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Load the cities, API keys, condition rules, saved data and GeoIP database,
+/// bind the listener and check the settings that depend on each other
+///
+/// Keeps going after a failure, so one run reports every problem instead of
+/// the first one.
+pub fn check(config: &ServerConfig, addr: SocketAddr, backlog: u32) -> Result<Startup, Report> {
+    let mut report = Report::default();
+
+    let cities = match &config.cities_file {
+        Some(path) => CityDatabase::load(path)
+            .map_err(|e| report.errors.push(format!("--cities-file: {}", e)))
+            .ok(),
+        None => Some(CityDatabase::builtin()),
+    };
+
+    let api_keys = match &config.api_keys_file {
+        Some(path) => match ApiKeys::load(path) {
+            Ok(keys) => Some(Some(keys)),
+            Err(e) => {
+                report.errors.push(format!("--api-keys-file: {}", e));
+                None
+            }
+        },
+        None => Some(None),
+    };
+    if let (Some(Some(keys)), Some(cities)) = (&api_keys, &cities) {
+        for (name, tenant) in keys.tenants() {
+            let unknown = tenant.unknown_cities(cities);
+            if !unknown.is_empty() {
+                report.warnings.push(format!(
+                    "API key '{}' lists unknown cities: {}",
+                    name,
+                    unknown.join(", ")
+                ));
+            }
+        }
+    }
+
+    let condition_rules = match &config.condition_rules {
+        Some(path) => match ConditionRules::load(path) {
+            Ok(rules) => Some(Some(rules)),
+            Err(e) => {
+                report.errors.push(format!("--condition-rules: {}", e));
+                None
+            }
+        },
+        None => Some(None),
+    };
+    if condition_rules.as_ref().is_some_and(Option::is_some) && !config.simulate {
+        report.warnings.push(
+            "--condition-rules without --simulate: static data has no measurements to infer from"
+                .to_string(),
+        );
+    }
+
+    let saved = match store::open(config).load() {
+        Ok(saved) => Some(saved),
+        Err(e) => {
+            report.errors.push(format!("--data-file: {}", e));
+            None
+        }
+    };
+    if let Some(saved) = &saved {
+        let per_key = !saved.favorites.is_empty() || !saved.digests.is_empty();
+        if per_key && config.api_keys_file.is_none() {
+            report.warnings.push(
+                "--data-file has favorites or digests but there is no --api-keys-file to serve them"
+                    .to_string(),
+            );
+        }
+    }
+
+    #[cfg(feature = "geoip")]
+    let geoip = match &config.geoip_db {
+        Some(path) => match crate::geoip::GeoIp::open(path) {
+            Ok(geoip) => Some(Some(geoip)),
+            Err(e) => {
+                report.errors.push(format!("--geoip-db: {}", e));
+                None
+            }
+        },
+        None => Some(None),
+    };
+
+    if let Some(dir) = &config.static_dir {
+        if !dir.is_dir() {
+            report.warnings.push(format!(
+                "--static-dir: {} is not a directory; /static will return 404",
+                dir.display()
+            ));
+        }
+    }
+    if config.chaos && config.chaos_error_rate + config.chaos_malformed_rate > 100.0 {
+        report.errors.push(format!(
+            "--chaos-error-rate ({}%) and --chaos-malformed-rate ({}%) add up to more than 100%",
+            config.chaos_error_rate, config.chaos_malformed_rate
+        ));
+    }
+    if let Some(secret) = &config.signing_secret {
+        if secret.len() < MIN_SIGNING_SECRET_LEN {
+            report.warnings.push(format!(
+                "--signing-secret is shorter than {} characters and easy to guess",
+                MIN_SIGNING_SECRET_LEN
+            ));
+        }
+    }
+    if config.sign_mcp && config.signing_secret.is_none() && config.admin_token.is_none() {
+        report.warnings.push(
+            "--sign-mcp without --signing-secret or --admin-token: there is no way to add a signing key"
+                .to_string(),
+        );
+    }
+
+    let listener = serve::bind(addr, backlog)
+        .map_err(|e| {
+            report
+                .errors
+                .push(format!("cannot listen on {}: {}", addr, e))
+        })
+        .ok();
+
+    match (cities, api_keys, condition_rules, saved, listener) {
+        (Some(cities), Some(api_keys), Some(condition_rules), Some(saved), Some(listener))
+            if report.errors.is_empty() =>
+        {
+            Ok(Startup {
+                cities,
+                api_keys,
+                condition_rules,
+                saved,
+                #[cfg(feature = "geoip")]
+                geoip: geoip.flatten(),
+                listener,
+                warnings: report.warnings,
+            })
+        }
+        _ => Err(report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_reports_every_problem_at_once() {
+        let config = ServerConfig::parse_from([
+            "server",
+            "--cities-file",
+            "/nonexistent/cities.json",
+            "--api-keys-file",
+            "/nonexistent/keys.toml",
+            "--chaos",
+            "--chaos-error-rate",
+            "80",
+            "--chaos-malformed-rate",
+            "30",
+            "--signing-secret",
+            "short",
+        ]);
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let Err(report) = check(&config, addr, 16) else {
+            panic!("the check should fail");
+        };
+        assert_eq!(report.errors.len(), 3, "{}", report);
+        assert!(report.errors[0].starts_with("--cities-file: "));
+        assert!(report.errors[1].starts_with("--api-keys-file: "));
+        assert_eq!(report.warnings.len(), 1);
+
+        let config = ServerConfig::parse_from(["server"]);
+        let startup = check(&config, addr, 16).unwrap();
+        assert!(startup.warnings.is_empty());
+        assert!(startup.cities.len() >= 40);
+    }
+}
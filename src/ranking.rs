@@ -0,0 +1,206 @@
+use axum::{extract::Query, http::StatusCode, Json};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::config::RankWeights;
+use crate::tenants::Scoped;
+use crate::{error_response, ErrorResponse, WeatherData};
+
+/// Ideal temperature when `ideal_temp` is not given (°C)
+const DEFAULT_IDEAL_TEMP: f64 = 22.0;
+/// Ideal relative humidity when `ideal_humidity` is not given (%)
+const DEFAULT_IDEAL_HUMIDITY: f64 = 50.0;
+/// Distance from the ideal temperature at which it no longer scores (°C)
+const TEMP_TOLERANCE: f64 = 20.0;
+/// Distance from the ideal humidity at which it no longer scores (%)
+const HUMIDITY_TOLERANCE: f64 = 50.0;
+/// Wind speed at which the wind no longer scores (km/h)
+const WIND_TOLERANCE: f64 = 50.0;
+
+type RankError = (StatusCode, Json<ErrorResponse>);
+
+#[derive(Debug, Deserialize)]
+pub struct RankQuery {
+    ideal_temp: Option<f64>,
+    ideal_humidity: Option<f64>,
+    /// Override the `--rank-weights` defaults
+    temp_weight: Option<f64>,
+    humidity_weight: Option<f64>,
+    wind_weight: Option<f64>,
+    /// Return only the best cities
+    limit: Option<usize>,
+}
+
+/// Closeness of each factor to ideal, from 0 (at or beyond the tolerance) to 1
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Components {
+    pub temperature: f64,
+    pub humidity: f64,
+    pub wind: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankedCity {
+    rank: usize,
+    city: String,
+    /// Weighted mean of the components, 0 to 100
+    score: f64,
+    components: Components,
+    weather: WeatherData,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankResponse {
+    ideal_temp: f64,
+    ideal_humidity: f64,
+    weights: RankWeights,
+    count: usize,
+    cities: Vec<RankedCity>,
+}
+
+/// How close one reading is to the ideal conditions
+pub fn components(weather: &WeatherData, ideal_temp: f64, ideal_humidity: f64) -> Components {
+    let closeness = |distance: f64, tolerance: f64| (1.0 - distance / tolerance).clamp(0.0, 1.0);
+    Components {
+        temperature: closeness(
+            (weather.temperature as f64 - ideal_temp).abs(),
+            TEMP_TOLERANCE,
+        ),
+        humidity: closeness(
+            (weather.humidity as f64 - ideal_humidity).abs(),
+            HUMIDITY_TOLERANCE,
+        ),
+        wind: closeness(weather.wind_speed as f64, WIND_TOLERANCE),
+    }
+}
+
+/// Weighted mean of the components, scaled to 0..=100 with one decimal
+pub fn score(components: Components, weights: RankWeights) -> f64 {
+    let total = weights.temperature + weights.humidity + weights.wind;
+    let weighted = components.temperature * weights.temperature
+        + components.humidity * weights.humidity
+        + components.wind * weights.wind;
+    (weighted / total * 1000.0).round() / 10.0
+}
+
+/// Every city ranked by how close its current weather is to the ideal
+///
+/// The score combines closeness to `ideal_temp` and `ideal_humidity` and how
+/// calm the wind is, weighted by `--rank-weights` unless the request sets
+/// `temp_weight`, `humidity_weight` or `wind_weight`. Ties are broken by name.
+pub async fn get_rank(
+    Scoped(state): Scoped,
+    Query(query): Query<RankQuery>,
+) -> Result<Json<RankResponse>, RankError> {
+    let ideal_temp = query.ideal_temp.unwrap_or(DEFAULT_IDEAL_TEMP);
+    if !(-60.0..=60.0).contains(&ideal_temp) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "ideal_temp must be between -60 and 60".to_string(),
+        ));
+    }
+    let ideal_humidity = query.ideal_humidity.unwrap_or(DEFAULT_IDEAL_HUMIDITY);
+    if !(0.0..=100.0).contains(&ideal_humidity) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "ideal_humidity must be between 0 and 100".to_string(),
+        ));
+    }
+    let defaults = state.config.rank_weights;
+    let weights = RankWeights {
+        temperature: query.temp_weight.unwrap_or(defaults.temperature),
+        humidity: query.humidity_weight.unwrap_or(defaults.humidity),
+        wind: query.wind_weight.unwrap_or(defaults.wind),
+    };
+    weights
+        .validate()
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+    if query.limit == Some(0) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "limit must be at least 1".to_string(),
+        ));
+    }
+    println!("🏅 Ranking cities for an ideal of {}°C", ideal_temp);
+
+    let now = Utc::now();
+    let database = state.cities.current();
+    let mut ranked: Vec<RankedCity> = database
+        .keyed()
+        .map(|(key, record)| {
+            let weather = state.weather(key, key.clone(), record, now);
+            let components = components(&weather, ideal_temp, ideal_humidity);
+            RankedCity {
+                rank: 0,
+                city: key.to_string(),
+                score: score(components, weights),
+                components,
+                weather,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.city.cmp(&b.city))
+    });
+    ranked.truncate(query.limit.unwrap_or(usize::MAX));
+    for (i, city) in ranked.iter_mut().enumerate() {
+        city.rank = i + 1;
+    }
+
+    Ok(Json(RankResponse {
+        ideal_temp,
+        ideal_humidity,
+        weights,
+        count: ranked.len(),
+        cities: ranked,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::Condition;
+    use crate::Source;
+
+    fn reading(temperature: i32, humidity: i32, wind_speed: i32) -> WeatherData {
+        WeatherData {
+            city: "test".into(),
+            temperature,
+            condition: Condition::Clear,
+            description: None,
+            humidity,
+            wind_speed,
+            icon: Condition::Clear.icon(),
+            source: Source::Static,
+            observed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_scores_favor_readings_close_to_ideal() {
+        let weights = RankWeights {
+            temperature: 3.0,
+            humidity: 1.0,
+            wind: 1.0,
+        };
+        let perfect = components(&reading(22, 50, 0), 22.0, 50.0);
+        assert_eq!(score(perfect, weights), 100.0);
+
+        let mild = score(components(&reading(18, 60, 10), 22.0, 50.0), weights);
+        let harsh = score(components(&reading(-5, 90, 40), 22.0, 50.0), weights);
+        assert!(mild > harsh, "{} should beat {}", mild, harsh);
+        assert_eq!(harsh, 8.0);
+
+        let wind_only = RankWeights {
+            temperature: 0.0,
+            humidity: 0.0,
+            wind: 1.0,
+        };
+        assert_eq!(
+            score(components(&reading(-5, 90, 0), 22.0, 50.0), wind_only),
+            100.0
+        );
+    }
+}
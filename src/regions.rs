@@ -0,0 +1,177 @@
+use axum::Json;
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::conditions::Condition;
+use crate::rounding::TemperatureRounding;
+use crate::tenants::Scoped;
+use crate::WeatherData;
+
+/// Aggregates over the cities of one continent or country
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RegionStats {
+    pub cities: usize,
+    pub average_temp: f64,
+    pub min_temp: i32,
+    pub max_temp: i32,
+    /// Most frequent condition (first by code on ties)
+    pub dominant_condition: Condition,
+}
+
+impl RegionStats {
+    fn of(readings: &[&WeatherData], rounding: TemperatureRounding) -> Option<Self> {
+        let total: i32 = readings.iter().map(|w| w.temperature).sum();
+        Some(RegionStats {
+            cities: readings.len(),
+            average_temp: rounding.round(total as f64 / readings.len() as f64),
+            min_temp: readings.iter().map(|w| w.temperature).min()?,
+            max_temp: readings.iter().map(|w| w.temperature).max()?,
+            dominant_condition: Condition::most_common(readings.iter().map(|w| w.condition))?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CountryStats {
+    /// ISO 3166-1 alpha-2 code; `null` for cities without one
+    pub country: Option<String>,
+    #[serde(flatten)]
+    pub stats: RegionStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContinentStats {
+    /// `null` for cities without one
+    pub continent: Option<String>,
+    #[serde(flatten)]
+    pub stats: RegionStats,
+    pub countries: Vec<CountryStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionsResponse {
+    pub total_cities: usize,
+    pub continents: Vec<ContinentStats>,
+}
+
+/// Readings grouped by a name, with cities lacking it (`None`) sorted last
+type Groups<'a> = BTreeMap<(bool, Option<&'a str>), Vec<&'a WeatherData>>;
+
+fn group<'a>(groups: &mut Groups<'a>, name: Option<&'a str>, reading: &'a WeatherData) {
+    groups
+        .entry((name.is_none(), name))
+        .or_default()
+        .push(reading);
+}
+
+/// Continents (by name) and their countries (by code), each with its stats
+pub fn rollup(
+    cities: &[(Option<&str>, Option<&str>, WeatherData)],
+    rounding: TemperatureRounding,
+) -> Vec<ContinentStats> {
+    let mut continents: BTreeMap<(bool, Option<&str>), Groups> = BTreeMap::new();
+    for (continent, country, reading) in cities {
+        let countries = continents
+            .entry((continent.is_none(), *continent))
+            .or_default();
+        group(countries, *country, reading);
+    }
+
+    continents
+        .into_iter()
+        .filter_map(|((_, continent), countries)| {
+            let readings: Vec<&WeatherData> = countries.values().flatten().copied().collect();
+            Some(ContinentStats {
+                continent: continent.map(str::to_string),
+                stats: RegionStats::of(&readings, rounding)?,
+                countries: countries
+                    .into_iter()
+                    .filter_map(|((_, country), readings)| {
+                        Some(CountryStats {
+                            country: country.map(str::to_string),
+                            stats: RegionStats::of(&readings, rounding)?,
+                        })
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Current weather aggregated by continent and, within each, by country
+///
+/// Averages follow `--temperature-precision` and `--temperature-rounding`.
+/// Cities without a continent or country are grouped under `null`, last.
+pub async fn get_region_stats(Scoped(state): Scoped) -> Json<RegionsResponse> {
+    println!("🗺️  Received regional stats request");
+
+    let now = Utc::now();
+    let database = state.cities.current();
+    let cities: Vec<(Option<&str>, Option<&str>, WeatherData)> = database
+        .keyed()
+        .map(|(key, record)| {
+            (
+                record.continent.as_deref(),
+                record.country.as_deref(),
+                state.weather(key, key.clone(), record, now),
+            )
+        })
+        .collect();
+    let continents = rollup(&cities, state.config.rounding());
+
+    println!("📤 Sending stats for {} continents\n", continents.len());
+    Json(RegionsResponse {
+        total_cities: cities.len(),
+        continents,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(temperature: i32, condition: Condition) -> WeatherData {
+        WeatherData {
+            temperature,
+            condition,
+            ..WeatherData::unknown("test")
+        }
+    }
+
+    #[test]
+    fn test_rollup_by_continent_and_country() {
+        use Condition::{Clear, Rain};
+        let cities = vec![
+            (Some("Europe"), Some("SE"), reading(7, Rain)),
+            (Some("Europe"), Some("SE"), reading(10, Rain)),
+            (Some("Europe"), Some("IT"), reading(24, Clear)),
+            (None, None, reading(20, Clear)),
+            (Some("Asia"), None, reading(30, Clear)),
+        ];
+        let continents = rollup(&cities, TemperatureRounding::default());
+
+        let names: Vec<Option<&str>> = continents.iter().map(|c| c.continent.as_deref()).collect();
+        assert_eq!(names, vec![Some("Asia"), Some("Europe"), None]);
+
+        let europe = &continents[1];
+        assert_eq!(
+            europe.stats,
+            RegionStats {
+                cities: 3,
+                average_temp: 13.7,
+                min_temp: 7,
+                max_temp: 24,
+                dominant_condition: Rain,
+            }
+        );
+        let countries: Vec<Option<&str>> = europe
+            .countries
+            .iter()
+            .map(|c| c.country.as_deref())
+            .collect();
+        assert_eq!(countries, vec![Some("IT"), Some("SE")]);
+        assert_eq!(europe.countries[1].stats.average_temp, 8.5);
+        assert_eq!(continents[0].countries[0].country, None);
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::Utc;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::cities::{CityDatabase, CityStore};
+use crate::events::{EventHub, UpdateEvent};
+
+/// How long to wait for a burst of file events to settle before reloading
+///
+/// Editors often write a file in several steps (truncate, write, rename);
+/// reloading after the first event would see a partial file.
+const SETTLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Watch the cities file and swap in its new contents whenever it changes
+///
+/// The parent directory is watched rather than the file itself, so the
+/// watch survives editors that save by replacing the file. A file that fails
+/// to load or validate is reported and the current data is kept.
+///
+/// The returned watcher stops watching when dropped.
+pub fn watch_cities_file(
+    path: PathBuf,
+    store: Arc<CityStore>,
+    events: Arc<EventHub>,
+) -> notify::Result<RecommendedWatcher> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        if event
+            .paths
+            .iter()
+            .any(|p| p.file_name() == file_name.as_deref())
+        {
+            let _ = tx.send(());
+        }
+    })?;
+
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(SETTLE_DELAY).await;
+            while rx.try_recv().is_ok() {}
+
+            reload(&path, &store, &events);
+        }
+    });
+
+    Ok(watcher)
+}
+
+fn reload(path: &Path, store: &CityStore, events: &EventHub) {
+    match CityDatabase::load(path) {
+        Ok(cities) => {
+            println!(
+                "🔄 Reloaded {} cities from {}",
+                cities.len(),
+                path.display()
+            );
+            let count = cities.len();
+            store.replace(cities);
+            events.publish(UpdateEvent::CitiesReplaced {
+                cities: count,
+                at: Utc::now(),
+            });
+        }
+        Err(e) => eprintln!("⚠️  Keeping previous city data: {}", e),
+    }
+}
@@ -0,0 +1,302 @@
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::{MatchedPath, Query, Request, State},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::admin::AdminError;
+use crate::traceparent::TraceParent;
+use crate::AppState;
+
+/// Longest error message kept per entry
+const MAX_ERROR_CHARS: usize = 200;
+/// Error bodies larger than this are not inspected
+const MAX_ERROR_BODY_BYTES: usize = 16 * 1024;
+
+/// Summary of one handled request
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestSummary {
+    pub at: DateTime<Utc>,
+    pub method: String,
+    /// Route pattern, e.g. `/normals/:city` (absent for unknown routes)
+    pub route: Option<String>,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    pub status: u16,
+    pub latency_ms: f64,
+    /// Error message from 4xx/5xx responses, truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Distributed trace the request belonged to (see [`crate::trace::join`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// Recent request summaries, bounded by age and count
+///
+/// Kept in memory only; entries older than the retention or beyond the
+/// entry limit are dropped, oldest first.
+#[derive(Debug)]
+pub struct RequestLog {
+    retention: Duration,
+    max_entries: usize,
+    entries: Mutex<VecDeque<RequestSummary>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestLogQuery {
+    /// Only requests to this route pattern
+    route: Option<String>,
+    /// Only requests with exactly this status
+    status: Option<u16>,
+    /// Only requests with at least this status, e.g. 400 for all errors
+    min_status: Option<u16>,
+    /// Only requests of this distributed trace
+    trace_id: Option<String>,
+    /// Most recent entries to return (default 100)
+    limit: Option<usize>,
+}
+
+impl RequestLog {
+    pub fn new(retention: Duration, max_entries: usize) -> Self {
+        RequestLog {
+            retention,
+            max_entries,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn push(&self, entry: RequestSummary) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+        self.expire(&mut entries, Utc::now());
+    }
+
+    fn expire(&self, entries: &mut VecDeque<RequestSummary>, now: DateTime<Utc>) {
+        // A retention reaching past the earliest date keeps everything
+        let Some(cutoff) = chrono::Duration::from_std(self.retention)
+            .ok()
+            .and_then(|retention| now.checked_sub_signed(retention))
+        else {
+            return;
+        };
+        while entries.front().is_some_and(|entry| entry.at < cutoff) {
+            entries.pop_front();
+        }
+    }
+
+    /// Matching entries, newest first
+    fn search(&self, query: &RequestLogQuery) -> Vec<RequestSummary> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut entries, Utc::now());
+        entries
+            .iter()
+            .rev()
+            .filter(|e| query.route.is_none() || e.route == query.route)
+            .filter(|e| query.status.is_none_or(|status| e.status == status))
+            .filter(|e| query.min_status.is_none_or(|status| e.status >= status))
+            .filter(|e| query.trace_id.is_none() || e.trace_id == query.trace_id)
+            .take(query.limit.unwrap_or(100))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Middleware recording a summary of every request when `--request-log` is on
+pub async fn record(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(log) = state.request_log.clone() else {
+        return next.run(request).await;
+    };
+
+    let at = Utc::now();
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().map(str::to_string);
+    let trace_id = request
+        .extensions()
+        .get::<TraceParent>()
+        .map(TraceParent::trace_id_hex);
+
+    let response = next.run(request).await;
+    let status = response.status();
+
+    // Keep the error message; successful bodies are not inspected, nor are
+    // bodies that are streamed or too large, which pass through untouched
+    let inspect = (status.is_client_error() || status.is_server_error())
+        && response
+            .body()
+            .size_hint()
+            .upper()
+            .is_some_and(|size| size <= MAX_ERROR_BODY_BYTES as u64);
+    let (response, error) = if inspect {
+        let (parts, body) = response.into_parts();
+        match to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+            Ok(bytes) => {
+                let error = error_message(&bytes);
+                (Response::from_parts(parts, Body::from(bytes)), Some(error))
+            }
+            Err(_) => (Response::from_parts(parts, Body::empty()), None),
+        }
+    } else {
+        (response, None)
+    };
+
+    log.push(RequestSummary {
+        at,
+        method,
+        route,
+        path,
+        query,
+        status: status.as_u16(),
+        latency_ms: (started.elapsed().as_secs_f64() * 1000.0 * 100.0).round() / 100.0,
+        error,
+        trace_id,
+    });
+
+    response
+}
+
+/// The `error` field of a JSON error body, or the body as text, truncated
+fn error_message(body: &[u8]) -> String {
+    let message = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| json["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).into_owned());
+    message.chars().take(MAX_ERROR_CHARS).collect()
+}
+
+/// Search the request log, newest first
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<RequestLogQuery>,
+) -> Result<Json<serde_json::Value>, AdminError> {
+    println!("🛠️  Admin: searching request log");
+
+    let log = state.request_log.as_ref().ok_or_else(|| {
+        AdminError::Status(
+            axum::http::StatusCode::NOT_FOUND,
+            "Request logging is disabled (start the server with --request-log)".to_string(),
+        )
+    })?;
+    let entries = log.search(&query);
+
+    Ok(Json(serde_json::json!({
+        "count": entries.len(),
+        "requests": entries
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(status: u16, age: chrono::Duration) -> RequestSummary {
+        RequestSummary {
+            at: Utc::now() - age,
+            method: "GET".to_string(),
+            route: Some("/stats".to_string()),
+            path: "/stats".to_string(),
+            query: None,
+            status,
+            latency_ms: 1.0,
+            error: None,
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn test_retention_and_filters() {
+        let log = RequestLog::new(Duration::from_secs(3600), 3);
+        log.push(entry(200, chrono::Duration::hours(2)));
+        log.push(entry(200, chrono::Duration::zero()));
+        log.push(entry(404, chrono::Duration::zero()));
+        log.push(entry(500, chrono::Duration::zero()));
+        log.push(entry(200, chrono::Duration::zero()));
+
+        let all = RequestLogQuery {
+            route: None,
+            status: None,
+            min_status: None,
+            trace_id: None,
+            limit: None,
+        };
+        let statuses: Vec<u16> = log.search(&all).iter().map(|e| e.status).collect();
+        assert_eq!(statuses, vec![200, 500, 404]);
+
+        let errors = RequestLogQuery {
+            min_status: Some(400),
+            limit: Some(1),
+            ..all
+        };
+        let statuses: Vec<u16> = log.search(&errors).iter().map(|e| e.status).collect();
+        assert_eq!(statuses, vec![500]);
+
+        let forever = RequestLog::new(crate::duration::parse_duration("99999999d").unwrap(), 3);
+        forever.push(entry(200, chrono::Duration::days(10_000)));
+        forever.push(entry(200, chrono::Duration::zero()));
+        assert_eq!(forever.entries.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_error_message_is_extracted_and_truncated() {
+        assert_eq!(
+            error_message(br#"{"error":"Unknown city","code":404}"#),
+            "Unknown city"
+        );
+        assert_eq!(error_message(&[b'x'; 500]).len(), MAX_ERROR_CHARS);
+    }
+
+    #[tokio::test]
+    async fn test_large_error_bodies_pass_through() {
+        use crate::cities::{CityDatabase, CityStore};
+        use crate::config::ServerConfig;
+        use axum::{http::StatusCode, middleware, routing::get, Router};
+        use clap::Parser;
+        use std::sync::Arc;
+        use tower::ServiceExt;
+
+        let config = ServerConfig::parse_from(["server", "--request-log"]);
+        let cities = Arc::new(CityStore::new(CityDatabase::builtin()));
+        let state = AppState::new(config, cities, None);
+        let large = "x".repeat(MAX_ERROR_BODY_BYTES + 1);
+        let app = Router::new()
+            .route(
+                "/large",
+                get(move || async move { (StatusCode::BAD_GATEWAY, large) }),
+            )
+            .layer(middleware::from_fn_with_state(state.clone(), record));
+
+        let request = Request::get("/large").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.len(), MAX_ERROR_BODY_BYTES + 1);
+
+        let log = state.request_log.unwrap();
+        let entries = log.search(&RequestLogQuery {
+            route: None,
+            status: None,
+            min_status: None,
+            trace_id: None,
+            limit: None,
+        });
+        assert_eq!(entries[0].status, 502);
+        assert_eq!(entries[0].error, None);
+    }
+}
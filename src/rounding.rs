@@ -0,0 +1,84 @@
+use serde::Serialize;
+
+/// How ties are broken when rounding temperatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    /// 2.25 → 2.3 and -2.25 → -2.3
+    #[default]
+    HalfAwayFromZero,
+    /// Bankers rounding: 2.25 → 2.2 and 2.35 → 2.4, so ties do not drift
+    /// averages upwards
+    #[value(alias = "bankers")]
+    HalfEven,
+}
+
+/// Precision and tie-breaking of every temperature the server computes:
+/// `/stats` and `/weather` summary averages, climate normals, anomaly means
+/// and simulated readings (always whole degrees)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TemperatureRounding {
+    /// Decimal places
+    pub decimals: u8,
+    pub mode: RoundingMode,
+}
+
+impl Default for TemperatureRounding {
+    fn default() -> Self {
+        TemperatureRounding {
+            decimals: 1,
+            mode: RoundingMode::HalfAwayFromZero,
+        }
+    }
+}
+
+impl TemperatureRounding {
+    pub fn round(self, value: f64) -> f64 {
+        let scale = 10f64.powi(self.decimals as i32);
+        round_with(value * scale, self.mode) / scale
+    }
+
+    /// Round to whole degrees with this mode, whatever the precision
+    pub fn whole(self, value: f64) -> i32 {
+        round_with(value, self.mode) as i32
+    }
+}
+
+fn round_with(value: f64, mode: RoundingMode) -> f64 {
+    // Averages such as 41 / 20 * 10 land a hair off the tie they stand for
+    // (20.499999999999996); snap those back before breaking the tie
+    let snapped = (value * 1e9).round() / 1e9;
+    match mode {
+        RoundingMode::HalfAwayFromZero => snapped.round(),
+        RoundingMode::HalfEven => snapped.round_ties_even(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_modes_and_precision() {
+        let away = TemperatureRounding::default();
+        let even = TemperatureRounding {
+            decimals: 1,
+            mode: RoundingMode::HalfEven,
+        };
+        assert_eq!(away.round(41.0 / 20.0), 2.1);
+        assert_eq!(even.round(41.0 / 20.0), 2.0);
+        assert_eq!(away.round(-2.25), -2.3);
+        assert_eq!(even.round(-2.25), -2.2);
+        assert_eq!(even.round(2.35), 2.4);
+        assert_eq!(even.round(2.26), 2.3);
+
+        let integer = TemperatureRounding {
+            decimals: 0,
+            mode: RoundingMode::HalfEven,
+        };
+        assert_eq!(integer.round(12.5), 12.0);
+        assert_eq!(integer.round(13.5), 14.0);
+        assert_eq!(away.whole(12.5), 13);
+        assert_eq!(integer.whole(12.5), 12);
+    }
+}
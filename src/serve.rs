@@ -0,0 +1,134 @@
+use axum::extract::{ConnectInfo, Request};
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::Semaphore;
+use tower::ServiceExt;
+
+use crate::config::ServerConfig;
+
+/// Connection handling settings, from the server configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    /// Connections served at once; further clients wait in the TCP backlog
+    pub max_connections: usize,
+    /// How long an idle keep-alive connection may wait for its next request
+    pub keep_alive_timeout: Duration,
+    /// Pending connections the kernel queues before refusing new ones
+    pub backlog: u32,
+}
+
+impl ConnectionLimits {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        ConnectionLimits {
+            max_connections: config.max_connections.get(),
+            keep_alive_timeout: config.keep_alive_timeout,
+            backlog: config.tcp_backlog,
+        }
+    }
+}
+
+/// Live connection counters, reported by `GET /metrics`
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    active: AtomicUsize,
+    total: AtomicU64,
+    max: AtomicUsize,
+}
+
+impl ConnectionStats {
+    /// Currently open connections
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Connections accepted since startup
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Configured connection limit
+    pub fn max(&self) -> usize {
+        self.max.load(Ordering::Relaxed)
+    }
+}
+
+/// Decrements the active count when a connection ends, however it ends
+struct ActiveConnection(Arc<ConnectionStats>);
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Bind a listener with an explicit TCP backlog
+pub fn bind(addr: SocketAddr, backlog: u32) -> std::io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(backlog)
+}
+
+/// Accept loop serving `app` under the given connection limits
+///
+/// Replaces `axum::serve`, which has no knobs for connection counts or
+/// keep-alive.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    limits: ConnectionLimits,
+    stats: Arc<ConnectionStats>,
+) {
+    stats.max.store(limits.max_connections, Ordering::Relaxed);
+    let slots = Arc::new(Semaphore::new(limits.max_connections));
+
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(limits.keep_alive_timeout);
+
+    loop {
+        // Wait for a free slot before accepting, so excess clients queue in the backlog
+        let Ok(permit) = slots.clone().acquire_owned().await else {
+            return;
+        };
+        let (stream, remote) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                eprintln!("⚠️  Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        stats.active.fetch_add(1, Ordering::Relaxed);
+        stats.total.fetch_add(1, Ordering::Relaxed);
+        let active = ActiveConnection(stats.clone());
+
+        let builder = builder.clone();
+        // Make the peer address available to handlers as `ConnectInfo<SocketAddr>`
+        let service =
+            TowerToHyperService::new(app.clone().map_request(move |mut request: Request<_>| {
+                request.extensions_mut().insert(ConnectInfo(remote));
+                request
+            }));
+        tokio::spawn(async move {
+            let _ = builder
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await;
+            drop(active);
+            drop(permit);
+        });
+    }
+}
@@ -0,0 +1,306 @@
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Path, Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::RwLock;
+
+use crate::{error_response, AppState, ErrorResponse};
+
+/// Header carrying the signatures of webhook deliveries and (with `--sign-mcp`)
+/// MCP tool results: `t=<unix time>,<key id>=<hex HMAC-SHA256>,...`
+pub const SIGNATURE_HEADER: &str = "x-weather-signature";
+/// Most keys signing at once, so rotations are finished by retiring old keys
+const MAX_SIGNING_KEYS: usize = 5;
+/// Shortest secret accepted from the admin API
+const MIN_SECRET_LEN: usize = 16;
+/// Largest response body the MCP signing middleware reads
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+type SigningError = (StatusCode, Json<ErrorResponse>);
+
+#[derive(Debug, Clone)]
+struct SigningKey {
+    id: String,
+    secret: String,
+    created_at: DateTime<Utc>,
+}
+
+/// A signing key as listed by the admin API, without its secret
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    id: String,
+    created_at: DateTime<Utc>,
+    /// The newest key; the others still sign until they are retired
+    primary: bool,
+}
+
+#[derive(Debug, Default)]
+struct KeyRing {
+    /// Oldest first
+    keys: Vec<SigningKey>,
+    /// Keys issued so far, for ids that are never reused
+    issued: usize,
+}
+
+impl KeyRing {
+    fn add(&mut self, secret: String, now: DateTime<Utc>) -> SigningKey {
+        self.issued += 1;
+        let key = SigningKey {
+            id: format!("k{}", self.issued),
+            secret,
+            created_at: now,
+        };
+        self.keys.push(key.clone());
+        key
+    }
+}
+
+/// HMAC keys for outgoing payloads
+///
+/// Every key signs, so receivers keep verifying while they switch to a new
+/// secret: rotate (add a key), update the receivers, then retire the old key.
+/// Keys added through the admin API are kept in memory only.
+#[derive(Debug, Default)]
+pub struct SigningKeys {
+    ring: RwLock<KeyRing>,
+}
+
+impl SigningKeys {
+    /// Keys starting with `--signing-secret`, if set; nothing is signed without keys
+    pub fn new(initial_secret: Option<&str>) -> Self {
+        let mut ring = KeyRing::default();
+        if let Some(secret) = initial_secret {
+            ring.add(secret.to_string(), Utc::now());
+        }
+        SigningKeys {
+            ring: RwLock::new(ring),
+        }
+    }
+
+    /// The signature header value for `body` sent at `at`, newest key first
+    pub fn sign(&self, body: &[u8], at: DateTime<Utc>) -> Option<String> {
+        let ring = self.ring.read().unwrap_or_else(|e| e.into_inner());
+        if ring.keys.is_empty() {
+            return None;
+        }
+        let timestamp = at.timestamp();
+        let mut header = format!("t={}", timestamp);
+        for key in ring.keys.iter().rev() {
+            header.push_str(&format!(
+                ",{}={}",
+                key.id,
+                signature(&key.secret, timestamp, body)
+            ));
+        }
+        Some(header)
+    }
+
+    pub fn list(&self) -> Vec<KeyInfo> {
+        let ring = self.ring.read().unwrap_or_else(|e| e.into_inner());
+        let newest = ring.keys.len().saturating_sub(1);
+        ring.keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| KeyInfo {
+                id: key.id.clone(),
+                created_at: key.created_at,
+                primary: i == newest,
+            })
+            .collect()
+    }
+
+    fn rotate(&self, secret: String, now: DateTime<Utc>) -> Result<SigningKey, SigningError> {
+        let mut ring = self.ring.write().unwrap_or_else(|e| e.into_inner());
+        if ring.keys.len() == MAX_SIGNING_KEYS {
+            return Err(error_response(
+                StatusCode::CONFLICT,
+                format!(
+                    "Already {} signing keys; retire one before adding another",
+                    MAX_SIGNING_KEYS
+                ),
+            ));
+        }
+        Ok(ring.add(secret, now))
+    }
+
+    /// Stop signing with a key; false if there is no such key
+    fn retire(&self, id: &str) -> bool {
+        let mut ring = self.ring.write().unwrap_or_else(|e| e.into_inner());
+        let before = ring.keys.len();
+        ring.keys.retain(|key| key.id != id);
+        ring.keys.len() != before
+    }
+}
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>`
+fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 32 random bytes from the OS, hex-encoded
+fn generate_secret() -> Result<String, SigningError> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Cannot generate a secret: {}", e),
+        )
+    })?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Middleware adding [`SIGNATURE_HEADER`] to responses, for `--sign-mcp`
+pub async fn sign_response(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = next.run(request).await.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Response body too large").into_response();
+    };
+    if let Some(signature) = state.signing.sign(&bytes, Utc::now()) {
+        if let Ok(value) = HeaderValue::from_str(&signature) {
+            parts.headers.insert(SIGNATURE_HEADER, value);
+        }
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotateRequest {
+    /// Generated when not given
+    secret: Option<String>,
+}
+
+/// List the signing keys (never their secrets)
+pub async fn list_keys(State(state): State<AppState>) -> Json<serde_json::Value> {
+    println!("🛠️  Admin: signing keys");
+
+    let keys = state.signing.list();
+    Json(serde_json::json!({
+        "count": keys.len(),
+        "keys": keys
+    }))
+}
+
+/// Add a signing key, which becomes the primary one
+///
+/// The secret is returned once, in this response.
+pub async fn rotate_key(
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), SigningError> {
+    // The body is optional, so it is parsed here rather than by a `Json` extractor
+    let payload: RotateRequest = if body.is_empty() {
+        RotateRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| {
+            error_response(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid request body: {}", e),
+            )
+        })?
+    };
+    let secret = match payload.secret {
+        Some(secret) if secret.len() < MIN_SECRET_LEN => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("secret must be at least {} characters", MIN_SECRET_LEN),
+            ));
+        }
+        Some(secret) => secret,
+        None => generate_secret()?,
+    };
+
+    let key = state.signing.rotate(secret, Utc::now())?;
+    println!("🔑 Admin: added signing key {}", key.id);
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "id": key.id,
+            "secret": key.secret,
+            "created_at": key.created_at
+        })),
+    ))
+}
+
+/// Retire a signing key
+pub async fn retire_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, SigningError> {
+    if !state.signing.retire(&id) {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("Unknown signing key '{}'", id),
+        ));
+    }
+    println!("🔑 Admin: retired signing key {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_matches_openssl() {
+        // echo -n '1700000000.{}' | openssl dgst -sha256 -hmac whsec_test
+        assert_eq!(
+            signature("whsec_test", 1_700_000_000, b"{}"),
+            "35495024f4ef3f94e5a93e22221544c4b75e9a42300cd965ab81cb85cd994e91"
+        );
+    }
+
+    #[test]
+    fn test_every_key_signs_until_retired() {
+        let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let keys = SigningKeys::new(None);
+        assert!(keys.sign(b"{}", at).is_none());
+
+        let keys = SigningKeys::new(Some("whsec_test"));
+        let expected = signature("whsec_test", 1_700_000_000, b"{}");
+        assert_eq!(
+            keys.sign(b"{}", at).unwrap(),
+            format!("t=1700000000,k1={}", expected)
+        );
+
+        let new = keys.rotate("whsec_rotated_secret".to_string(), at).unwrap();
+        assert_eq!(new.id, "k2");
+        let header = keys.sign(b"{}", at).unwrap();
+        assert!(header.starts_with("t=1700000000,k2="), "{}", header);
+        assert!(header.ends_with(&format!(",k1={}", expected)), "{}", header);
+
+        assert!(keys.retire("k1"));
+        assert!(!keys.retire("k1"));
+        let listed = keys.list();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].primary);
+        // Ids are never reused
+        assert_eq!(
+            keys.rotate("another_long_secret".to_string(), at)
+                .unwrap()
+                .id,
+            "k3"
+        );
+    }
+}
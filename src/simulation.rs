@@ -0,0 +1,264 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::f64::consts::PI;
+
+/// How strongly each hourly random step carries over into the next hour
+const WALK_PERSISTENCE: f64 = 0.85;
+/// Number of past hourly steps summed into the random walk
+const WALK_HOURS: i64 = 48;
+/// Largest deviation (°C) the random walk may add to the temperature
+const WALK_TEMP_BOUND: f64 = 4.0;
+/// Largest deviation (percentage points) the random walk may add to humidity
+const WALK_HUMIDITY_BOUND: f64 = 12.0;
+/// Largest deviation (km/h) the random walk may add to wind speed
+const WALK_WIND_BOUND: f64 = 8.0;
+
+/// Static description of a city's climate that the simulation varies around
+#[derive(Debug, Clone, Copy)]
+pub struct ClimateProfile {
+    /// Annual mean temperature in °C
+    pub mean_temp: f64,
+    /// Annual mean relative humidity in percent
+    pub mean_humidity: f64,
+    /// Annual mean wind speed in km/h
+    pub mean_wind: f64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl ClimateProfile {
+    /// Half the difference between the warmest and coldest month
+    ///
+    /// Grows with distance from the equator: ~1°C in the tropics,
+    /// up to 15°C in continental high latitudes.
+    pub fn seasonal_amplitude(&self) -> f64 {
+        (self.latitude.abs() * 0.25).clamp(1.0, 15.0)
+    }
+
+    /// Half the difference between the afternoon high and the pre-dawn low
+    ///
+    /// Dry air cools down more at night, so drier cities swing more.
+    pub fn diurnal_amplitude(&self) -> f64 {
+        3.0 + (100.0 - self.mean_humidity).max(0.0) / 10.0
+    }
+
+    /// Typical temperature for a month (1-12): the annual mean shifted by
+    /// the seasonal cycle at mid-month
+    pub fn monthly_mean_temp(&self, month: u32) -> f64 {
+        let mid_month = (month.clamp(1, 12) - 1) as f64 * 365.25 / 12.0 + 15.0;
+        self.mean_temp + seasonal_cycle(self.latitude, mid_month) * self.seasonal_amplitude()
+    }
+}
+
+/// Simulated conditions for one city at one instant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    pub temperature: f64,
+    pub humidity: f64,
+    pub wind_speed: f64,
+    /// mm per hour; only above zero when the simulated humidity reaches 90%
+    pub precipitation: f64,
+}
+
+/// Deterministic weather generator
+///
+/// Readings are a pure function of the seed, the city and the instant:
+/// seasonal and diurnal cycles plus a bounded, mean-reverting random walk.
+/// The same inputs always produce the same weather, while consecutive hours
+/// drift believably.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Simulator {
+    seed: u64,
+}
+
+impl Simulator {
+    pub fn new(seed: u64) -> Self {
+        Simulator { seed }
+    }
+
+    /// Evaluate the simulation for `city` at `at`
+    pub fn reading(&self, city: &str, profile: &ClimateProfile, at: DateTime<Utc>) -> Reading {
+        let city_key = fnv1a(city.as_bytes());
+
+        let seasonal = seasonal_factor(profile.latitude, at) * profile.seasonal_amplitude();
+        let diurnal_phase = diurnal_factor(profile.longitude, at);
+        let diurnal = diurnal_phase * profile.diurnal_amplitude();
+
+        let temp_walk = self.walk(city_key, 1, at) * WALK_TEMP_BOUND;
+        let humidity_walk = self.walk(city_key, 2, at) * WALK_HUMIDITY_BOUND;
+        let wind_walk = self.walk(city_key, 3, at) * WALK_WIND_BOUND;
+
+        // Relative humidity falls as the afternoon warms up
+        let humidity =
+            (profile.mean_humidity - diurnal_phase * 8.0 + humidity_walk).clamp(5.0, 100.0);
+
+        Reading {
+            temperature: profile.mean_temp + seasonal + diurnal + temp_walk,
+            humidity,
+            wind_speed: (profile.mean_wind + wind_walk).max(0.0),
+            precipitation: if humidity >= 90.0 {
+                0.5 + (humidity - 90.0) * 0.5
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Bounded AR(1) random walk in `[-1, 1]`, linearly interpolated between hours
+    fn walk(&self, city_key: u64, channel: u64, at: DateTime<Utc>) -> f64 {
+        let hour = at.timestamp().div_euclid(3600);
+        let fraction = at.timestamp().rem_euclid(3600) as f64 / 3600.0;
+
+        let value_at = |hour: i64| {
+            let mut value = 0.0;
+            let mut weight = 1.0;
+            for k in 0..WALK_HOURS {
+                value += weight * self.step(city_key, channel, hour - k);
+                weight *= WALK_PERSISTENCE;
+            }
+            // Scale to a standard deviation of ~0.4, then bound
+            (value * (1.0 - WALK_PERSISTENCE * WALK_PERSISTENCE).sqrt() * 0.7).clamp(-1.0, 1.0)
+        };
+
+        let current = value_at(hour);
+        current + (value_at(hour + 1) - current) * fraction
+    }
+
+    /// Uniform pseudo-random step in `[-1, 1]` for one city, channel and hour
+    fn step(&self, city_key: u64, channel: u64, hour: i64) -> f64 {
+        let bits = splitmix64(self.seed ^ city_key ^ channel.rotate_left(48) ^ hour as u64);
+        (bits >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+}
+
+/// -1 in mid-winter, +1 in mid-summer (shifted by six months south of the equator)
+fn seasonal_factor(latitude: f64, at: DateTime<Utc>) -> f64 {
+    seasonal_cycle(latitude, at.ordinal0() as f64 + at.hour() as f64 / 24.0)
+}
+
+/// [`seasonal_factor`] for a (fractional) zero-based day of the year
+fn seasonal_cycle(latitude: f64, day: f64) -> f64 {
+    // Warmest around July 20th in the northern hemisphere
+    let factor = (2.0 * PI * (day - 200.0) / 365.25).cos();
+    if latitude < 0.0 {
+        -factor
+    } else {
+        factor
+    }
+}
+
+/// -1 before dawn, +1 in the mid-afternoon, using local solar time
+fn diurnal_factor(longitude: f64, at: DateTime<Utc>) -> f64 {
+    let utc_hours = at.num_seconds_from_midnight() as f64 / 3600.0;
+    let solar_hours = (utc_hours + longitude / 15.0).rem_euclid(24.0);
+    // Warmest around 15:00 local time
+    (2.0 * PI * (solar_hours - 15.0) / 24.0).cos()
+}
+
+/// SplitMix64 finalizer: a well-mixed 64-bit hash of `x`
+pub fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Stable string hash, so simulated data does not change between builds
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const STOCKHOLM: ClimateProfile = ClimateProfile {
+        mean_temp: 15.0,
+        mean_humidity: 75.0,
+        mean_wind: 15.0,
+        latitude: 59.33,
+        longitude: 18.07,
+    };
+
+    const SYDNEY: ClimateProfile = ClimateProfile {
+        mean_temp: 24.0,
+        mean_humidity: 55.0,
+        mean_wind: 14.0,
+        latitude: -33.87,
+        longitude: 151.21,
+    };
+
+    fn at(month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_readings_are_deterministic() {
+        let sim = Simulator::new(42);
+        let a = sim.reading("stockholm", &STOCKHOLM, at(10, 8, 12));
+        let b = sim.reading("stockholm", &STOCKHOLM, at(10, 8, 12));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_seed_changes_the_random_walk() {
+        let differs = (0..24).any(|hour| {
+            let a = Simulator::new(1).reading("stockholm", &STOCKHOLM, at(10, 8, hour));
+            let b = Simulator::new(2).reading("stockholm", &STOCKHOLM, at(10, 8, hour));
+            a != b
+        });
+        assert!(differs, "different seeds should produce different weather");
+    }
+
+    #[test]
+    fn test_seasons_are_opposite_across_hemispheres() {
+        let sim = Simulator::new(0);
+        let summer = sim.reading("stockholm", &STOCKHOLM, at(7, 20, 12));
+        let winter = sim.reading("stockholm", &STOCKHOLM, at(1, 20, 12));
+        assert!(summer.temperature > winter.temperature + 15.0);
+
+        let austral_summer = sim.reading("sydney", &SYDNEY, at(1, 20, 4));
+        let austral_winter = sim.reading("sydney", &SYDNEY, at(7, 20, 4));
+        assert!(austral_summer.temperature > austral_winter.temperature);
+    }
+
+    #[test]
+    fn test_monthly_normals_follow_the_seasons() {
+        let july = STOCKHOLM.monthly_mean_temp(7);
+        let january = STOCKHOLM.monthly_mean_temp(1);
+        assert!(july > STOCKHOLM.mean_temp && january < STOCKHOLM.mean_temp);
+
+        let mean = (1..=12).map(|m| SYDNEY.monthly_mean_temp(m)).sum::<f64>() / 12.0;
+        assert!((mean - SYDNEY.mean_temp).abs() < 0.5);
+        assert!(SYDNEY.monthly_mean_temp(1) > SYDNEY.monthly_mean_temp(7));
+    }
+
+    #[test]
+    fn test_values_stay_bounded() {
+        let sim = Simulator::new(7);
+        let max_swing =
+            STOCKHOLM.seasonal_amplitude() + STOCKHOLM.diurnal_amplitude() + WALK_TEMP_BOUND;
+
+        for hour in 0..24 * 365 {
+            let time = at(1, 1, 0) + chrono::Duration::hours(hour);
+            let reading = sim.reading("stockholm", &STOCKHOLM, time);
+            assert!((reading.temperature - STOCKHOLM.mean_temp).abs() <= max_swing + 1e-9);
+            assert!((5.0..=100.0).contains(&reading.humidity));
+            assert!(reading.wind_speed >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_consecutive_minutes_change_smoothly() {
+        let sim = Simulator::new(3);
+        let a = sim.reading("stockholm", &STOCKHOLM, at(3, 1, 10));
+        let b = sim.reading(
+            "stockholm",
+            &STOCKHOLM,
+            at(3, 1, 10) + chrono::Duration::minutes(1),
+        );
+        assert!((a.temperature - b.temperature).abs() < 0.5);
+    }
+}
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::alerts::AlertEngine;
+use crate::analytics::CityAnalytics;
+use crate::anomalies::AnomalyDetector;
+use crate::api_keys::ApiKeys;
+use crate::cities::{CityDatabase, CityRecord, CityStore};
+use crate::config::ServerConfig;
+use crate::digests::Digests;
+use crate::events::EventHub;
+use crate::favorites::Favorites;
+use crate::history::{Retention, WeatherHistory};
+use crate::idempotency::IdempotencyCache;
+use crate::inference::{ConditionRules, Measurements};
+use crate::mcp_session::McpSessions;
+use crate::request_log::RequestLog;
+use crate::serve::ConnectionStats;
+use crate::signing::SigningKeys;
+use crate::simulation::{ClimateProfile, Simulator};
+use crate::stations::{self, Aggregation};
+use crate::stats_cache::StatsCache;
+use crate::store::{self, Persistence};
+use crate::sync::SyncLog;
+use crate::tenants::TenantCities;
+use crate::warmup::Readiness;
+use crate::webhooks::Webhooks;
+use crate::{Source, WeatherData};
+
+/// Shared application state handed to every handler
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<ServerConfig>,
+    pub started_at: DateTime<Utc>,
+    /// Built-in cities, or the ones loaded from `--cities-file`
+    pub cities: Arc<CityStore>,
+    /// Present when the server runs in simulation mode
+    pub simulator: Option<Simulator>,
+    /// Stored responses for `Idempotency-Key` replays
+    pub idempotency: Arc<IdempotencyCache>,
+    /// Open and accepted connection counts, for `/metrics`
+    pub connections: Arc<ConnectionStats>,
+    /// Recent readings per city, recorded by the background sampler
+    pub history: Arc<WeatherHistory>,
+    /// Precomputed `/stats` responses, used when the simulation is off; a
+    /// tenant's state has its own
+    pub stats_cache: Arc<StatsCache>,
+    /// Latest anomaly analysis over `history`
+    pub anomalies: Arc<AnomalyDetector>,
+    /// Latest `--alert-rule` evaluation over `history`
+    pub alerts: Arc<AlertEngine>,
+    /// How often each city is requested, for `/admin/analytics/cities`
+    pub analytics: Arc<CityAnalytics>,
+    /// Keys and quotas from `--api-keys-file`; requests are not
+    /// authenticated when absent
+    pub api_keys: Option<Arc<ApiKeys>>,
+    /// Favorite cities per API key, for `/me/favorites`
+    pub favorites: Arc<Favorites>,
+    /// Daily webhook digests per API key, for `/me/digest`
+    pub digests: Arc<Digests>,
+    /// Per-city change webhooks, for `/admin/webhooks`
+    pub webhooks: Arc<Webhooks>,
+    /// Where favorites, digests and webhooks are saved after each change
+    pub persistence: Arc<Persistence>,
+    /// MCP sessions and the protocol revision each negotiated
+    pub mcp_sessions: Arc<McpSessions>,
+    /// Recent request summaries, when `--request-log` is on
+    pub request_log: Option<Arc<RequestLog>>,
+    /// HMAC keys for webhook deliveries and MCP results
+    pub signing: Arc<SigningKeys>,
+    /// The API key's own cities when scoped with [`crate::tenants::Scoped`];
+    /// `cities` is then the tenant's view
+    pub tenant: Option<Arc<TenantCities>>,
+    /// Update events published by every data mutation
+    pub events: Arc<EventHub>,
+    /// When each city last changed, for `GET /sync` (the tenant's own log
+    /// when scoped)
+    pub sync: Arc<SyncLog>,
+    /// Whether the `--warm-up` phase has finished, for `GET /ready`
+    pub readiness: Arc<Readiness>,
+    /// Table inferring simulated conditions from the raw measurements
+    pub condition_rules: Arc<ConditionRules>,
+    /// IP geolocation for `/weather/me`, when `--geoip-db` is set
+    #[cfg(feature = "geoip")]
+    pub geoip: Option<Arc<crate::geoip::GeoIp>>,
+}
+
+impl AppState {
+    pub fn new(config: ServerConfig, cities: Arc<CityStore>, api_keys: Option<ApiKeys>) -> Self {
+        let simulator = config.simulate.then(|| Simulator::new(config.seed));
+        let idempotency = IdempotencyCache::new(Duration::from_secs(config.idempotency_ttl));
+        let history =
+            WeatherHistory::new(config.history_len.get(), Retention::from_config(&config));
+        let anomalies = AnomalyDetector::new(config.anomaly_threshold, config.rounding());
+        let alerts = AlertEngine::new(config.alert_rules.clone());
+        let signing = SigningKeys::new(config.signing_secret.as_deref());
+        let readiness = Readiness::new(!config.warm_up);
+        let persistence = Persistence::new(store::open(&config));
+        let started_at = Utc::now();
+        let events = EventHub::new(
+            config.event_buffer.get(),
+            config.event_queue.get(),
+            config.slow_subscriber_policy,
+        );
+        let request_log = config.request_log.then(|| {
+            RequestLog::new(
+                config.request_log_retention,
+                config.request_log_max_entries.get(),
+            )
+        });
+
+        AppState {
+            config: Arc::new(config),
+            started_at,
+            cities,
+            simulator,
+            idempotency: Arc::new(idempotency),
+            connections: Arc::new(ConnectionStats::default()),
+            history: Arc::new(history),
+            stats_cache: Arc::new(StatsCache::new()),
+            anomalies: Arc::new(anomalies),
+            alerts: Arc::new(alerts),
+            analytics: Arc::new(CityAnalytics::new()),
+            api_keys: api_keys.map(Arc::new),
+            favorites: Arc::new(Favorites::new()),
+            digests: Arc::new(Digests::new()),
+            webhooks: Arc::new(Webhooks::new()),
+            persistence: Arc::new(persistence),
+            mcp_sessions: Arc::new(McpSessions::new()),
+            request_log: request_log.map(Arc::new),
+            signing: Arc::new(signing),
+            tenant: None,
+            events: Arc::new(events),
+            sync: Arc::new(SyncLog::new(started_at)),
+            readiness: Arc::new(readiness),
+            condition_rules: Arc::new(ConditionRules::default()),
+            #[cfg(feature = "geoip")]
+            geoip: None,
+        }
+    }
+
+    /// This state as seen by a tenant, whose cities replace the global ones
+    pub fn for_tenant(&self, tenant: Arc<TenantCities>) -> AppState {
+        AppState {
+            cities: tenant.store(&self.cities.current()),
+            sync: tenant.sync.clone(),
+            stats_cache: tenant.stats_cache.clone(),
+            tenant: Some(tenant),
+            ..self.clone()
+        }
+    }
+
+    /// Whether global data about `city` (such as its history) belongs in
+    /// responses for this state's tenant
+    pub fn shares(&self, city: &str) -> bool {
+        self.tenant
+            .as_ref()
+            .is_none_or(|tenant| tenant.shares(city))
+    }
+
+    /// Weather for a city at the given instant, or the default values if the
+    /// city is unknown
+    ///
+    /// `city` is used as given in the response; the lookup is case-insensitive.
+    /// Only the simulation varies with `at`; static data is the same at any time.
+    pub fn weather_or_default(&self, city: &str, at: DateTime<Utc>) -> WeatherData {
+        let city_lower = city.to_lowercase();
+        let cities = self.cities.current();
+        match cities.get(&city_lower) {
+            Some(record) => self.weather(&city_lower, Cow::Owned(city.to_string()), record, at),
+            None => WeatherData {
+                observed_at: at,
+                ..WeatherData::unknown(city)
+            },
+        }
+    }
+
+    /// Count a client's request for `city` in the usage analytics
+    pub fn count_request(&self, city: &str) {
+        let city_lower = city.to_lowercase();
+        let known = self.cities.current().get(&city_lower).is_some();
+        self.analytics
+            .record(known.then_some(city_lower.as_str()), Utc::now());
+    }
+
+    /// Current weather for every known city, named by their database key
+    pub fn all_weather(&self) -> Vec<WeatherData> {
+        self.all_weather_in(&self.cities.current(), Utc::now())
+    }
+
+    /// Weather at the given instant for every city of a database snapshot
+    pub fn all_weather_in(&self, cities: &CityDatabase, at: DateTime<Utc>) -> Vec<WeatherData> {
+        cities
+            .keyed()
+            .map(|(key, record)| self.weather(key, key.clone(), record, at))
+            .collect()
+    }
+
+    /// How a city's stations are aggregated
+    pub fn station_aggregation(&self, record: &CityRecord) -> Aggregation {
+        record
+            .aggregation
+            .unwrap_or(self.config.station_aggregation)
+    }
+
+    /// Weather for a known city (lowercase `key`) at the given instant,
+    /// reported under `name`
+    ///
+    /// A city with stations reports the aggregate of their readings.
+    pub fn weather(
+        &self,
+        key: &str,
+        name: Cow<'static, str>,
+        record: &CityRecord,
+        at: DateTime<Utc>,
+    ) -> WeatherData {
+        let static_data = WeatherData {
+            city: name,
+            temperature: record.temperature,
+            condition: record.condition,
+            description: record.description.clone(),
+            icon: record.condition.icon(),
+            humidity: record.humidity,
+            wind_speed: record.wind_speed,
+            source: Source::Static,
+            observed_at: at,
+        };
+
+        let readings: Vec<_> = stations::readings(self.simulator.as_ref(), key, record, at)
+            .into_iter()
+            .map(|(_, reading)| reading)
+            .collect();
+        let stations = self.station_aggregation(record).combine(&readings);
+
+        let (Some(simulator), Some((latitude, longitude))) =
+            (&self.simulator, record.coordinates())
+        else {
+            return match stations {
+                Some(reading) => WeatherData {
+                    temperature: self.config.rounding().whole(reading.temperature),
+                    humidity: reading.humidity.round() as i32,
+                    wind_speed: reading.wind_speed.round() as i32,
+                    ..static_data
+                },
+                None => static_data,
+            };
+        };
+
+        let reading = stations.unwrap_or_else(|| {
+            // The static values act as the city's climate normals
+            let profile = ClimateProfile {
+                mean_temp: record.temperature as f64,
+                mean_humidity: record.humidity as f64,
+                mean_wind: record.wind_speed as f64,
+                latitude,
+                longitude,
+            };
+            simulator.reading(key, &profile, at)
+        });
+
+        let measurements = Measurements {
+            temperature: reading.temperature,
+            humidity: reading.humidity,
+            wind_speed: reading.wind_speed,
+            precipitation: Some(reading.precipitation),
+            cloud_cover: None,
+        };
+        let (condition, description) = match self.condition_rules.infer(&measurements) {
+            Some(rule) => (rule.condition, rule.description.clone().map(Cow::Owned)),
+            None => (static_data.condition, static_data.description),
+        };
+
+        WeatherData {
+            city: static_data.city,
+            temperature: self.config.rounding().whole(reading.temperature),
+            condition,
+            description,
+            icon: condition.icon(),
+            humidity: reading.humidity.round() as i32,
+            wind_speed: reading.wind_speed.round() as i32,
+            source: Source::Simulated,
+            observed_at: at,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use axum::{extract::Path, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::cities::CityRecord;
+use crate::simulation::{ClimateProfile, Reading, Simulator};
+use crate::tenants::Scoped;
+use crate::ErrorResponse;
+
+/// Most stations one city can have
+pub const MAX_STATIONS: usize = 50;
+
+/// How a city's reading is derived from its stations' readings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    /// Average of every station
+    #[default]
+    Mean,
+    /// Middle value of each measurement, so one faulty station does not
+    /// move the city's reading
+    Median,
+    /// The first station listed
+    Primary,
+}
+
+impl Aggregation {
+    /// One reading from every station's; `None` without stations
+    pub fn combine(self, readings: &[Reading]) -> Option<Reading> {
+        let combine = |value: fn(&Reading) -> f64| {
+            let mut values: Vec<f64> = readings.iter().map(value).collect();
+            match self {
+                Aggregation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+                Aggregation::Median => {
+                    values.sort_by(f64::total_cmp);
+                    let middle = values.len() / 2;
+                    if values.len().is_multiple_of(2) {
+                        (values[middle - 1] + values[middle]) / 2.0
+                    } else {
+                        values[middle]
+                    }
+                }
+                Aggregation::Primary => values[0],
+            }
+        };
+        if readings.is_empty() {
+            return None;
+        }
+        Some(Reading {
+            temperature: combine(|r| r.temperature),
+            humidity: combine(|r| r.humidity),
+            wind_speed: combine(|r| r.wind_speed),
+            precipitation: combine(|r| r.precipitation),
+        })
+    }
+}
+
+/// One weather station of a city, with its own values
+///
+/// The values play the part of the city's for the station: its static
+/// reading, and what the simulation varies around.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Station {
+    /// Unique within the city, e.g. `airport`
+    pub id: String,
+    /// Temperature in °C
+    pub temperature: i32,
+    /// Relative humidity in percent (0-100)
+    pub humidity: i32,
+    /// Wind speed in km/h
+    pub wind_speed: i32,
+    /// Where the station stands; the city's coordinates when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+}
+
+/// Each station of a city with its reading at `at`
+///
+/// Stations are simulated like cities (around their own values, at their
+/// own coordinates) when `simulator` is given and the city has coordinates;
+/// otherwise their static values are used.
+pub fn readings<'a>(
+    simulator: Option<&Simulator>,
+    key: &str,
+    record: &'a CityRecord,
+    at: DateTime<Utc>,
+) -> Vec<(&'a Station, Reading)> {
+    let simulated = simulator.zip(record.coordinates());
+    record
+        .stations
+        .iter()
+        .map(|station| {
+            let reading = match simulated {
+                Some((simulator, (latitude, longitude))) => {
+                    let profile = ClimateProfile {
+                        mean_temp: station.temperature as f64,
+                        mean_humidity: station.humidity as f64,
+                        mean_wind: station.wind_speed as f64,
+                        latitude: station.latitude.unwrap_or(latitude),
+                        longitude: station.longitude.unwrap_or(longitude),
+                    };
+                    // Keyed per station, so each follows its own random walk
+                    simulator.reading(&format!("{}#{}", key, station.id), &profile, at)
+                }
+                None => Reading {
+                    temperature: station.temperature as f64,
+                    humidity: station.humidity as f64,
+                    wind_speed: station.wind_speed as f64,
+                    precipitation: 0.0,
+                },
+            };
+            (station, reading)
+        })
+        .collect()
+}
+
+/// One station's reading in a response, rounded like city readings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StationReading {
+    pub id: String,
+    pub temperature: i32,
+    pub humidity: i32,
+    pub wind_speed: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+}
+
+/// A city's stations with their current readings, next to the city reading
+/// they are aggregated into
+pub async fn get_stations(
+    Scoped(state): Scoped,
+    Path(city): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let key = city.trim().to_lowercase();
+    println!("📡 Received station readings request for {}", key);
+
+    let database = state.cities.current();
+    let record = database.get(&key).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Unknown city '{}'", city),
+                code: 404,
+            }),
+        )
+    })?;
+
+    let now = Utc::now();
+    let rounding = state.config.rounding();
+    let stations: Vec<StationReading> = readings(state.simulator.as_ref(), &key, record, now)
+        .into_iter()
+        .map(|(station, reading)| StationReading {
+            id: station.id.clone(),
+            temperature: rounding.whole(reading.temperature),
+            humidity: reading.humidity.round() as i32,
+            wind_speed: reading.wind_speed.round() as i32,
+            latitude: station.latitude.or(record.latitude),
+            longitude: station.longitude.or(record.longitude),
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "city": key,
+        "aggregation": state.station_aggregation(record),
+        "count": stations.len(),
+        "stations": stations,
+        "weather": state.weather(&key, key.clone().into(), record, now)
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(temperature: f64, humidity: f64) -> Reading {
+        Reading {
+            temperature,
+            humidity,
+            wind_speed: 10.0,
+            precipitation: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_aggregation_policies() {
+        let readings = [reading(4.0, 80.0), reading(30.0, 40.0), reading(5.0, 70.0)];
+        let mean = Aggregation::Mean.combine(&readings).unwrap();
+        assert_eq!(mean.temperature, 13.0);
+        assert_eq!(mean.humidity, 190.0 / 3.0);
+        // The faulty 30° station does not move the median
+        let median = Aggregation::Median.combine(&readings).unwrap();
+        assert_eq!(median.temperature, 5.0);
+        assert_eq!(median.humidity, 70.0);
+        let even = Aggregation::Median.combine(&readings[..2]).unwrap();
+        assert_eq!(even.temperature, 17.0);
+        assert_eq!(Aggregation::Primary.combine(&readings), Some(readings[0]));
+        assert_eq!(Aggregation::Mean.combine(&[]), None);
+    }
+}
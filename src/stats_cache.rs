@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::cities::CityDatabase;
+use crate::StatsResponse;
+
+/// `/stats` responses computed once per city database and sort order
+///
+/// Only used for static data: without the simulation, readings change only
+/// when the database is replaced (reload or admin edit), so a response stays
+/// valid until then. Entries for an older database are dropped on first use
+/// of the new one.
+#[derive(Debug, Default)]
+pub struct StatsCache {
+    entries: Mutex<Option<CachedStats>>,
+}
+
+#[derive(Debug)]
+struct CachedStats {
+    /// Kept alive so a new database can never reuse its address
+    database: Arc<CityDatabase>,
+    by_sort: HashMap<&'static str, Arc<StatsResponse>>,
+}
+
+impl StatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached response for `database` and `sort`, computing it on a miss
+    ///
+    /// The lock is held while computing, so concurrent misses compute once.
+    pub fn get_or_compute(
+        &self,
+        database: &Arc<CityDatabase>,
+        sort: &'static str,
+        compute: impl FnOnce() -> StatsResponse,
+    ) -> Arc<StatsResponse> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries
+            .as_ref()
+            .is_some_and(|cached| !Arc::ptr_eq(&cached.database, database))
+        {
+            *entries = None;
+        }
+        entries
+            .get_or_insert_with(|| CachedStats {
+                database: database.clone(),
+                by_sort: HashMap::new(),
+            })
+            .by_sort
+            .entry(sort)
+            .or_insert_with(|| Arc::new(compute()))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(total_cities: usize) -> StatsResponse {
+        StatsResponse {
+            total_cities,
+            average_temp: None,
+            hottest_city: None,
+            coldest_city: None,
+            average_humidity: None,
+            most_humid_city: None,
+            driest_city: None,
+            average_wind_speed: None,
+            windiest_city: None,
+            calmest_city: None,
+            cities: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_stats_are_reused_until_the_database_changes() {
+        let cache = StatsCache::new();
+        let database = Arc::new(CityDatabase::builtin());
+
+        let first = cache.get_or_compute(&database, "name", || stats(1));
+        let again = cache.get_or_compute(&database, "name", || stats(2));
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(
+            cache
+                .get_or_compute(&database, "wind", || stats(3))
+                .total_cities,
+            3
+        );
+
+        let reloaded = Arc::new(CityDatabase::builtin());
+        assert_eq!(
+            cache
+                .get_or_compute(&reloaded, "name", || stats(4))
+                .total_cities,
+            4
+        );
+    }
+}
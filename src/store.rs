@@ -0,0 +1,293 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::config::ServerConfig;
+use crate::locale::{Language, Units};
+use crate::state::AppState;
+
+/// Version of the `--data-file` format, checked on load
+const FORMAT_VERSION: u32 = 1;
+
+/// A daily digest as saved, without its delivery state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedDigest {
+    pub cities: Vec<String>,
+    pub time: NaiveTime,
+    pub timezone: Tz,
+    pub webhook_url: String,
+    pub language: Language,
+    pub units: Units,
+    pub month_first: bool,
+}
+
+/// A city webhook as saved, without its delivery state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedWebhook {
+    pub id: String,
+    pub url: String,
+    pub cities: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Configuration created through the API: favorites, digests and webhooks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Saved {
+    /// Favorite cities per API key name
+    #[serde(default)]
+    pub favorites: HashMap<String, Vec<String>>,
+    /// Digest per API key name
+    #[serde(default)]
+    pub digests: HashMap<String, SavedDigest>,
+    #[serde(default)]
+    pub webhooks: Vec<SavedWebhook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedFile {
+    version: u32,
+    #[serde(flatten)]
+    saved: Saved,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    Version(PathBuf, u32),
+    Write(PathBuf, std::io::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            StoreError::Parse(path, e) => {
+                write!(f, "invalid data file {}: {}", path.display(), e)
+            }
+            StoreError::Version(path, version) => write!(
+                f,
+                "{} has format version {}, this server reads version {}",
+                path.display(),
+                version,
+                FORMAT_VERSION
+            ),
+            StoreError::Write(path, e) => write!(f, "cannot write {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+/// Storage backend for the configuration users create through the API
+///
+/// The whole [`Saved`] set is written after every change and read once at
+/// startup, so a backend only needs to store one document.
+pub trait WeatherStore: Send + Sync {
+    /// What was saved last; empty when nothing was saved yet
+    fn load(&self) -> Result<Saved, StoreError>;
+    fn save(&self, saved: &Saved) -> Result<(), StoreError>;
+    /// Where the data is kept, for log lines
+    fn location(&self) -> String;
+}
+
+/// Keeps nothing: configuration is lost on restart (without `--data-file`)
+#[derive(Debug, Default)]
+pub struct MemoryStore;
+
+impl WeatherStore for MemoryStore {
+    fn load(&self) -> Result<Saved, StoreError> {
+        Ok(Saved::default())
+    }
+
+    fn save(&self, _saved: &Saved) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+/// A JSON file, replaced atomically on every save (`--data-file`)
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+}
+
+impl JsonFileStore {
+    pub fn new(path: PathBuf) -> Self {
+        JsonFileStore { path }
+    }
+}
+
+impl WeatherStore for JsonFileStore {
+    fn load(&self) -> Result<Saved, StoreError> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Saved::default()),
+            Err(e) => return Err(StoreError::Read(self.path.clone(), e)),
+        };
+        let file: SavedFile =
+            serde_json::from_str(&contents).map_err(|e| StoreError::Parse(self.path.clone(), e))?;
+        if file.version != FORMAT_VERSION {
+            return Err(StoreError::Version(self.path.clone(), file.version));
+        }
+        Ok(file.saved)
+    }
+
+    fn save(&self, saved: &Saved) -> Result<(), StoreError> {
+        let file = SavedFile {
+            version: FORMAT_VERSION,
+            saved: saved.clone(),
+        };
+        let contents = serde_json::to_vec_pretty(&file).unwrap_or_default();
+        // Write next to the file, flush it to disk and rename, so neither a
+        // crash nor a power loss leaves half a file
+        let partial = self.path.with_extension("json.partial");
+        std::fs::File::create(&partial)
+            .and_then(|mut file| {
+                file.write_all(&contents)?;
+                file.sync_all()
+            })
+            .and_then(|()| std::fs::rename(&partial, &self.path))
+            .map_err(|e| StoreError::Write(self.path.clone(), e))
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// The configured backend: `--data-file`, or memory only
+pub fn open(config: &ServerConfig) -> Box<dyn WeatherStore> {
+    match &config.data_file {
+        Some(path) => Box::new(JsonFileStore::new(path.clone())),
+        None => Box::new(MemoryStore),
+    }
+}
+
+/// Saves the state's favorites, digests and webhooks after each change
+pub struct Persistence {
+    backend: Arc<dyn WeatherStore>,
+    /// Held while collecting and writing, so an older set never overwrites
+    /// a newer one
+    writing: Mutex<()>,
+}
+
+impl Persistence {
+    pub fn new(backend: Box<dyn WeatherStore>) -> Self {
+        Persistence {
+            backend: Arc::from(backend),
+            writing: Mutex::new(()),
+        }
+    }
+
+    pub fn location(&self) -> String {
+        self.backend.location()
+    }
+
+    /// Save the current configuration; a failure is logged and the change
+    /// stays in memory
+    ///
+    /// The backend runs on the blocking thread pool, so a slow disk holds up
+    /// other saves but not the requests around them.
+    pub async fn save(&self, state: &AppState) {
+        let _writing = self.writing.lock().await;
+        let saved = Saved {
+            favorites: state.favorites.all(),
+            digests: state.digests.saved(),
+            webhooks: state.webhooks.saved(),
+        };
+        let backend = self.backend.clone();
+        let result = tokio::task::spawn_blocking(move || backend.save(&saved)).await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("⚠️  Cannot save favorites, digests and webhooks: {}", e),
+            Err(e) => println!("⚠️  Cannot save favorites, digests and webhooks: {}", e),
+        }
+    }
+}
+
+/// Put saved configuration back into a freshly started server
+pub fn rehydrate(state: &AppState, saved: Saved) {
+    state.favorites.restore(saved.favorites);
+    state.digests.restore(saved.digests, Utc::now());
+    let now = Utc::now();
+    let database = state.cities.current();
+    state.webhooks.restore(saved.webhooks, |city| {
+        database
+            .get(city)
+            .map(|_| state.weather_or_default(city, now))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("weather-store-{}.json", std::process::id()));
+        let store = JsonFileStore::new(path.clone());
+        assert_eq!(store.load().unwrap(), Saved::default());
+
+        let mut saved = Saved::default();
+        saved
+            .favorites
+            .insert("mobile-app".to_string(), vec!["oslo".to_string()]);
+        saved.digests.insert(
+            "mobile-app".to_string(),
+            SavedDigest {
+                cities: vec!["rome".to_string()],
+                time: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                timezone: chrono_tz::Europe::Stockholm,
+                webhook_url: "http://localhost/hook".to_string(),
+                language: Language::Swedish,
+                units: Units::Metric,
+                month_first: false,
+            },
+        );
+        saved.webhooks.push(SavedWebhook {
+            id: "wh3".to_string(),
+            url: "http://localhost/purge".to_string(),
+            cities: vec!["paris".to_string()],
+            created_at: Utc::now(),
+        });
+        store.save(&saved).unwrap();
+        assert_eq!(store.load().unwrap(), saved);
+
+        std::fs::write(&path, r#"{"version": 2}"#).unwrap();
+        assert!(matches!(store.load(), Err(StoreError::Version(_, 2))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_is_saved_off_the_runtime() {
+        use crate::cities::{CityDatabase, CityStore};
+        use clap::Parser;
+
+        let path = std::env::temp_dir().join(format!("weather-saved-{}.json", std::process::id()));
+        let config = ServerConfig::parse_from(["server", "--data-file", path.to_str().unwrap()]);
+        let state = AppState::new(
+            config,
+            std::sync::Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let favorites = HashMap::from([("mobile-app".to_string(), vec!["oslo".to_string()])]);
+        state.favorites.restore(favorites.clone());
+        state.persistence.save(&state).await;
+
+        let saved = JsonFileStore::new(path.clone()).load().unwrap();
+        assert_eq!(saved.favorites, favorites);
+        assert!(!path.with_extension("json.partial").exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::conditions::Condition;
+use crate::history::{CityTiers, Rollup};
+use crate::tenants::Scoped;
+use crate::ErrorResponse;
+
+/// Longest streaks returned by default
+const DEFAULT_LIMIT: usize = 5;
+const MAX_LIMIT: usize = 50;
+
+/// A stretch of the history with one condition throughout
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Streak {
+    pub condition: Condition,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub hours: f64,
+    /// e.g. "Rain for 14 hours"
+    pub summary: String,
+}
+
+impl Streak {
+    fn new(condition: Condition, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        let length = until - since;
+        Streak {
+            condition,
+            since,
+            until,
+            hours: (length.num_minutes() as f64 / 6.0).round() / 10.0,
+            summary: format!("{} for {}", condition.label(), describe(length)),
+        }
+    }
+}
+
+/// "35 minutes", "14 hours" or "3 days"
+fn describe(length: TimeDelta) -> String {
+    let (count, unit) = if length < TimeDelta::hours(1) {
+        (length.num_minutes(), "minute")
+    } else if length < TimeDelta::hours(48) {
+        (length.num_hours(), "hour")
+    } else {
+        (length.num_days(), "day")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Every streak in a city's history, oldest first
+///
+/// Days and hours whose condition changed within them (`None`) end a streak,
+/// so streaks reaching into compacted history are accurate to the hour or
+/// day. Returns whether the last streak is still going on.
+fn streaks(tiers: &CityTiers) -> (Vec<Streak>, bool) {
+    let rollups = |rollups: &[Rollup], period: TimeDelta| {
+        rollups
+            .iter()
+            .map(move |r| (r.start, r.start + period, r.condition))
+            .collect::<Vec<_>>()
+    };
+    let spans = rollups(&tiers.daily, TimeDelta::days(1))
+        .into_iter()
+        .chain(rollups(&tiers.hourly, TimeDelta::hours(1)))
+        .chain(tiers.raw.iter().map(|s| (s.at, s.at, Some(s.condition))));
+
+    let mut done = Vec::new();
+    let mut open: Option<(Condition, DateTime<Utc>, DateTime<Utc>)> = None;
+    for (from, to, condition) in spans {
+        match (open.as_mut(), condition) {
+            (Some((current, _, until)), Some(condition)) if *current == condition => {
+                *until = (*until).max(to);
+                continue;
+            }
+            _ => {}
+        }
+        if let Some((condition, since, until)) = open.take() {
+            done.push(Streak::new(condition, since, until));
+        }
+        open = condition.map(|condition| (condition, from, to));
+    }
+    let ongoing = open.is_some();
+    if let Some((condition, since, until)) = open {
+        done.push(Streak::new(condition, since, until));
+    }
+    (done, ongoing)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreakQuery {
+    /// Longest streaks to return (default 5)
+    limit: Option<usize>,
+}
+
+/// How long a city's current condition has lasted, and its longest streaks
+///
+/// Measured over the recorded history (see `GET /history/{city}`), so a
+/// streak cannot be longer than what the server has recorded.
+pub async fn get_streak(
+    Scoped(state): Scoped,
+    Path(city): Path<String>,
+    Query(query): Query<StreakQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| {
+        (
+            status,
+            Json(ErrorResponse {
+                error,
+                code: status.as_u16(),
+            }),
+        )
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid limit {}: expected 1 to {}", limit, MAX_LIMIT),
+        ));
+    }
+
+    let key = city.trim().to_lowercase();
+    println!("⏱️  Received condition streak request for {}", key);
+
+    // The history covers global cities only
+    let tiers = state
+        .shares(&key)
+        .then(|| state.history.tiers(&key))
+        .flatten()
+        .ok_or_else(|| {
+            error(
+                StatusCode::NOT_FOUND,
+                format!("No history recorded for '{}'", city),
+            )
+        })?;
+
+    let (mut streaks, ongoing) = streaks(&tiers);
+    let current = if ongoing {
+        streaks.last().cloned()
+    } else {
+        None
+    };
+    streaks.sort_by(|a, b| {
+        (b.until - b.since)
+            .cmp(&(a.until - a.since))
+            .then(b.since.cmp(&a.since))
+    });
+    streaks.truncate(limit);
+
+    Ok(Json(serde_json::json!({
+        "city": key,
+        "current": current,
+        "longest": streaks
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{Retention, WeatherHistory};
+    use crate::WeatherData;
+
+    #[test]
+    fn test_streaks_span_compacted_history() {
+        use Condition::{Clear, Rain};
+        let retention = Retention {
+            raw: std::time::Duration::from_secs(3 * 3600),
+            ..Retention::default()
+        };
+        let history = WeatherHistory::new(1000, retention);
+        let start = DateTime::parse_from_rfc3339("2026-10-16T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // Clear for 4 hours, then rain for 14 hours, every 30 minutes
+        for step in 0..36 {
+            let condition = if step < 8 { Clear } else { Rain };
+            let reading = WeatherData {
+                condition,
+                ..WeatherData::unknown("oslo")
+            };
+            history.record(&[reading], start + TimeDelta::minutes(30 * step));
+        }
+        history.compact(start + TimeDelta::hours(18));
+
+        let (streaks, ongoing) = streaks(&history.tiers("oslo").unwrap());
+        assert!(ongoing);
+        assert_eq!(streaks.len(), 2);
+        assert_eq!(streaks[0].condition, Clear);
+        assert_eq!(streaks[0].until, start + TimeDelta::hours(4));
+        let rain = &streaks[1];
+        assert_eq!(rain.since, start + TimeDelta::hours(4));
+        assert_eq!(rain.until, start + TimeDelta::minutes(30 * 35));
+        assert_eq!(rain.hours, 13.5);
+        assert_eq!(rain.summary, "Rain for 13 hours");
+
+        assert_eq!(describe(TimeDelta::minutes(1)), "1 minute");
+        assert_eq!(describe(TimeDelta::hours(72)), "3 days");
+    }
+}
@@ -0,0 +1,343 @@
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::bulk::{fnv1a, reading_time};
+use crate::tenants::Scoped;
+use crate::{ErrorResponse, WeatherData};
+
+type SyncError = (StatusCode, Json<ErrorResponse>);
+
+/// Where a client's previous sync left off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Since {
+    /// A `sync_token` from an earlier response
+    Token { epoch: i64, seq: u64 },
+    /// An RFC 3339 timestamp
+    Time(DateTime<Utc>),
+}
+
+impl Since {
+    fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim().trim_matches('"');
+        if let Some((epoch, seq)) = input.split_once('-') {
+            if let (Ok(epoch), Ok(seq)) = (epoch.parse(), seq.parse()) {
+                return Ok(Since::Token { epoch, seq });
+            }
+        }
+        DateTime::parse_from_rfc3339(input)
+            .map(|at| Since::Time(at.with_timezone(&Utc)))
+            .map_err(|_| {
+                format!(
+                    "Invalid 'since' value '{}': expected a sync_token or an RFC 3339 timestamp",
+                    input
+                )
+            })
+    }
+
+    /// Whether a change numbered `seq` at `at` came after this point
+    fn precedes(self, seq: u64, at: DateTime<Utc>) -> bool {
+        match self {
+            Since::Token { seq: since, .. } => seq > since,
+            Since::Time(since) => at > since,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Version {
+    fingerprint: u64,
+    seq: u64,
+    changed_at: DateTime<Utc>,
+}
+
+/// A city that was removed, for clients to delete their copy
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Tombstone {
+    pub city: String,
+    pub removed_at: DateTime<Utc>,
+    #[serde(skip)]
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct Versions {
+    /// Bumped for every change seen
+    seq: u64,
+    cities: HashMap<String, Version>,
+    removed: HashMap<String, Tombstone>,
+}
+
+/// What changed since a client's last sync
+#[derive(Debug, PartialEq)]
+pub struct Changes {
+    /// Everything is listed because `since` predates this log
+    pub full: bool,
+    /// Keys of the changed cities
+    pub changed: Vec<String>,
+    pub removed: Vec<Tombstone>,
+    pub token: String,
+}
+
+/// When each city's data last changed, as seen by `GET /sync`
+///
+/// Versions are recorded when a sync compares the current readings with the
+/// previous ones, so they only exist since the server started: tokens from
+/// another run, or timestamps before the start, get a full sync.
+#[derive(Debug)]
+pub struct SyncLog {
+    started_at: DateTime<Utc>,
+    versions: Mutex<Versions>,
+}
+
+impl SyncLog {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        SyncLog {
+            started_at,
+            versions: Mutex::new(Versions::default()),
+        }
+    }
+
+    /// Record the current `(key, fingerprint)` of every city, then list
+    /// what changed after `since`
+    pub fn sync(
+        &self,
+        current: &[(String, u64)],
+        since: Option<Since>,
+        now: DateTime<Utc>,
+    ) -> Changes {
+        let mut versions = self.versions.lock().unwrap_or_else(|e| e.into_inner());
+        let Versions {
+            seq,
+            cities,
+            removed,
+        } = &mut *versions;
+
+        for (key, fingerprint) in current {
+            match cities.get_mut(key) {
+                Some(version) if version.fingerprint == *fingerprint => {}
+                Some(version) => {
+                    *seq += 1;
+                    *version = Version {
+                        fingerprint: *fingerprint,
+                        seq: *seq,
+                        changed_at: now,
+                    };
+                }
+                None => {
+                    *seq += 1;
+                    removed.remove(key);
+                    cities.insert(
+                        key.clone(),
+                        Version {
+                            fingerprint: *fingerprint,
+                            seq: *seq,
+                            changed_at: now,
+                        },
+                    );
+                }
+            }
+        }
+        if cities.len() > current.len() {
+            let keys: HashSet<&str> = current.iter().map(|(key, _)| key.as_str()).collect();
+            let gone: Vec<String> = cities
+                .keys()
+                .filter(|key| !keys.contains(key.as_str()))
+                .cloned()
+                .collect();
+            for key in gone {
+                cities.remove(&key);
+                *seq += 1;
+                removed.insert(
+                    key.clone(),
+                    Tombstone {
+                        city: key,
+                        removed_at: now,
+                        seq: *seq,
+                    },
+                );
+            }
+        }
+
+        let epoch = self.started_at.timestamp_millis();
+        let since = since.filter(|since| match since {
+            Since::Token { epoch: e, seq: s } => *e == epoch && s <= seq,
+            Since::Time(t) => *t >= self.started_at,
+        });
+
+        let mut changed: Vec<String> = cities
+            .iter()
+            .filter(|(_, v)| since.is_none_or(|since| since.precedes(v.seq, v.changed_at)))
+            .map(|(key, _)| key.clone())
+            .collect();
+        changed.sort_unstable();
+        let mut removed: Vec<Tombstone> = match since {
+            Some(since) => removed
+                .values()
+                .filter(|t| since.precedes(t.seq, t.removed_at))
+                .cloned()
+                .collect(),
+            // A full sync replaces the client's copy, so nothing to delete
+            None => Vec::new(),
+        };
+        removed.sort_by(|a, b| a.city.cmp(&b.city));
+
+        Changes {
+            full: since.is_none(),
+            changed,
+            removed,
+            token: format!("{}-{}", epoch, seq),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    since: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncResponse {
+    /// Pass as `since` (or `If-None-Match`) on the next sync
+    sync_token: String,
+    /// The client should replace its copy rather than merge into it
+    full: bool,
+    changed: Vec<WeatherData>,
+    removed: Vec<Tombstone>,
+}
+
+/// Cities whose weather changed since the client's last sync
+///
+/// `since` is the `sync_token` of the previous response or an RFC 3339
+/// timestamp. The token is also the `ETag`, so `If-None-Match` works in place
+/// of `since` and gets `304 Not Modified` when nothing changed. Removed cities
+/// come back as tombstones. Without `since`, or when it is older than this
+/// server's records, every city is sent with `"full": true`.
+pub async fn get_sync(
+    Scoped(state): Scoped,
+    Query(query): Query<SyncQuery>,
+    headers: HeaderMap,
+) -> Result<Response, SyncError> {
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok());
+    let since = query
+        .since
+        .as_deref()
+        .or(if_none_match)
+        .map(Since::parse)
+        .transpose()
+        .map_err(|error| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse { error, code: 400 }),
+            )
+        })?;
+
+    let now = Utc::now();
+    let at = reading_time(now);
+    let database = state.cities.current();
+    let readings: HashMap<String, WeatherData> = database
+        .keyed()
+        .map(|(key, record)| (key.to_string(), state.weather(key, key.clone(), record, at)))
+        .collect();
+    let current: Vec<(String, u64)> = readings
+        .iter()
+        .map(|(key, weather)| (key.clone(), fingerprint(weather)))
+        .collect();
+
+    let changes = state.sync.sync(&current, since, now);
+    println!(
+        "🔄 Sync: {} changed, {} removed{}",
+        changes.changed.len(),
+        changes.removed.len(),
+        if changes.full { " (full)" } else { "" }
+    );
+
+    let etag = [(header::ETAG, format!("\"{}\"", changes.token))];
+    let unchanged = !changes.full && changes.changed.is_empty() && changes.removed.is_empty();
+    if unchanged && query.since.is_none() && if_none_match.is_some() {
+        return Ok((StatusCode::NOT_MODIFIED, etag).into_response());
+    }
+
+    let mut readings = readings;
+    let changed = changes
+        .changed
+        .iter()
+        .filter_map(|key| readings.remove(key))
+        .collect();
+    Ok((
+        etag,
+        Json(SyncResponse {
+            sync_token: changes.token,
+            full: changes.full,
+            changed,
+            removed: changes.removed,
+        }),
+    )
+        .into_response())
+}
+
+/// Hash of everything a client shows, leaving out the observation time
+fn fingerprint(weather: &WeatherData) -> u64 {
+    let fields = (
+        weather.temperature,
+        weather.condition,
+        &weather.description,
+        weather.humidity,
+        weather.wind_speed,
+        weather.source,
+    );
+    fnv1a(&serde_json::to_vec(&fields).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn city(key: &str, fingerprint: u64) -> (String, u64) {
+        (key.to_string(), fingerprint)
+    }
+
+    #[test]
+    fn test_sync_lists_changes_and_tombstones_since_a_token() {
+        let started = DateTime::parse_from_rfc3339("2026-10-16T18:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let log = SyncLog::new(started);
+        let first = log.sync(&[city("oslo", 1), city("rome", 2)], None, started);
+        assert!(first.full);
+        assert_eq!(first.changed, vec!["oslo", "rome"]);
+        let token = Since::parse(&first.token).unwrap();
+
+        let later = started + chrono::TimeDelta::minutes(1);
+        let second = log.sync(&[city("oslo", 3), city("paris", 4)], Some(token), later);
+        assert!(!second.full);
+        assert_eq!(second.changed, vec!["oslo", "paris"]);
+        assert_eq!(second.removed.len(), 1);
+        assert_eq!(second.removed[0].city, "rome");
+
+        // Nothing new since the last token; timestamps work too
+        let token = Since::parse(&second.token).unwrap();
+        let third = log.sync(&[city("oslo", 3), city("paris", 4)], Some(token), later);
+        assert_eq!((third.changed.len(), third.removed.len()), (0, 0));
+        let since_start = log.sync(
+            &[city("oslo", 3), city("paris", 4)],
+            Some(Since::Time(started)),
+            later,
+        );
+        assert_eq!(since_start.changed, vec!["oslo", "paris"]);
+
+        // Tokens from another run mean a full sync
+        let stale = Since::Token { epoch: 1, seq: 1 };
+        assert!(log.sync(&[city("oslo", 3)], Some(stale), later).full);
+        assert!(Since::parse("yesterday").is_err());
+    }
+}
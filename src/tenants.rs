@@ -0,0 +1,178 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::Utc;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use crate::api_keys::Caller;
+use crate::cities::{CityDatabase, CityStore};
+use crate::stats_cache::StatsCache;
+use crate::sync::SyncLog;
+use crate::AppState;
+
+/// One API key's own city set, layered over the global database
+///
+/// Set per key in `--api-keys-file`: `cities` limits which global cities the
+/// key sees, and `cities_file` adds cities or replaces global ones for that
+/// key only. Admin edits and reloads of the global database show through.
+#[derive(Debug)]
+pub struct TenantCities {
+    /// Lowercase names of the global cities the tenant sees (all when unset)
+    only: Option<HashSet<String>>,
+    /// The tenant's own cities
+    overrides: Option<CityDatabase>,
+    /// The layered store for the latest global database
+    view: Mutex<Option<(Arc<CityDatabase>, Arc<CityStore>)>>,
+    /// Changes to the tenant's cities, for `GET /sync`
+    pub sync: Arc<SyncLog>,
+    /// `/stats` responses for the tenant's cities, apart from the global
+    /// ones so neither evicts the other
+    pub stats_cache: Arc<StatsCache>,
+}
+
+impl TenantCities {
+    pub fn new(only: Option<Vec<String>>, overrides: Option<CityDatabase>) -> Self {
+        TenantCities {
+            only: only.map(|cities| {
+                cities
+                    .iter()
+                    .map(|city| city.trim().to_lowercase())
+                    .collect()
+            }),
+            overrides,
+            view: Mutex::new(None),
+            sync: Arc::new(SyncLog::new(Utc::now())),
+            stats_cache: Arc::new(StatsCache::new()),
+        }
+    }
+
+    /// The tenant's cities over `global`, rebuilt only when `global` changes
+    pub fn store(&self, global: &Arc<CityDatabase>) -> Arc<CityStore> {
+        let mut view = self.view.lock().unwrap_or_else(|e| e.into_inner());
+        match &*view {
+            Some((database, store)) if Arc::ptr_eq(database, global) => store.clone(),
+            _ => {
+                let layered = global.layered(self.only.as_ref(), self.overrides.as_ref());
+                let store = Arc::new(CityStore::new(layered));
+                *view = Some((global.clone(), store.clone()));
+                store
+            }
+        }
+    }
+
+    /// Whether the tenant sees the global data for `city`, e.g. in the
+    /// history, which is only recorded for global cities
+    pub fn shares(&self, city: &str) -> bool {
+        let listed = self.only.as_ref().is_none_or(|only| only.contains(city));
+        let replaced = self
+            .overrides
+            .as_ref()
+            .is_some_and(|overrides| overrides.get_any(city).is_some());
+        listed && !replaced
+    }
+
+    /// Names in `cities` that are not in `global`, sorted
+    pub fn unknown_cities(&self, global: &CityDatabase) -> Vec<&str> {
+        let mut unknown: Vec<&str> = self
+            .only
+            .iter()
+            .flatten()
+            .filter(|city| global.get_any(city).is_none())
+            .map(String::as_str)
+            .collect();
+        unknown.sort_unstable();
+        unknown
+    }
+}
+
+/// [`AppState`] scoped to the calling API key's cities
+///
+/// Handlers serving city data extract this instead of `State<AppState>`, so
+/// every lookup (including the `AppState` helpers) stays within the tenant's
+/// cities. Without a tenant it is the global state.
+pub struct Scoped(pub AppState);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Scoped {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let tenant = parts
+            .extensions
+            .get::<Caller>()
+            .and_then(|caller| caller.tenant.clone());
+        Ok(Scoped(match tenant {
+            Some(tenant) => state.for_tenant(tenant),
+            None => state.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_view_is_layered_over_global_cities() {
+        let global = Arc::new(CityDatabase::builtin());
+        let mut oslo = global.get("oslo").unwrap().clone();
+        oslo.temperature = -30;
+        let mut atlantis = oslo.clone();
+        atlantis.name = "Atlantis".to_string();
+        let overrides = CityDatabase::from_records(vec![oslo, atlantis]).unwrap();
+
+        let tenant = TenantCities::new(
+            Some(vec![
+                "Oslo".to_string(),
+                "paris".to_string(),
+                "nowhere".to_string(),
+            ]),
+            Some(overrides),
+        );
+        let store = tenant.store(&global);
+        let database = store.current();
+        let mut names: Vec<&str> = database.names().collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["atlantis", "oslo", "paris"]);
+        assert_eq!(database.get("oslo").unwrap().temperature, -30);
+        assert!(Arc::ptr_eq(&store, &tenant.store(&global)));
+
+        assert!(tenant.shares("paris"));
+        assert!(!tenant.shares("oslo"));
+        assert!(!tenant.shares("rome"));
+        assert_eq!(tenant.unknown_cities(&global), vec!["nowhere"]);
+    }
+
+    #[test]
+    fn test_tenant_and_global_stats_are_cached_apart() {
+        use crate::config::ServerConfig;
+        use crate::trace::CacheUse;
+        use clap::Parser;
+
+        let config = ServerConfig::parse_from(["server"]);
+        let global = AppState::new(
+            config,
+            Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let tenant = global.for_tenant(Arc::new(TenantCities::new(
+            Some(vec!["oslo".to_string()]),
+            None,
+        )));
+        let cache_use = |state: &AppState| crate::compute_stats_traced(state, "name").1;
+
+        assert_eq!(cache_use(&global), CacheUse::Miss);
+        assert_eq!(cache_use(&tenant), CacheUse::Miss);
+        for _ in 0..3 {
+            assert_eq!(cache_use(&global), CacheUse::Hit);
+            assert_eq!(cache_use(&tenant), CacheUse::Hit);
+        }
+        assert_eq!(
+            crate::compute_stats_traced(&tenant, "name").0.total_cities,
+            1
+        );
+    }
+}
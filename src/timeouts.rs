@@ -0,0 +1,102 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{RouteTimeout, ServerConfig};
+use crate::ErrorResponse;
+
+/// Timeouts applied when no `--route-timeout` overrides them
+const DEFAULT_ROUTE_TIMEOUTS: &[(&str, Duration)] = &[
+    ("/cities", Duration::from_secs(2)),
+    ("/weather", Duration::from_secs(10)),
+];
+
+/// How long each route may take before the request is abandoned
+#[derive(Debug, Clone)]
+pub struct TimeoutPolicy {
+    default: Duration,
+    routes: HashMap<String, Duration>,
+}
+
+impl TimeoutPolicy {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self::new(config.request_timeout, &config.route_timeouts)
+    }
+
+    fn new(default: Duration, overrides: &[RouteTimeout]) -> Self {
+        let mut routes: HashMap<String, Duration> = DEFAULT_ROUTE_TIMEOUTS
+            .iter()
+            .map(|(path, timeout)| (path.to_string(), *timeout))
+            .collect();
+        for route in overrides {
+            routes.insert(route.path.clone(), route.timeout);
+        }
+
+        TimeoutPolicy { default, routes }
+    }
+
+    /// Timeout for a route pattern such as `/weather` or `/admin/cities/:name`
+    pub fn timeout_for(&self, route: Option<&str>) -> Duration {
+        route
+            .and_then(|route| self.routes.get(route))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Middleware abandoning requests that exceed their route's timeout with a 504
+pub async fn enforce(
+    State(policy): State<Arc<TimeoutPolicy>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let timeout = policy.timeout_for(route.as_deref());
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            let route = route.as_deref().unwrap_or("request");
+            println!("⏱️  {} timed out after {:?}", route, timeout);
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                Json(ErrorResponse {
+                    error: format!("{} timed out after {:?}", route, timeout),
+                    code: 504,
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_win_over_defaults() {
+        let policy = TimeoutPolicy::new(
+            Duration::from_secs(30),
+            &[RouteTimeout {
+                path: "/weather".to_string(),
+                timeout: Duration::from_secs(3),
+            }],
+        );
+
+        assert_eq!(policy.timeout_for(Some("/weather")), Duration::from_secs(3));
+        assert_eq!(policy.timeout_for(Some("/cities")), Duration::from_secs(2));
+        assert_eq!(policy.timeout_for(Some("/stats")), Duration::from_secs(30));
+        assert_eq!(policy.timeout_for(None), Duration::from_secs(30));
+    }
+}
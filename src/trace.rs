@@ -0,0 +1,107 @@
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::admin::has_admin_token;
+use crate::traceparent::{TraceParent, TRACEPARENT};
+use crate::{AppState, ErrorResponse, Source};
+
+/// Response header with the server's span (W3C Trace Context Level 2)
+const TRACERESPONSE: &str = "traceresponse";
+
+/// How a lookup was answered, attached to responses with `?debug=true`
+///
+/// Only for admins: it reveals cache state and timings.
+#[derive(Debug, Default, Serialize)]
+pub struct Trace {
+    /// One entry per requested city, in request order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cities: Vec<CityTrace>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats_cache: Option<CacheUse>,
+    /// Time spent in the handler so far
+    pub elapsed_us: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CityTrace {
+    pub city: String,
+    /// Whether the city is in the database (otherwise the fallback values are used)
+    pub found: bool,
+    pub source: Source,
+    pub elapsed_us: u64,
+}
+
+/// What the `/stats` cache did for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheUse {
+    /// Served a stored response
+    Hit,
+    /// Computed and stored a response
+    Miss,
+    /// Not used: simulated readings change on every call
+    Bypassed,
+}
+
+impl Trace {
+    /// Set `elapsed_us` to the time since `started`
+    pub fn finish(mut self, started: Instant) -> Self {
+        self.elapsed_us = micros_since(started);
+        self
+    }
+}
+
+pub fn micros_since(started: Instant) -> u64 {
+    started.elapsed().as_micros() as u64
+}
+
+/// Middleware joining the caller's distributed trace
+///
+/// The request gets a span of its own: a child of the `traceparent` span
+/// when the caller sent a valid one (such as the bundled client), otherwise
+/// the root of a new trace. Handlers and the request log read it from the
+/// request extensions, and it is returned in `traceresponse`.
+pub async fn join(mut request: Request, next: Next) -> Response {
+    let span = request
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceParent::parse)
+        .map_or_else(TraceParent::root, |parent| parent.child());
+    request.extensions_mut().insert(span);
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&span.to_string()) {
+        response.headers_mut().insert(TRACERESPONSE, value);
+    }
+    response
+}
+
+/// Whether to trace the request: false without `?debug=true`, 403 when
+/// tracing is asked for without the admin token
+pub fn requested(
+    state: &AppState,
+    headers: &HeaderMap,
+    debug: bool,
+) -> Result<bool, (StatusCode, Json<ErrorResponse>)> {
+    if !debug {
+        return Ok(false);
+    }
+    if !has_admin_token(state, headers) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "debug=true requires the admin token".to_string(),
+                code: 403,
+            }),
+        ));
+    }
+    Ok(true)
+}
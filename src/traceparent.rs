@@ -0,0 +1,137 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header carrying the caller's trace context (W3C Trace Context)
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Position of one span in a distributed trace, as sent in `traceparent`
+///
+/// Shared by the client and the server: the client sends its span, and the
+/// server continues the same trace with a child span of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParent {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub sampled: bool,
+}
+
+impl TraceParent {
+    /// Start a new, sampled trace
+    pub fn root() -> Self {
+        let (trace_id, span_id) = random_ids();
+        TraceParent {
+            trace_id,
+            span_id,
+            sampled: true,
+        }
+    }
+
+    /// A new span in the same trace, whose parent is this one
+    pub fn child(&self) -> Self {
+        TraceParent {
+            span_id: random_ids().1,
+            ..*self
+        }
+    }
+
+    /// Parse a `traceparent` header value
+    ///
+    /// Versions after `00` may append fields, which are ignored. Returns
+    /// `None` for anything malformed, so the caller starts a new trace.
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut fields = header.trim().split('-');
+        let version = fields.next().filter(|v| is_hex(v, 2) && *v != "ff")?;
+        let trace_id = fields.next().filter(|id| is_hex(id, 32))?;
+        let span_id = fields.next().filter(|id| is_hex(id, 16))?;
+        let flags = fields.next().filter(|flags| is_hex(flags, 2))?;
+        if version == "00" && fields.next().is_some() {
+            return None;
+        }
+
+        let trace_id = u128::from_str_radix(trace_id, 16)
+            .ok()
+            .filter(|id| *id != 0)?;
+        let span_id = u64::from_str_radix(span_id, 16)
+            .ok()
+            .filter(|id| *id != 0)?;
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(TraceParent {
+            trace_id,
+            span_id,
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+}
+
+/// The `traceparent` header value
+impl fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.sampled as u8
+        )
+    }
+}
+
+fn is_hex(field: &str, len: usize) -> bool {
+    field.len() == len
+        && field
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Random, non-zero trace and span ids
+fn random_ids() -> (u128, u64) {
+    let mut bytes = [0u8; 24];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        // Still distinct per call, if predictable
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        bytes[..16].copy_from_slice(&nanos.to_le_bytes());
+        bytes[16..].copy_from_slice(&(nanos as u64 ^ std::process::id() as u64).to_be_bytes());
+    }
+    let trace_id = u128::from_le_bytes(bytes[..16].try_into().unwrap_or_default());
+    let span_id = u64::from_le_bytes(bytes[16..].try_into().unwrap_or_default());
+    (trace_id.max(1), span_id.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_continue_a_trace() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let parent = TraceParent::parse(header).unwrap();
+        assert_eq!(parent.to_string(), header);
+        assert_eq!(parent.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(parent.sampled);
+
+        let child = parent.child();
+        assert_eq!(child.trace_id, parent.trace_id);
+        assert_ne!(child.span_id, parent.span_id);
+
+        // Later versions may add fields; version 00 may not
+        assert!(TraceParent::parse(&format!("cc{}-extra", &header[2..])).is_some());
+        assert!(TraceParent::parse(&format!("{}-extra", header)).is_none());
+        for invalid in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert!(TraceParent::parse(invalid).is_none(), "{}", invalid);
+        }
+
+        let root = TraceParent::root();
+        assert_eq!(TraceParent::parse(&root.to_string()), Some(root));
+    }
+}
@@ -0,0 +1,115 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::{compute_stats, AppState};
+
+/// Seconds a probe is asked to wait (via `Retry-After`) while warming up
+const RETRY_AFTER_SECS: u64 = 1;
+/// Every canonical `/stats` sort order (see `sort_order` in server.rs)
+const SORT_ORDERS: &[&str] = &["none", "name", "temperature", "humidity", "wind"];
+
+/// Whether the server takes traffic, as reported by `GET /ready`
+#[derive(Debug)]
+pub struct Readiness {
+    ready: AtomicBool,
+}
+
+impl Readiness {
+    pub fn new(ready: bool) -> Self {
+        Readiness {
+            ready: AtomicBool::new(ready),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    fn set_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+}
+
+/// Fill the caches in the background, then report ready
+///
+/// Until then `GET /ready` answers 503, so a load balancer holds traffic back
+/// instead of sending every first request to a cold server.
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let primed = {
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || prime(&state)).await
+        };
+        match primed {
+            Ok(count) => println!(
+                "🔥 Warm-up done in {:?}: {} /stats responses precomputed",
+                started.elapsed(),
+                count
+            ),
+            Err(e) => println!("⚠️  Warm-up failed: {} (serving cold)", e),
+        }
+        state.readiness.set_ready();
+    });
+}
+
+/// Precompute `/stats` for every sort order; returns how many were computed
+///
+/// Simulated readings are not cached, so there is nothing to prime then.
+fn prime(state: &AppState) -> usize {
+    if state.simulator.is_some() {
+        return 0;
+    }
+    for sort in SORT_ORDERS {
+        compute_stats(state, sort);
+    }
+    SORT_ORDERS.len()
+}
+
+/// Readiness probe: 200 once the warm-up (if any) has finished, 503 before
+pub async fn ready(State(state): State<AppState>) -> impl IntoResponse {
+    if state.readiness.is_ready() {
+        (
+            StatusCode::OK,
+            Json(serde_json::json!({ "status": "ready" })),
+        )
+            .into_response()
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+            Json(serde_json::json!({ "status": "warming_up" })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cities::{CityDatabase, CityStore};
+    use crate::config::ServerConfig;
+    use clap::Parser;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_prime_fills_the_stats_cache() {
+        let config = ServerConfig::parse_from(["server", "--warm-up"]);
+        let cities = Arc::new(CityStore::new(CityDatabase::builtin()));
+        let state = AppState::new(config, cities, None);
+        assert!(!state.readiness.is_ready());
+
+        assert_eq!(prime(&state), SORT_ORDERS.len());
+        let database = state.cities.current();
+        let cached = state
+            .stats_cache
+            .get_or_compute(&database, "wind", || panic!("should be cached"));
+        assert_eq!(cached.total_cities, database.len());
+    }
+}
@@ -0,0 +1,272 @@
+use chrono::{DateTime, Utc};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{StatusCode, Url};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use crate::signing::{SigningKeys, SIGNATURE_HEADER};
+
+/// Give up on a webhook that does not answer within this time
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Outcome of the latest attempt to deliver to a webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub at: DateTime<Utc>,
+    /// HTTP status returned by the webhook
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Delivery {
+    pub fn new(at: DateTime<Utc>, result: &Result<StatusCode, reqwest::Error>) -> Self {
+        match result {
+            Ok(status) => Delivery {
+                at,
+                status: Some(status.as_u16()),
+                error: None,
+            },
+            Err(e) => Delivery {
+                at,
+                status: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// A client for posting to webhooks, which gives up on slow ones
+pub fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().timeout(TIMEOUT)
+}
+
+/// Post `payload` as JSON to `url`, signed with the newest key if there is one
+pub async fn post_signed(
+    signing: &SigningKeys,
+    client: &reqwest::Client,
+    url: &str,
+    payload: &serde_json::Value,
+    at: DateTime<Utc>,
+) -> Result<StatusCode, reqwest::Error> {
+    let body = serde_json::to_vec(payload).unwrap_or_default();
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(signature) = signing.sign(&body, at) {
+        request = request.header(SIGNATURE_HEADER, signature);
+    }
+    Ok(request.body(body).send().await?.status())
+}
+
+/// Whether `ip` is reachable on the public internet: not loopback, private,
+/// link-local (such as the cloud metadata address 169.254.169.254),
+/// shared, documentation, multicast or otherwise reserved
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space (carrier-grade NAT), 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // Documentation, 2001:db8::/32
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+fn allowed(host: &str, allow_hosts: &[String]) -> bool {
+    allow_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+}
+
+/// A webhook URL a caller may have the server post to
+///
+/// It must be http or https, and its host public unless listed in
+/// `allow_hosts` (`--webhook-allow-host`). Host names are only checked for
+/// `localhost` here; what they resolve to is checked on every delivery by
+/// clients built with [`public_only`].
+pub fn check_url(url: &str, allow_hosts: &[String]) -> Result<Url, String> {
+    let invalid = |reason: &str| format!("Invalid webhook URL '{}' ({})", url, reason);
+    let parsed = Url::parse(url).map_err(|_| invalid("expected an http or https URL"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid("expected an http or https URL"));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| invalid("expected an http or https URL"))?;
+    if allowed(host, allow_hosts) {
+        return Ok(parsed);
+    }
+    // IPv6 hosts come in brackets
+    let public = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => is_public(ip),
+        Err(_) => {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            host != "localhost" && !host.ends_with(".localhost")
+        }
+    };
+    if !public {
+        return Err(invalid("not a public address"));
+    }
+    Ok(parsed)
+}
+
+/// Resolves host names to their public addresses only, so a name pointing at
+/// an internal address cannot be used to reach it
+struct PublicResolver {
+    allow_hosts: Vec<String>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let unrestricted = allowed(&host, &self.allow_hosts);
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|address| unrestricted || is_public(address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// `builder` limited to public addresses and the hosts in `allow_hosts`, for
+/// posting to URLs that API-key holders supply
+///
+/// Redirects are not followed, as they could lead anywhere.
+pub fn public_only(
+    builder: reqwest::ClientBuilder,
+    allow_hosts: &[String],
+) -> reqwest::ClientBuilder {
+    builder
+        .dns_resolver(Arc::new(PublicResolver {
+            allow_hosts: allow_hosts.to_vec(),
+        }))
+        .redirect(reqwest::redirect::Policy::none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_public_hosts_are_accepted() {
+        let none: &[String] = &[];
+        for url in [
+            "https://hooks.example.com/digest",
+            "http://203.0.113.1.nip.io/x",
+            "https://8.8.8.8/hook",
+            "https://[2606:4700::1111]/hook",
+        ] {
+            assert!(check_url(url, none).is_ok(), "{}", url);
+        }
+        for url in [
+            "ftp://example.com/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:8080/",
+            "http://2130706433/",
+            "http://10.1.2.3/",
+            "http://192.168.1.1/",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://localhost:3000/",
+            "http://api.localhost/",
+        ] {
+            assert!(check_url(url, none).is_err(), "{}", url);
+        }
+
+        let allow = ["LOCALHOST".to_string(), "10.1.2.3".to_string()];
+        assert!(check_url("http://localhost:9000/hook", &allow).is_ok());
+        assert!(check_url("http://10.1.2.3/hook", &allow).is_ok());
+        assert!(check_url("http://10.1.2.4/hook", &allow).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_are_signed_json() {
+        use axum::http::HeaderMap;
+
+        let app = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(|headers: HeaderMap, body: String| async move {
+                let signed = headers.contains_key(SIGNATURE_HEADER);
+                let json = headers[reqwest::header::CONTENT_TYPE] == "application/json";
+                match (signed, json, body.as_str()) {
+                    (true, true, r#"{"ok":true}"#) => StatusCode::ACCEPTED,
+                    _ => StatusCode::BAD_REQUEST,
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = client_builder().build().unwrap();
+        let signing = SigningKeys::new(Some("secret"));
+        let payload = serde_json::json!({"ok": true});
+        let result = post_signed(&signing, &client, &url, &payload, Utc::now()).await;
+        assert_eq!(result.as_ref().unwrap(), &StatusCode::ACCEPTED);
+        assert_eq!(Delivery::new(Utc::now(), &result).status, Some(202));
+
+        let closed = post_signed(
+            &signing,
+            &client,
+            "http://127.0.0.1:9/",
+            &payload,
+            Utc::now(),
+        )
+        .await;
+        assert!(Delivery::new(Utc::now(), &closed).error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_names_resolve_to_public_addresses_only() {
+        let resolve = |allow_hosts: Vec<String>| {
+            PublicResolver { allow_hosts }.resolve("localhost".parse().unwrap())
+        };
+        assert!(resolve(Vec::new()).await.is_err());
+        let addresses = resolve(vec!["localhost".to_string()]).await.unwrap();
+        assert!(addresses
+            .into_iter()
+            .all(|address| address.ip().is_loopback()));
+    }
+}
@@ -0,0 +1,497 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::UpdateEvent;
+use crate::store::SavedWebhook;
+use crate::webhook::{self, Delivery};
+use crate::{error_response, AppState, ErrorResponse, WeatherData};
+
+/// Most cities one webhook can watch
+pub const MAX_WEBHOOK_CITIES: usize = 100;
+/// Most webhooks registered at once
+const MAX_WEBHOOKS: usize = 50;
+
+type WebhookError = (StatusCode, Json<ErrorResponse>);
+
+/// A URL told whenever the data of one of its cities changes
+#[derive(Debug, Clone, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub cities: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub deliveries: u64,
+    pub last_delivery: Option<Delivery>,
+    /// What was last sent per city, to skip events that changed nothing
+    #[serde(skip)]
+    sent: HashMap<String, Option<String>>,
+}
+
+/// One change to post
+#[derive(Debug, Clone, PartialEq)]
+struct Notification {
+    webhook: String,
+    url: String,
+    city: String,
+    weather: Option<serde_json::Value>,
+    /// The anomaly or alert that triggered the notification
+    finding: Option<serde_json::Value>,
+}
+
+/// The reading without `observed_at`, which moves on every sample even when
+/// nothing else does; `None` for a city that no longer exists
+fn fingerprint(weather: Option<&WeatherData>) -> Option<String> {
+    let mut value = serde_json::to_value(weather?).ok()?;
+    value.as_object_mut()?.remove("observed_at");
+    Some(value.to_string())
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    webhooks: Vec<Webhook>,
+    /// Webhooks registered so far, for ids that are never reused
+    issued: usize,
+}
+
+/// Per-city change webhooks, registered through the admin API
+///
+/// Saved through [`crate::store`], so they survive restarts with `--data-file`.
+#[derive(Debug, Default)]
+pub struct Webhooks {
+    registry: Mutex<Registry>,
+}
+
+impl Webhooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> Vec<Webhook> {
+        let registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        registry.webhooks.clone()
+    }
+
+    /// Every webhook without its delivery state, for saving
+    pub fn saved(&self) -> Vec<SavedWebhook> {
+        let registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        registry
+            .webhooks
+            .iter()
+            .map(|webhook| SavedWebhook {
+                id: webhook.id.clone(),
+                url: webhook.url.clone(),
+                cities: webhook.cities.clone(),
+                created_at: webhook.created_at,
+            })
+            .collect()
+    }
+
+    /// Put back webhooks saved before a restart; `current` gives each
+    /// city's reading now, which counts as already sent
+    pub fn restore(&self, saved: Vec<SavedWebhook>, current: impl Fn(&str) -> Option<WeatherData>) {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        // Ids stay unique: new webhooks are numbered after the restored ones
+        registry.issued = saved
+            .iter()
+            .filter_map(|webhook| webhook.id.strip_prefix("wh")?.parse().ok())
+            .max()
+            .unwrap_or(0);
+        registry.webhooks = saved
+            .into_iter()
+            .map(|saved| Webhook {
+                sent: saved
+                    .cities
+                    .iter()
+                    .map(|city| (city.clone(), fingerprint(current(city).as_ref())))
+                    .collect(),
+                id: saved.id,
+                url: saved.url,
+                cities: saved.cities,
+                created_at: saved.created_at,
+                deliveries: 0,
+                last_delivery: None,
+            })
+            .collect();
+    }
+
+    /// Register a webhook; `current` is each city's reading now, which is
+    /// not sent
+    fn add(
+        &self,
+        url: String,
+        current: Vec<(String, Option<&WeatherData>)>,
+        now: DateTime<Utc>,
+    ) -> Result<Webhook, String> {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        if registry.webhooks.len() >= MAX_WEBHOOKS {
+            return Err(format!(
+                "At most {} webhooks can be registered; delete one first",
+                MAX_WEBHOOKS
+            ));
+        }
+        registry.issued += 1;
+        let webhook = Webhook {
+            id: format!("wh{}", registry.issued),
+            url,
+            cities: current.iter().map(|(city, _)| city.clone()).collect(),
+            created_at: now,
+            deliveries: 0,
+            last_delivery: None,
+            sent: current
+                .into_iter()
+                .map(|(city, weather)| (city, fingerprint(weather)))
+                .collect(),
+        };
+        registry.webhooks.push(webhook.clone());
+        Ok(webhook)
+    }
+
+    fn remove(&self, id: &str) -> bool {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let before = registry.webhooks.len();
+        registry.webhooks.retain(|webhook| webhook.id != id);
+        registry.webhooks.len() < before
+    }
+
+    /// Cities watched by any webhook
+    fn watched(&self) -> Vec<String> {
+        let registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cities: Vec<String> = registry
+            .webhooks
+            .iter()
+            .flat_map(|webhook| webhook.cities.iter().cloned())
+            .collect();
+        cities.sort();
+        cities.dedup();
+        cities
+    }
+
+    /// Notifications for the readings that differ from what each webhook
+    /// was last sent, which are remembered as sent
+    ///
+    /// With a `finding` (an anomaly or alert about the cities), every
+    /// watching webhook is notified, changed or not.
+    fn changes(
+        &self,
+        readings: &[(String, Option<WeatherData>)],
+        finding: Option<&serde_json::Value>,
+    ) -> Vec<Notification> {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let mut notifications = Vec::new();
+        for webhook in registry.webhooks.iter_mut() {
+            for (city, weather) in readings {
+                let Some(sent) = webhook.sent.get_mut(city) else {
+                    continue;
+                };
+                let current = fingerprint(weather.as_ref());
+                if *sent == current && finding.is_none() {
+                    continue;
+                }
+                *sent = current;
+                notifications.push(Notification {
+                    webhook: webhook.id.clone(),
+                    url: webhook.url.clone(),
+                    city: city.clone(),
+                    weather: weather
+                        .as_ref()
+                        .and_then(|weather| serde_json::to_value(weather).ok()),
+                    finding: finding.cloned(),
+                });
+            }
+        }
+        notifications
+    }
+
+    fn record_delivery(&self, id: &str, delivery: Delivery) {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(webhook) = registry.webhooks.iter_mut().find(|w| w.id == id) {
+            webhook.deliveries += 1;
+            webhook.last_delivery = Some(delivery);
+        }
+    }
+}
+
+/// Post one change to its webhook
+async fn deliver(
+    state: &AppState,
+    client: &reqwest::Client,
+    notification: &Notification,
+    reason: &str,
+) -> Delivery {
+    let now = Utc::now();
+    // Checked again, as a restored webhook may predate `--webhook-allow-host` changes
+    if let Err(e) = webhook::check_url(&notification.url, &state.config.webhook_allow_hosts) {
+        println!("⚠️  Not notifying webhook {}: {}", notification.webhook, e);
+        return Delivery {
+            at: now,
+            status: None,
+            error: Some(e),
+        };
+    }
+    let mut payload = serde_json::json!({
+        "webhook": notification.webhook,
+        "city": notification.city,
+        "reason": reason,
+        "at": now,
+        "weather": notification.weather
+    });
+    if let Some(finding) = &notification.finding {
+        payload["finding"] = finding.clone();
+    }
+
+    let result =
+        webhook::post_signed(&state.signing, client, &notification.url, &payload, now).await;
+    match &result {
+        Ok(status) if status.is_success() => {}
+        Ok(status) => println!(
+            "⚠️  Webhook {} answered {} for {}",
+            notification.webhook, status, notification.city
+        ),
+        Err(e) => println!(
+            "⚠️  Cannot notify webhook {} of {}: {}",
+            notification.webhook, notification.city, e
+        ),
+    }
+    Delivery::new(now, &result)
+}
+
+/// The anomaly or alert an event reports, sent along with the reading
+fn finding(event: &UpdateEvent) -> Option<serde_json::Value> {
+    match event {
+        UpdateEvent::AnomalyDetected(anomaly) => serde_json::to_value(anomaly).ok(),
+        UpdateEvent::AlertFired(alert) => serde_json::to_value(alert).ok(),
+        _ => None,
+    }
+}
+
+/// Post city changes to the webhooks watching them, as update events arrive
+///
+/// Events name the cities that may have changed (every watched city for a
+/// reload, a restore or a history sample); only readings that actually
+/// differ from the last one sent are posted. Anomalies and alerts are
+/// always posted, with the finding next to the reading. A failed delivery
+/// is not retried: the webhook gets the next change as usual.
+pub fn spawn_dispatcher(state: AppState) {
+    tokio::spawn(async move {
+        // Only public hosts are reached, as for digests
+        let client =
+            webhook::public_only(webhook::client_builder(), &state.config.webhook_allow_hosts)
+                .build()
+                .unwrap_or_default();
+        let mut receiver = state.events.subscribe();
+        loop {
+            let (cities, reason, at, finding) = match receiver.recv().await {
+                Ok(event) => {
+                    let cities = match event.city() {
+                        Some(city) => vec![city.to_string()],
+                        None => state.webhooks.watched(),
+                    };
+                    (cities, event.kind(), event.at(), finding(&event))
+                }
+                // Compare everything instead of replaying what was missed
+                Err(RecvError::Lagged(_)) => (state.webhooks.watched(), "lagged", Utc::now(), None),
+                Err(RecvError::Closed) => return,
+            };
+
+            let database = state.cities.current();
+            let readings: Vec<(String, Option<WeatherData>)> = cities
+                .into_iter()
+                .map(|city| {
+                    let weather = database
+                        .get(&city)
+                        .map(|_| state.weather_or_default(&city, at));
+                    (city, weather)
+                })
+                .collect();
+            for notification in state.webhooks.changes(&readings, finding.as_ref()) {
+                let state = state.clone();
+                let client = client.clone();
+                tokio::spawn(async move {
+                    let delivery = deliver(&state, &client, &notification, reason).await;
+                    state
+                        .webhooks
+                        .record_delivery(&notification.webhook, delivery);
+                });
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookRequest {
+    url: String,
+    cities: Vec<String>,
+}
+
+/// List the registered webhooks
+pub async fn list_webhooks(State(state): State<AppState>) -> Json<serde_json::Value> {
+    println!("🛠️  Admin: webhooks");
+
+    let webhooks = state.webhooks.list();
+    Json(serde_json::json!({
+        "count": webhooks.len(),
+        "webhooks": webhooks
+    }))
+}
+
+/// Register a webhook for changes to some cities
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    Json(payload): Json<WebhookRequest>,
+) -> Result<(StatusCode, Json<Webhook>), WebhookError> {
+    let bad_request = |message: String| error_response(StatusCode::BAD_REQUEST, message);
+
+    webhook::check_url(&payload.url, &state.config.webhook_allow_hosts).map_err(bad_request)?;
+    if payload.cities.is_empty() || payload.cities.len() > MAX_WEBHOOK_CITIES {
+        return Err(bad_request(format!(
+            "A webhook needs 1 to {} cities, you sent {}",
+            MAX_WEBHOOK_CITIES,
+            payload.cities.len()
+        )));
+    }
+
+    let now = Utc::now();
+    let database = state.cities.current();
+    let mut current: Vec<(String, Option<WeatherData>)> = Vec::new();
+    for city in &payload.cities {
+        let city = city.trim().to_lowercase();
+        if database.get(&city).is_none() {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                format!("Unknown city '{}'", city),
+            ));
+        }
+        if !current.iter().any(|(known, _)| *known == city) {
+            let weather = state.weather_or_default(&city, now);
+            current.push((city, Some(weather)));
+        }
+    }
+
+    let current = current
+        .iter()
+        .map(|(city, weather)| (city.clone(), weather.as_ref()))
+        .collect();
+    let webhook = state
+        .webhooks
+        .add(payload.url, current, now)
+        .map_err(|message| error_response(StatusCode::CONFLICT, message))?;
+    println!(
+        "🪝 Admin: webhook {} watches {} cities",
+        webhook.id,
+        webhook.cities.len()
+    );
+    state.persistence.save(&state).await;
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+/// Remove a webhook
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, WebhookError> {
+    if !state.webhooks.remove(&id) {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("Unknown webhook '{}'", id),
+        ));
+    }
+    println!("🪝 Admin: removed webhook {}", id);
+    state.persistence.save(&state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(city: &'static str, temperature: i32, at: &str) -> WeatherData {
+        WeatherData {
+            temperature,
+            observed_at: at.parse().unwrap(),
+            ..WeatherData::unknown(city)
+        }
+    }
+
+    #[test]
+    fn test_only_changed_cities_are_sent() {
+        let webhooks = Webhooks::new();
+        let oslo = reading("oslo", 5, "2026-10-16T10:00:00Z");
+        let paris = reading("paris", 12, "2026-10-16T10:00:00Z");
+        webhooks
+            .add(
+                "http://localhost/hook".to_string(),
+                vec![
+                    ("oslo".to_string(), Some(&oslo)),
+                    ("paris".to_string(), Some(&paris)),
+                ],
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(webhooks.watched(), ["oslo", "paris"]);
+
+        // A new sample of the same values is not a change
+        let later = "2026-10-16T10:05:00Z";
+        let readings = vec![
+            ("oslo".to_string(), Some(reading("oslo", 5, later))),
+            ("paris".to_string(), Some(reading("paris", 13, later))),
+            ("rome".to_string(), Some(reading("rome", 20, later))),
+        ];
+        let sent = webhooks.changes(&readings, None);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].city, "paris");
+        assert_eq!(sent[0].weather.as_ref().unwrap()["temperature"], 13);
+        assert!(webhooks.changes(&readings, None).is_empty());
+
+        // An anomaly is sent even though the reading did not change
+        let finding = serde_json::json!({"metric": "temperature", "z_score": 4.2});
+        let sent = webhooks.changes(&readings[..1], Some(&finding));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].finding.as_ref(), Some(&finding));
+
+        // Deleting a city is a change too
+        let sent = webhooks.changes(&[("oslo".to_string(), None)], None);
+        assert_eq!(sent[0].weather, None);
+    }
+
+    #[tokio::test]
+    async fn test_only_public_urls_can_be_registered() {
+        use crate::cities::{CityDatabase, CityStore};
+        use clap::Parser;
+        use std::sync::Arc;
+
+        let state = AppState::new(
+            crate::config::ServerConfig::parse_from([
+                "server",
+                "--webhook-allow-host",
+                "cdn.internal",
+            ]),
+            Arc::new(CityStore::new(CityDatabase::builtin())),
+            None,
+        );
+        let register = |url: &str| {
+            let request = WebhookRequest {
+                url: url.to_string(),
+                cities: vec!["oslo".to_string()],
+            };
+            create_webhook(State(state.clone()), Json(request))
+        };
+        for url in [
+            "http://169.254.169.254/latest",
+            "http://localhost/hook",
+            "ftp://cdn.example.com/",
+        ] {
+            let (status, _) = register(url).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", url);
+        }
+        assert!(register("https://cdn.example.com/purge").await.is_ok());
+        assert!(register("http://cdn.internal/purge").await.is_ok());
+    }
+}